@@ -3,7 +3,7 @@ use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
 use quote::{format_ident, quote};
 use rust_format::Formatter;
-use syn::{parse_file, parse_macro_input, punctuated::Punctuated, ItemStruct, LitStr, Meta, Token, Ident, Type, parse_quote};
+use syn::{parse_file, parse_macro_input, punctuated::Punctuated, Attribute, Expr, ExprLit, ExprUnary, ItemEnum, ItemStruct, Lit, LitStr, Meta, MetaNameValue, Token, Ident, Type, UnOp, parse_quote};
 
 #[proc_macro_derive(UiMarker, attributes(ui))]
 pub fn marker(_input: TokenStream) -> TokenStream {
@@ -40,55 +40,32 @@ pub fn generate_ui_impl(input: TokenStream) -> TokenStream {
     
     let mut aggregation: Option<Ident> = None;
 
+    // Enums marked with UiMarker become dropdowns wherever they are used as a field
+    let mut enums = Vec::new();
+    for item in &whole_file.items {
+        if let syn::Item::Enum(item_enum) = item {
+            if has_derive(&item_enum.attrs, "UiMarker") {
+                impls.extend(dropdown_options(item_enum));
+                enums.push(item_enum.ident.clone());
+            }
+        }
+    }
+
     for item in whole_file.items {
         let item_struct = match item {
             syn::Item::Struct(item_struct) => item_struct,
             _ => continue,
         };
-        let struct_is_marked = item_struct
-            .attrs
-            .iter()
-            .find(|attr| {
-                if attr.path().is_ident("derive") {
-                    let mut has_ui_marker = false;
-                    let nested = attr
-                        .parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
-                        .unwrap();
-                    for meta in nested {
-                        has_ui_marker |= meta.path().is_ident("UiMarker")
-                    }
-                    has_ui_marker
-                } else {
-                    false
-                }
-            })
-            .is_some();
-        
-        let struct_is_aggregation = item_struct
-            .attrs
-            .iter()
-            .find(|attr| {
-                if attr.path().is_ident("derive") {
-                    let mut has_ui_marker = false;
-                    let nested = attr
-                        .parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
-                        .unwrap();
-                    for meta in nested {
-                        has_ui_marker |= meta.path().is_ident("UiAggregation")
-                    }
-                    has_ui_marker
-                } else {
-                    false
-                }
-            })
-            .is_some();
+        let struct_is_marked = has_derive(&item_struct.attrs, "UiMarker");
+
+        let struct_is_aggregation = has_derive(&item_struct.attrs, "UiAggregation");
 
         if struct_is_aggregation{
             aggregation = Some(item_struct.ident.clone());
         }
 
         if struct_is_marked {
-            let (the_impl, struct_name, ui_struct_name) = playground_ui(item_struct);
+            let (the_impl, struct_name, ui_struct_name) = playground_ui(item_struct, &enums);
             let snake_case = format!("{}", AsSnakeCase(struct_name.to_string()));
             let var_name = format_ident!("{}", snake_case);
             full_definition.extend(quote!(#var_name: #ui_struct_name,));
@@ -131,20 +108,101 @@ pub fn generate_ui_impl(input: TokenStream) -> TokenStream {
 }
 
 
-fn ui_element_by_type(ident: &Ident, title_case: &String, ty: &Type) -> (TokenStream2, TokenStream2){
+fn has_derive(attrs: &[Attribute], name: &str) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path().is_ident("derive")
+            && attr
+                .parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+                .unwrap()
+                .iter()
+                .any(|meta| meta.path().is_ident(name))
+    })
+}
+
+fn dropdown_options(input: &ItemEnum) -> TokenStream2 {
+    let enum_name = &input.ident;
+
+    let options = input.variants.iter().map(|variant| {
+        let variant_name = &variant.ident;
+        let title_case = format!("{}", AsTitleCase(variant_name.to_string()));
+        quote!((#enum_name::#variant_name, #title_case),)
+    });
+
+    quote!(
+        impl DropdownOptions for #enum_name{
+            fn options() -> &'static [(Self, &'static str)]{
+                &[#(#options)*]
+            }
+        }
+    )
+}
+
+/// Slider bounds from #[ui(min = .., max = ..)], for the types that use them
+struct Bounds{
+    min: f32,
+    max: f32,
+}
+
+impl Default for Bounds{
+    fn default() -> Self{
+        Self{
+            min: -100.,
+            max: 100.,
+        }
+    }
+}
+
+fn number_literal(expr: &Expr) -> f32{
+    match expr{
+        Expr::Lit(ExprLit{ lit: Lit::Int(int), .. }) => int.base10_parse::<i64>().unwrap() as f32,
+        Expr::Lit(ExprLit{ lit: Lit::Float(float), .. }) => float.base10_parse::<f32>().unwrap(),
+        Expr::Unary(ExprUnary{ op: UnOp::Neg(_), expr, .. }) => -number_literal(expr),
+        _ => panic!("The bounds in #[ui(..)] must be number literals"),
+    }
+}
+
+fn parse_bounds(attrs: &[Attribute]) -> Bounds{
+    let mut bounds = Bounds::default();
+    for attr in attrs {
+        if !attr.path().is_ident("ui") {
+            continue;
+        }
+        let name_values = attr
+            .parse_args_with(Punctuated::<MetaNameValue, Token![,]>::parse_terminated)
+            .expect("The attribute must be of the form #[ui(min = value1, max = value2)]");
+        for name_value in name_values{
+            if name_value.path.is_ident("min"){
+                bounds.min = number_literal(&name_value.value);
+            } else if name_value.path.is_ident("max"){
+                bounds.max = number_literal(&name_value.value);
+            } else {
+                panic!("Unrecognized key in #[ui(..)], expected min or max");
+            }
+        }
+    }
+    bounds
+}
+
+fn ui_element_by_type(ident: &Ident, title_case: &String, ty: &Type, bounds: &Bounds, enums: &[Ident]) -> (TokenStream2, TokenStream2){
     let float: Type = parse_quote!(f32);
     let int: Type = parse_quote!(i32);
+    let uint: Type = parse_quote!(u32);
     let glam_mat4: Type = parse_quote!(glam::Mat4);
     let mat4: Type = parse_quote!(Mat4);
+    let tone_curve: Type = parse_quote!(ToneCurve);
+    let hue_bands: Type = parse_quote!(HueBands);
 
     if ty == &float{
+        let Bounds{ min, max } = bounds;
+        let min_str = min.to_string();
+        let max_str = max.to_string();
         let def = quote!(#ident: BoundedSlider,);
         let new = quote!(#ident: BoundedSlider{
             name: #title_case.to_string(),
-            min: -100.,
-            min_str: (-100.).to_string(),
-            max: 100.,
-            max_str: (100.).to_string(),
+            min: #min,
+            min_str: #min_str.to_string(),
+            max: #max,
+            max_str: #max_str.to_string(),
         },);
 
         (def, new)
@@ -154,18 +212,43 @@ fn ui_element_by_type(ident: &Ident, title_case: &String, ty: &Type) -> (TokenSt
             name: #title_case,
         },);
 
+        (def, new)
+    } else if ty == &uint{
+        let def = quote!(#ident: IntDrag,);
+        let new = quote!(#ident: IntDrag{
+            name: #title_case,
+        },);
+
         (def, new)
     } else if ty == &mat4 || ty == &glam_mat4{
         let def = quote!(#ident: Mat4Slider,);
         let new = quote!(#ident: Mat4Slider::new(#title_case.to_string(), -1., 2., ids()),);
 
+        (def, new)
+    } else if ty == &tone_curve{
+        let def = quote!(#ident: CurveEditor,);
+        let new = quote!(#ident: CurveEditor::new(#title_case, ids()),);
+
+        (def, new)
+    } else if ty == &hue_bands{
+        let def = quote!(#ident: HueBandsEditor,);
+        let new = quote!(#ident: HueBandsEditor::new(#title_case, ids()),);
+
+        (def, new)
+    } else if enums.iter().any(|enum_name| {
+        let enum_ty: Type = parse_quote!(#enum_name);
+        ty == &enum_ty
+    }){
+        let def = quote!(#ident: EnumDropdown,);
+        let new = quote!(#ident: EnumDropdown::new(#title_case, ids()),);
+
         (def, new)
     } else {
         panic!("Unrecognized type in field of struct marked with UiMarker")
     }
 }
 
-fn playground_ui(input: ItemStruct) -> (TokenStream2, Ident, Ident) {
+fn playground_ui(input: ItemStruct, enums: &[Ident]) -> (TokenStream2, Ident, Ident) {
     let struct_name = input.ident;
     
     let mut definitions = TokenStream2::new();
@@ -173,15 +256,7 @@ fn playground_ui(input: ItemStruct) -> (TokenStream2, Ident, Ident) {
     let mut uis = TokenStream2::new();
 
     for field in input.fields.iter() {
-        for attr in &field.attrs {
-            if !attr.path().is_ident("playground") {
-                continue;
-            }
-            let _meta_list =  match &attr.meta{
-                syn::Meta::List(meta_list) => meta_list,
-                _ => panic!("The attribute must be of the form #[playground(name1 = value1, name2 = value2)]"),
-            };
-        }
+        let bounds = parse_bounds(&field.attrs);
 
         let ident = field
             .ident
@@ -189,7 +264,7 @@ fn playground_ui(input: ItemStruct) -> (TokenStream2, Ident, Ident) {
             .expect("The struct cannot be a tuple struct");
 
         let title_case = format!("{}", heck::AsTitleCase(ident.to_string()));
-        let (def, new) = ui_element_by_type(ident, &title_case, &field.ty);
+        let (def, new) = ui_element_by_type(ident, &title_case, &field.ty, &bounds, enums);
         
         definitions.extend(def);
         defaults.extend(new);
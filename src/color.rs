@@ -0,0 +1,271 @@
+//! Colour temperature and camera calibration maths for manual white balance,
+//! and the colour spaces and transfer functions of the output transform.
+//!
+//! Follows the DNG approach: a camera is described by two colour matrices
+//! measured under different illuminants, and the matrix for any other white
+//! is interpolated between them by inverse colour temperature.
+
+use glam::{Mat3, Vec2, Vec3};
+
+/// Below and above this the locus approximation in [`temperature_to_xy`] is
+/// not defined, temperatures are clamped to it.
+pub const TEMPERATURE_RANGE: (f32, f32) = (1667., 25000.);
+
+/// Tint is given in the DNG unit: 1 tint is 1/3000 of a uv step away from the
+/// Planckian locus.
+const TINT_SCALE: f32 = 3000.;
+
+/// CIE 1931 xy of standard illuminant D65.
+pub const D65: Vec2 = Vec2::new(0.3127, 0.329);
+
+/// Cone response matrix of the Bradford chromatic adaptation.
+const BRADFORD: [[f32; 3]; 3] = [
+    [0.8951, 0.2664, -0.1614],
+    [-0.7502, 1.7135, 0.0367],
+    [0.0389, -0.0685, 1.0296],
+];
+
+/// CIE XYZ to linear sRGB, which is the colour matrix of a camera that
+/// already records sRGB primaries.
+const XYZ_TO_SRGB: [[f32; 3]; 3] = [
+    [3.2404542, -1.5371385, -0.4985314],
+    [-0.969266, 1.8760108, 0.041556],
+    [0.0556434, -0.2040259, 1.0572252],
+];
+
+/// A pair of DNG `ColorMatrix` tags with the temperatures of their
+/// calibration illuminants. The matrices map CIE XYZ to camera RGB and are
+/// given row by row.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CameraCalibration {
+    pub temperature_1: f32,
+    pub color_matrix_1: [[f32; 3]; 3],
+    pub temperature_2: f32,
+    pub color_matrix_2: [[f32; 3]; 3],
+}
+
+/// A camera with sRGB primaries, standard illuminants A and D65.
+impl Default for CameraCalibration {
+    fn default() -> Self {
+        Self {
+            temperature_1: 2856.,
+            color_matrix_1: XYZ_TO_SRGB,
+            temperature_2: 6504.,
+            color_matrix_2: XYZ_TO_SRGB,
+        }
+    }
+}
+
+impl CameraCalibration {
+    /// The colour matrix for a white of the given temperature, interpolated
+    /// linearly in inverse temperature and held constant outside the two
+    /// calibration temperatures.
+    pub fn color_matrix(&self, temperature: f32) -> Mat3 {
+        let matrix_1 = row_major(self.color_matrix_1);
+        let matrix_2 = row_major(self.color_matrix_2);
+
+        let inverse_1 = 1. / self.temperature_1;
+        let inverse_2 = 1. / self.temperature_2;
+        if (inverse_1 - inverse_2).abs() < f32::EPSILON {
+            return matrix_1;
+        }
+
+        let weight = ((1. / temperature - inverse_2) / (inverse_1 - inverse_2)).clamp(0., 1.);
+        matrix_1 * weight + matrix_2 * (1. - weight)
+    }
+
+    /// Camera RGB of a neutral surface under the given white, with green at 1.
+    pub fn camera_neutral(&self, temperature: f32, tint: f32) -> Vec3 {
+        let xyz = xy_to_xyz(temperature_to_xy(temperature, tint));
+        let neutral = self.color_matrix(temperature) * xyz;
+        neutral / neutral.y
+    }
+
+    /// R, G and B gains that make a neutral surface under the given white
+    /// neutral in camera space.
+    pub fn white_balance_gains(&self, temperature: f32, tint: f32) -> [f32; 3] {
+        let neutral = self.camera_neutral(temperature, tint);
+        (Vec3::ONE / neutral).to_array()
+    }
+
+    /// White balanced camera RGB to CIE XYZ with the D65 white, for a scene
+    /// lit by `white` and the colour matrix of `temperature`. Camera RGB 1 goes
+    /// to D65 at Y = 1.
+    pub fn camera_to_xyz(&self, temperature: f32, white: Vec2) -> Mat3 {
+        let color_matrix = self.color_matrix(temperature);
+        let neutral = color_matrix * xy_to_xyz(white);
+        let balanced_to_xyz = color_matrix.inverse() * Mat3::from_diagonal(neutral);
+        bradford_adaptation(white, D65) * balanced_to_xyz
+    }
+}
+
+/// CIE 1931 xy of the primaries and the white of an RGB colour space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Chromaticities {
+    pub red: Vec2,
+    pub green: Vec2,
+    pub blue: Vec2,
+    pub white: Vec2,
+}
+
+impl Chromaticities {
+    /// sRGB and Rec.709.
+    pub const SRGB: Self = Self {
+        red: Vec2::new(0.64, 0.33),
+        green: Vec2::new(0.3, 0.6),
+        blue: Vec2::new(0.15, 0.06),
+        white: D65,
+    };
+    pub const DISPLAY_P3: Self = Self {
+        red: Vec2::new(0.68, 0.32),
+        green: Vec2::new(0.265, 0.69),
+        blue: Vec2::new(0.15, 0.06),
+        white: D65,
+    };
+    pub const REC2020: Self = Self {
+        red: Vec2::new(0.708, 0.292),
+        green: Vec2::new(0.17, 0.797),
+        blue: Vec2::new(0.131, 0.046),
+        white: D65,
+    };
+    /// The AP1 primaries of ACEScg, with the ACES white.
+    pub const ACES_AP1: Self = Self {
+        red: Vec2::new(0.713, 0.293),
+        green: Vec2::new(0.165, 0.83),
+        blue: Vec2::new(0.128, 0.044),
+        white: Vec2::new(0.32168, 0.33767),
+    };
+
+    /// Linear RGB to CIE XYZ, with RGB 1 going to the white at Y = 1.
+    pub fn rgb_to_xyz(&self) -> Mat3 {
+        let primaries = Mat3::from_cols(
+            xy_to_xyz(self.red),
+            xy_to_xyz(self.green),
+            xy_to_xyz(self.blue),
+        );
+        let scale = primaries.inverse() * xy_to_xyz(self.white);
+        primaries * Mat3::from_diagonal(scale)
+    }
+
+    /// CIE XYZ with the D65 white to linear RGB, adapting the white with
+    /// [`bradford_adaptation`] where the colour space has another one.
+    pub fn xyz_d65_to_rgb(&self) -> Mat3 {
+        self.rgb_to_xyz().inverse() * bradford_adaptation(D65, self.white)
+    }
+}
+
+/// Linear sRGB to CIE XYZ, the camera to XYZ matrix of a camera whose white
+/// balanced output is already linear sRGB.
+pub fn srgb_to_xyz() -> Mat3 {
+    Chromaticities::SRGB.rgb_to_xyz()
+}
+
+/// Chromatic adaptation of CIE XYZ from the white `from` to the white `to`.
+pub fn bradford_adaptation(from: Vec2, to: Vec2) -> Mat3 {
+    let bradford = row_major(BRADFORD);
+    let scale = (bradford * xy_to_xyz(to)) / (bradford * xy_to_xyz(from));
+    bradford.inverse() * Mat3::from_diagonal(scale) * bradford
+}
+
+/// The piecewise sRGB encoding of a linear value, negative values clip to 0.
+pub fn srgb_oetf(linear: f32) -> f32 {
+    let linear = linear.max(0.);
+    if linear <= 0.0031308 {
+        12.92 * linear
+    } else {
+        1.055 * linear.powf(1. / 2.4) - 0.055
+    }
+}
+
+/// SMPTE ST 2084 (PQ) encoding of an absolute luminance in cd/m².
+pub fn pq_oetf(luminance: f32) -> f32 {
+    const M1: f32 = 2610. / 16384.;
+    const M2: f32 = 2523. / 4096. * 128.;
+    const C1: f32 = 3424. / 4096.;
+    const C2: f32 = 2413. / 4096. * 32.;
+    const C3: f32 = 2392. / 4096. * 32.;
+
+    let y = (luminance / 10000.).clamp(0., 1.).powf(M1);
+    ((C1 + C2 * y) / (1. + C3 * y)).powf(M2)
+}
+
+/// The HLG OETF of BT.2100 for scene light normalised to 0..1.
+pub fn hlg_oetf(scene: f32) -> f32 {
+    const A: f32 = 0.17883277;
+    const B: f32 = 0.28466892;
+    const C: f32 = 0.5599107;
+
+    let scene = scene.clamp(0., 1.);
+    if scene <= 1. / 12. {
+        (3. * scene).sqrt()
+    } else {
+        A * (12. * scene - B).ln() + C
+    }
+}
+
+fn row_major(rows: [[f32; 3]; 3]) -> Mat3 {
+    Mat3::from_cols_array_2d(&rows).transpose()
+}
+
+/// CIE 1931 xy of the Planckian radiator at `temperature` kelvin, from the
+/// cubic approximation of Kim et al.
+pub fn planckian_xy(temperature: f32) -> Vec2 {
+    let t = temperature.clamp(TEMPERATURE_RANGE.0, TEMPERATURE_RANGE.1) as f64;
+    let (t2, t3) = (t * t, t * t * t);
+
+    let x = if t <= 4000. {
+        -0.2661239e9 / t3 - 0.2343589e6 / t2 + 0.8776956e3 / t + 0.179910
+    } else {
+        -3.0258469e9 / t3 + 2.1070379e6 / t2 + 0.2226347e3 / t + 0.240390
+    };
+
+    let (x2, x3) = (x * x, x * x * x);
+    let y = if t <= 2222. {
+        -1.1063814 * x3 - 1.34811020 * x2 + 2.18555832 * x - 0.20219683
+    } else if t <= 4000. {
+        -0.9549476 * x3 - 1.37418593 * x2 + 2.09137015 * x - 0.16748867
+    } else {
+        3.0817580 * x3 - 5.87338670 * x2 + 3.75112997 * x - 0.37001483
+    };
+
+    Vec2::new(x as f32, y as f32)
+}
+
+/// CIE 1931 xy of a white `tint` units off the Planckian locus at
+/// `temperature`. Positive tint moves towards magenta, negative towards green.
+pub fn temperature_to_xy(temperature: f32, tint: f32) -> Vec2 {
+    let temperature = temperature.clamp(TEMPERATURE_RANGE.0, TEMPERATURE_RANGE.1);
+    let on_locus = xy_to_uv(planckian_xy(temperature));
+
+    // The locus runs from red to blue with rising temperature, normal to it
+    // on the side of lower v is magenta
+    let step = 0.01 * temperature;
+    let tangent = (xy_to_uv(planckian_xy(temperature + step))
+        - xy_to_uv(planckian_xy(temperature - step)))
+    .normalize();
+    let towards_magenta = Vec2::new(tangent.y, -tangent.x);
+    let towards_magenta = if towards_magenta.y > 0. {
+        -towards_magenta
+    } else {
+        towards_magenta
+    };
+
+    uv_to_xy(on_locus + towards_magenta * tint / TINT_SCALE)
+}
+
+/// CIE 1931 xy to CIE XYZ with Y = 1.
+pub fn xy_to_xyz(xy: Vec2) -> Vec3 {
+    Vec3::new(xy.x / xy.y, 1., (1. - xy.x - xy.y) / xy.y)
+}
+
+/// CIE 1931 xy to CIE 1960 uv.
+pub fn xy_to_uv(xy: Vec2) -> Vec2 {
+    let denom = -2. * xy.x + 12. * xy.y + 3.;
+    Vec2::new(4. * xy.x, 6. * xy.y) / denom
+}
+
+/// CIE 1960 uv to CIE 1931 xy.
+pub fn uv_to_xy(uv: Vec2) -> Vec2 {
+    let denom = 2. * uv.x - 8. * uv.y + 4.;
+    Vec2::new(3. * uv.x, 2. * uv.y) / denom
+}
@@ -0,0 +1,164 @@
+//! CPU reference of `color_adjust.wgsl`: saturation, vibrance and hue
+//! adjustments in OkLCh, the polar form of Björn Ottosson's Oklab.
+//!
+//! Input and output are linear sRGB. Lightness, chroma and hue are adjusted
+//! globally and per hue band, with every hue between two band centres
+//! blending the adjustments of both, so there are no edges between bands.
+
+use glam::{Mat3, Vec3};
+
+use crate::operations::ColorAdjustPush;
+
+/// Bands of [`HueBands`].
+pub const HUE_BANDS: usize = 8;
+
+/// The bands in order of their centres.
+pub const HUE_BAND_NAMES: [&str; HUE_BANDS] = [
+    "Red", "Orange", "Yellow", "Green", "Aqua", "Blue", "Purple", "Magenta",
+];
+
+/// OkLCh hue in degrees of every band centre: the sRGB primaries and
+/// secondaries, orange at (1, 0.5, 0) and purple at (0.5, 0, 1) in encoded
+/// sRGB.
+pub const HUE_BAND_CENTRES: [f32; HUE_BANDS] =
+    [29.23, 52.78, 109.77, 142.5, 194.77, 264.05, 293.77, 328.36];
+
+/// Chroma at and above which vibrance has no effect, a bit below that of the
+/// sRGB primaries.
+pub const VIBRANCE_CHROMA: f32 = 0.25;
+
+/// Chroma from which the lightness of a band is fully adjusted, below it the
+/// adjustment fades out so neutrals keep their lightness.
+pub const NEUTRAL_CHROMA: f32 = 0.05;
+
+/// Linear sRGB to the LMS cone responses of Oklab, column by column.
+const SRGB_TO_LMS: [f32; 9] = [
+    0.41222146,
+    0.2119035,
+    0.08830246,
+    0.53633255,
+    0.6806995,
+    0.28171885,
+    0.051445995,
+    0.10739696,
+    0.6299787,
+];
+
+/// Compressed LMS to Oklab, column by column.
+const LMS_TO_OKLAB: [f32; 9] = [
+    0.21045426,
+    1.9779985,
+    0.025904037,
+    0.7936178,
+    -2.4285922,
+    0.78277177,
+    -0.004072047,
+    0.4505937,
+    -0.80867577,
+];
+
+/// Oklab to compressed LMS, column by column.
+const OKLAB_TO_LMS: [f32; 9] = [
+    1.,
+    1.,
+    1.,
+    0.39633778,
+    -0.105561346,
+    -0.08948418,
+    0.21580376,
+    -0.06385417,
+    -1.2914855,
+];
+
+/// LMS to linear sRGB, column by column.
+const LMS_TO_SRGB: [f32; 9] = [
+    4.0767417,
+    -1.268438,
+    -0.0041960863,
+    -3.3077116,
+    2.6097574,
+    -0.7034186,
+    0.23096994,
+    -0.34131938,
+    1.7076147,
+];
+
+/// Per band offsets, all 0 by default. Indexed like [`HUE_BAND_NAMES`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct HueBands {
+    /// Hue rotation in degrees.
+    pub hue: [f32; HUE_BANDS],
+    /// Relative chroma change, -1 removes all colour from the band.
+    pub saturation: [f32; HUE_BANDS],
+    /// Relative lightness change.
+    pub lightness: [f32; HUE_BANDS],
+}
+
+impl HueBands {
+    /// The `(hue, saturation, lightness)` offsets at `hue` degrees, linearly
+    /// interpolated between the two nearest band centres.
+    pub fn at(&self, hue: f32) -> (f32, f32, f32) {
+        let hue = hue.rem_euclid(360.);
+        // The band at or below the hue, the last band wraps around past 0
+        let lower = HUE_BAND_CENTRES
+            .iter()
+            .rposition(|&centre| centre <= hue)
+            .unwrap_or(HUE_BANDS - 1);
+        let upper = (lower + 1) % HUE_BANDS;
+
+        let span = (HUE_BAND_CENTRES[upper] - HUE_BAND_CENTRES[lower]).rem_euclid(360.);
+        let t = (hue - HUE_BAND_CENTRES[lower]).rem_euclid(360.) / span;
+
+        let lerp = |values: &[f32; HUE_BANDS]| values[lower] + (values[upper] - values[lower]) * t;
+        (
+            lerp(&self.hue),
+            lerp(&self.saturation),
+            lerp(&self.lightness),
+        )
+    }
+}
+
+/// Cube root that keeps the sign, for colours outside the sRGB gamut.
+fn cbrt(x: Vec3) -> Vec3 {
+    Vec3::new(x.x.cbrt(), x.y.cbrt(), x.z.cbrt())
+}
+
+pub fn linear_srgb_to_oklab(rgb: Vec3) -> Vec3 {
+    let lms = Mat3::from_cols_array(&SRGB_TO_LMS) * rgb;
+    Mat3::from_cols_array(&LMS_TO_OKLAB) * cbrt(lms)
+}
+
+pub fn oklab_to_linear_srgb(lab: Vec3) -> Vec3 {
+    let lms = Mat3::from_cols_array(&OKLAB_TO_LMS) * lab;
+    Mat3::from_cols_array(&LMS_TO_SRGB) * (lms * lms * lms)
+}
+
+/// Oklab to `(lightness, chroma, hue in degrees)`.
+pub fn oklab_to_oklch(lab: Vec3) -> Vec3 {
+    let hue = lab.z.atan2(lab.y).to_degrees().rem_euclid(360.);
+    Vec3::new(lab.x, lab.y.hypot(lab.z), hue)
+}
+
+pub fn oklch_to_oklab(lch: Vec3) -> Vec3 {
+    let (sin, cos) = lch.z.to_radians().sin_cos();
+    Vec3::new(lch.x, lch.y * cos, lch.y * sin)
+}
+
+/// The adjustments of [`ColorAdjustPush`] applied to a linear sRGB colour.
+pub fn color_adjust(push: &ColorAdjustPush, rgb: Vec3) -> Vec3 {
+    let lch = oklab_to_oklch(linear_srgb_to_oklab(rgb));
+    let (lightness, chroma, hue) = (lch.x, lch.y, lch.z);
+    let (band_hue, band_saturation, band_lightness) = push.bands.at(hue);
+
+    let vibrance = 1. + push.vibrance * (1. - (chroma / VIBRANCE_CHROMA).min(1.));
+    let chroma_scale = push.saturation * (1. + band_saturation) * vibrance;
+
+    let neutral = (chroma / NEUTRAL_CHROMA).min(1.);
+    let adjusted = Vec3::new(
+        lightness * (1. + band_lightness * neutral).max(0.),
+        chroma * chroma_scale.max(0.),
+        (hue + push.hue_shift + band_hue).rem_euclid(360.),
+    );
+    oklab_to_linear_srgb(oklch_to_oklab(adjusted))
+}
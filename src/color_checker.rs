@@ -0,0 +1,385 @@
+//! Colour correction matrix calibration from a capture of a 24 patch
+//! ColorChecker.
+//!
+//! The capture should be demosaiced and white balanced, with an identity
+//! `color_correction_matrix` and a gamma of 1, e.g. a read back of
+//! `Buffers::RGB`. The mean of every patch is fitted to the linear sRGB
+//! values of the chart by a 3×3 matrix whose rows sum to 1, so the neutral
+//! patches stay neutral after the white balance. Exposure differences between
+//! the capture and the reference are taken out using the neutral row first.
+
+use glam::{Mat3, Mat4, Vec3};
+
+use crate::operations::ColorCorrectionPush;
+
+pub const PATCH_COUNT: usize = 24;
+const CHART_ROWS: usize = 4;
+const CHART_COLUMNS: usize = 6;
+
+/// White to black, the last row of the chart.
+const NEUTRAL_PATCHES: std::ops::Range<usize> = 18..24;
+
+/// 8-bit sRGB values of the ColorChecker Classic as published by X-Rite, in
+/// reading order from dark skin to black.
+const REFERENCE_SRGB: [[u8; 3]; PATCH_COUNT] = [
+    [115, 82, 68],
+    [194, 150, 130],
+    [98, 122, 157],
+    [87, 108, 67],
+    [133, 128, 177],
+    [103, 189, 170],
+    [214, 126, 44],
+    [80, 91, 166],
+    [193, 90, 99],
+    [94, 60, 108],
+    [157, 188, 64],
+    [224, 163, 46],
+    [56, 61, 150],
+    [70, 148, 73],
+    [175, 54, 60],
+    [231, 199, 31],
+    [187, 86, 149],
+    [8, 133, 161],
+    [243, 243, 242],
+    [200, 200, 200],
+    [160, 160, 160],
+    [122, 122, 121],
+    [85, 85, 85],
+    [52, 52, 52],
+];
+
+/// Linear sRGB to CIE XYZ with the D65 white.
+const SRGB_TO_XYZ: [[f32; 3]; 3] = [
+    [0.4124564, 0.3575761, 0.1804375],
+    [0.2126729, 0.7151522, 0.0721750],
+    [0.0193339, 0.119192, 0.9503041],
+];
+
+/// Rectangle of pixels inside a single patch, from the top left of the
+/// capture.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PatchRegion {
+    pub row: usize,
+    pub col: usize,
+    pub height: usize,
+    pub width: usize,
+}
+
+impl PatchRegion {
+    /// Regions of an upright chart, given the `(row, col)` centres of the dark
+    /// skin and black patches. Every region is a `size` square around the
+    /// centre of its patch, which should stay clear of the patch borders.
+    pub fn grid(first: [f32; 2], last: [f32; 2], size: usize) -> [Self; PATCH_COUNT] {
+        let step = [
+            (last[0] - first[0]) / (CHART_ROWS - 1) as f32,
+            (last[1] - first[1]) / (CHART_COLUMNS - 1) as f32,
+        ];
+        std::array::from_fn(|i| {
+            let (chart_row, chart_col) = (i / CHART_COLUMNS, i % CHART_COLUMNS);
+            let centre_row = first[0] + step[0] * chart_row as f32;
+            let centre_col = first[1] + step[1] * chart_col as f32;
+            let half = size as f32 / 2.;
+            Self {
+                row: (centre_row - half).round().max(0.) as usize,
+                col: (centre_col - half).round().max(0.) as usize,
+                height: size,
+                width: size,
+            }
+        })
+    }
+}
+
+/// What [`fit_color_correction`] minimises.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CcmFit {
+    /// Squared error in linear RGB. Closed form, but weighs bright patches
+    /// more than they are perceived.
+    #[default]
+    LeastSquares,
+    /// Squared CIEDE2000 difference in Lab, starting from the least squares
+    /// fit.
+    DeltaE2000,
+}
+
+/// Fits the colour correction matrix for `image`, a `width`×`height` RGBA
+/// capture of the chart with the patches at `patches`.
+pub fn fit_color_correction(
+    image: &[[f32; 4]],
+    width: usize,
+    height: usize,
+    patches: &[PatchRegion; PATCH_COUNT],
+    fit: CcmFit,
+) -> ColorCorrectionPush {
+    assert_eq!(
+        image.len(),
+        width * height,
+        "Image size doesn't match dimensions"
+    );
+    let measured = patches.map(|region| patch_mean(image, width, height, region));
+    let reference = reference_colors();
+    let measured = normalize_exposure(&measured, &reference);
+
+    let mut matrix = least_squares(&measured, &reference);
+    if fit == CcmFit::DeltaE2000 {
+        matrix = minimize_delta_e(&measured, &reference, matrix);
+    }
+
+    ColorCorrectionPush {
+        color_correction_matrix: Mat4::from_mat3(matrix),
+    }
+}
+
+/// Linear sRGB of the chart patches, in reading order.
+pub fn reference_colors() -> [Vec3; PATCH_COUNT] {
+    REFERENCE_SRGB
+        .map(|srgb| Vec3::from_array(srgb.map(|value| srgb_to_linear(value as f32 / 255.))))
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// CIE Lab of a linear sRGB colour, relative to the D65 white at RGB 1.
+pub fn linear_srgb_to_lab(rgb: Vec3) -> Vec3 {
+    let to_xyz = row_major(SRGB_TO_XYZ);
+    let xyz = to_xyz * rgb / (to_xyz * Vec3::ONE);
+
+    let f = |t: f32| {
+        let delta: f32 = 6. / 29.;
+        if t > delta.powi(3) {
+            t.cbrt()
+        } else {
+            t / (3. * delta * delta) + 4. / 29.
+        }
+    };
+    let (fx, fy, fz) = (f(xyz.x), f(xyz.y), f(xyz.z));
+
+    Vec3::new(116. * fy - 16., 500. * (fx - fy), 200. * (fy - fz))
+}
+
+/// The CIEDE2000 colour difference of two Lab colours, as formulated by
+/// Sharma, Wu and Dalal.
+pub fn delta_e_2000(lab_1: Vec3, lab_2: Vec3) -> f32 {
+    let (l_1, a_1, b_1) = (lab_1.x as f64, lab_1.y as f64, lab_1.z as f64);
+    let (l_2, a_2, b_2) = (lab_2.x as f64, lab_2.y as f64, lab_2.z as f64);
+    let pow_25_7 = 25f64.powi(7);
+
+    let c_mean = (a_1.hypot(b_1) + a_2.hypot(b_2)) / 2.;
+    let g = 0.5 * (1. - (c_mean.powi(7) / (c_mean.powi(7) + pow_25_7)).sqrt());
+    let (a_1, a_2) = ((1. + g) * a_1, (1. + g) * a_2);
+    let (c_1, c_2) = (a_1.hypot(b_1), a_2.hypot(b_2));
+
+    let hue = |a: f64, b: f64| {
+        if a == 0. && b == 0. {
+            0.
+        } else {
+            b.atan2(a).to_degrees().rem_euclid(360.)
+        }
+    };
+    let (h_1, h_2) = (hue(a_1, b_1), hue(a_2, b_2));
+    let chromatic = c_1 * c_2 != 0.;
+
+    let delta_l = l_2 - l_1;
+    let delta_c = c_2 - c_1;
+    let delta_h = match h_2 - h_1 {
+        _ if !chromatic => 0.,
+        d if d > 180. => d - 360.,
+        d if d < -180. => d + 360.,
+        d => d,
+    };
+    let delta_h = 2. * (c_1 * c_2).sqrt() * (delta_h / 2.).to_radians().sin();
+
+    let l_mean = (l_1 + l_2) / 2.;
+    let c_mean = (c_1 + c_2) / 2.;
+    let h_mean = if !chromatic {
+        h_1 + h_2
+    } else if (h_1 - h_2).abs() <= 180. {
+        (h_1 + h_2) / 2.
+    } else if h_1 + h_2 < 360. {
+        (h_1 + h_2 + 360.) / 2.
+    } else {
+        (h_1 + h_2 - 360.) / 2.
+    };
+
+    let cos = |degrees: f64| degrees.to_radians().cos();
+    let t = 1. - 0.17 * cos(h_mean - 30.) + 0.24 * cos(2. * h_mean) + 0.32 * cos(3. * h_mean + 6.)
+        - 0.20 * cos(4. * h_mean - 63.);
+    let delta_theta = 30. * (-((h_mean - 275.) / 25.).powi(2)).exp();
+    let r_c = 2. * (c_mean.powi(7) / (c_mean.powi(7) + pow_25_7)).sqrt();
+    let s_l = 1. + 0.015 * (l_mean - 50.).powi(2) / (20. + (l_mean - 50.).powi(2)).sqrt();
+    let s_c = 1. + 0.045 * c_mean;
+    let s_h = 1. + 0.015 * c_mean * t;
+    let r_t = -(2. * delta_theta).to_radians().sin() * r_c;
+
+    let (l, c, h) = (delta_l / s_l, delta_c / s_c, delta_h / s_h);
+    (l * l + c * c + h * h + r_t * c * h).sqrt() as f32
+}
+
+fn row_major(rows: [[f32; 3]; 3]) -> Mat3 {
+    Mat3::from_cols_array_2d(&rows).transpose()
+}
+
+fn patch_mean(image: &[[f32; 4]], width: usize, height: usize, region: PatchRegion) -> Vec3 {
+    assert!(
+        region.height > 0
+            && region.width > 0
+            && region.row + region.height <= height
+            && region.col + region.width <= width,
+        "{region:?} is not inside the image"
+    );
+    let mut sum = [0f64; 3];
+    for row in region.row..region.row + region.height {
+        for pixel in &image[row * width + region.col..row * width + region.col + region.width] {
+            for (sum, value) in sum.iter_mut().zip(pixel) {
+                *sum += *value as f64;
+            }
+        }
+    }
+    let n = (region.height * region.width) as f64;
+    Vec3::from_array(sum.map(|sum| (sum / n) as f32))
+}
+
+/// Scales `measured` so that the neutral patches best match the reference.
+fn normalize_exposure(
+    measured: &[Vec3; PATCH_COUNT],
+    reference: &[Vec3; PATCH_COUNT],
+) -> [Vec3; PATCH_COUNT] {
+    let (mut cross, mut square) = (0f64, 0f64);
+    for i in NEUTRAL_PATCHES {
+        cross += measured[i].dot(reference[i]) as f64;
+        square += measured[i].length_squared() as f64;
+    }
+    assert!(square > 0., "The neutral patches of the capture are black");
+    let scale = (cross / square) as f32;
+    measured.map(|color| color * scale)
+}
+
+/// Builds a matrix with rows summing to 1 from the first two entries of every
+/// row.
+fn white_preserving(params: &[f64; 6]) -> Mat3 {
+    let rows = [0, 1, 2].map(|row| {
+        let (first, second) = (params[2 * row], params[2 * row + 1]);
+        [first, second, 1. - first - second].map(|value| value as f32)
+    });
+    row_major(rows)
+}
+
+/// Solves every output channel separately, with the third coefficient
+/// eliminated by the row sum constraint.
+fn least_squares(measured: &[Vec3; PATCH_COUNT], reference: &[Vec3; PATCH_COUNT]) -> Mat3 {
+    let mut params = [0f64; 6];
+    for channel in 0..3 {
+        let (mut xx, mut xy, mut yy, mut xt, mut yt) = (0f64, 0f64, 0f64, 0f64, 0f64);
+        for (measured, reference) in measured.iter().zip(reference) {
+            let [r, g, b] = measured.to_array().map(|value| value as f64);
+            let (x, y) = (r - b, g - b);
+            let target = reference[channel] as f64 - b;
+            xx += x * x;
+            xy += x * y;
+            yy += y * y;
+            xt += x * target;
+            yt += y * target;
+        }
+        let det = xx * yy - xy * xy;
+        assert!(det.abs() > 1e-12, "The patches don't span the colour space");
+        params[2 * channel] = (yy * xt - xy * yt) / det;
+        params[2 * channel + 1] = (xx * yt - xy * xt) / det;
+    }
+    white_preserving(&params)
+}
+
+fn minimize_delta_e(
+    measured: &[Vec3; PATCH_COUNT],
+    reference: &[Vec3; PATCH_COUNT],
+    start: Mat3,
+) -> Mat3 {
+    let reference = reference.map(linear_srgb_to_lab);
+    let cost = |params: &[f64; 6]| {
+        let matrix = white_preserving(params);
+        measured
+            .iter()
+            .zip(&reference)
+            .map(|(measured, reference)| {
+                (delta_e_2000(linear_srgb_to_lab(matrix * *measured), *reference) as f64).powi(2)
+            })
+            .sum::<f64>()
+    };
+
+    let rows = start.transpose().to_cols_array_2d();
+    let start = [0, 1, 2, 3, 4, 5].map(|i| rows[i / 2][i % 2] as f64);
+    white_preserving(&nelder_mead(start, 0.05, cost))
+}
+
+/// Downhill simplex minimisation of `cost`, with an initial simplex of
+/// `step` along every axis.
+fn nelder_mead<const N: usize>(
+    start: [f64; N],
+    step: f64,
+    cost: impl Fn(&[f64; N]) -> f64,
+) -> [f64; N] {
+    const MAX_ITERATIONS: usize = 10000;
+
+    let mut simplex = (0..=N)
+        .map(|i| {
+            let mut point = start;
+            if i > 0 {
+                point[i - 1] += step;
+            }
+            (point, cost(&point))
+        })
+        .collect::<Vec<_>>();
+
+    let towards = |from: &[f64; N], to: &[f64; N], factor: f64| -> [f64; N] {
+        std::array::from_fn(|i| from[i] + factor * (to[i] - from[i]))
+    };
+
+    for _ in 0..MAX_ITERATIONS {
+        simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let (best, worst) = (simplex[0].1, simplex[N].1);
+        if worst - best <= 1e-12 * (1. + best.abs()) {
+            break;
+        }
+
+        let centroid: [f64; N] = std::array::from_fn(|i| {
+            simplex[..N].iter().map(|(point, _)| point[i]).sum::<f64>() / N as f64
+        });
+        let worst_point = simplex[N].0;
+
+        let reflected = towards(&centroid, &worst_point, -1.);
+        let reflected_cost = cost(&reflected);
+
+        if reflected_cost < best {
+            let expanded = towards(&centroid, &worst_point, -2.);
+            let expanded_cost = cost(&expanded);
+            simplex[N] = if expanded_cost < reflected_cost {
+                (expanded, expanded_cost)
+            } else {
+                (reflected, reflected_cost)
+            };
+        } else if reflected_cost < simplex[N - 1].1 {
+            simplex[N] = (reflected, reflected_cost);
+        } else {
+            let contracted = towards(&centroid, &worst_point, 0.5);
+            let contracted_cost = cost(&contracted);
+            if contracted_cost < worst {
+                simplex[N] = (contracted, contracted_cost);
+            } else {
+                let best_point = simplex[0].0;
+                for vertex in &mut simplex[1..] {
+                    let point = towards(&best_point, &vertex.0, 0.5);
+                    *vertex = (point, cost(&point));
+                }
+            }
+        }
+    }
+
+    simplex
+        .into_iter()
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(point, _)| point)
+        .unwrap()
+}
@@ -0,0 +1,252 @@
+//! Adobe / Resolve `.cube` 3D LUTs, applied by [`crate::operations::Lut3D`].
+//!
+//! Only 3D LUTs are supported. The table is stored as in the file, with red
+//! changing fastest, and inputs outside `domain_min..domain_max` are clamped
+//! to it.
+
+use std::{fmt, path::Path};
+
+use glam::Vec3;
+
+use crate::operations::{LutHeader, LutInterpolation, MAX_LUT_SIZE};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CubeLut {
+    pub title: Option<String>,
+    /// Entries along every axis.
+    pub size: usize,
+    pub domain_min: [f32; 3],
+    pub domain_max: [f32; 3],
+    /// `size`³ output colours, red fastest, then green, then blue.
+    pub table: Vec<[f32; 3]>,
+}
+
+#[derive(Debug)]
+pub enum CubeError {
+    Io(std::io::Error),
+    /// A problem on the given 1-based line, or with the file as a whole for
+    /// line 0.
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for CubeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CubeError::Io(err) => write!(f, "could not read the LUT: {err}"),
+            CubeError::Parse { line: 0, message } => write!(f, "invalid .cube file: {message}"),
+            CubeError::Parse { line, message } => {
+                write!(f, "invalid .cube file, line {line}: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CubeError {}
+
+impl From<std::io::Error> for CubeError {
+    fn from(err: std::io::Error) -> Self {
+        CubeError::Io(err)
+    }
+}
+
+fn parse_error(line: usize, message: impl Into<String>) -> CubeError {
+    CubeError::Parse {
+        line,
+        message: message.into(),
+    }
+}
+
+fn parse_floats<const N: usize>(line: usize, values: &[&str]) -> Result<[f32; N], CubeError> {
+    if values.len() != N {
+        return Err(parse_error(
+            line,
+            format!("expected {N} values, found {}", values.len()),
+        ));
+    }
+    let mut out = [0.; N];
+    for (out, value) in out.iter_mut().zip(values) {
+        *out = value
+            .parse()
+            .map_err(|_| parse_error(line, format!("{value:?} is not a number")))?;
+    }
+    Ok(out)
+}
+
+impl CubeLut {
+    /// The LUT that maps every colour to itself.
+    pub fn identity(size: usize) -> Self {
+        let step = 1. / (size - 1) as f32;
+        let table = (0..size * size * size)
+            .map(|i| {
+                let (r, g, b) = (i % size, i / size % size, i / (size * size));
+                [r as f32 * step, g as f32 * step, b as f32 * step]
+            })
+            .collect();
+        Self {
+            title: None,
+            size,
+            domain_min: [0.; 3],
+            domain_max: [1.; 3],
+            table,
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CubeError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, CubeError> {
+        let mut title = None;
+        let mut size = None;
+        let mut domain_min = [0.; 3];
+        let mut domain_max = [1.; 3];
+        let mut table = Vec::new();
+
+        for (index, line) in contents.lines().enumerate() {
+            let number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+            let keyword = words.next().unwrap();
+            let values = words.collect::<Vec<_>>();
+
+            match keyword {
+                "TITLE" => {
+                    let rest = line["TITLE".len()..].trim();
+                    title = Some(rest.trim_matches('"').to_string());
+                }
+                "LUT_3D_SIZE" => {
+                    let [value] = parse_floats::<1>(number, &values)?;
+                    if value.fract() != 0. || !(2. ..=MAX_LUT_SIZE as f32).contains(&value) {
+                        return Err(parse_error(
+                            number,
+                            format!("the size must be a whole number in 2..={MAX_LUT_SIZE}"),
+                        ));
+                    }
+                    size = Some(value as usize);
+                }
+                "LUT_1D_SIZE" => {
+                    return Err(parse_error(number, "1D LUTs are not supported"));
+                }
+                "DOMAIN_MIN" => domain_min = parse_floats(number, &values)?,
+                "DOMAIN_MAX" => domain_max = parse_floats(number, &values)?,
+                // Resolve's way of writing the domain
+                "LUT_3D_INPUT_RANGE" => {
+                    let [min, max] = parse_floats::<2>(number, &values)?;
+                    domain_min = [min; 3];
+                    domain_max = [max; 3];
+                }
+                _ if keyword.starts_with(|c: char| c.is_ascii_alphabetic()) => {
+                    // Other keywords don't change how the table is read
+                }
+                _ => {
+                    let mut row = vec![keyword];
+                    row.extend(values);
+                    table.push(parse_floats::<3>(number, &row)?);
+                }
+            }
+        }
+
+        let size = size.ok_or_else(|| parse_error(0, "LUT_3D_SIZE is missing"))?;
+        if table.len() != size * size * size {
+            return Err(parse_error(
+                0,
+                format!(
+                    "expected {} table entries for a size of {size}, found {}",
+                    size * size * size,
+                    table.len()
+                ),
+            ));
+        }
+        if (0..3).any(|channel| domain_max[channel] <= domain_min[channel]) {
+            return Err(parse_error(0, "DOMAIN_MAX must be above DOMAIN_MIN"));
+        }
+
+        Ok(Self {
+            title,
+            size,
+            domain_min,
+            domain_max,
+            table,
+        })
+    }
+
+    /// The start of [`crate::operations::Buffers::Lut`] for this LUT.
+    pub fn header(&self) -> LutHeader {
+        let [r, g, b] = self.domain_min;
+        let domain_min = [r, g, b, 0.];
+        let [r, g, b] = self.domain_max;
+        let domain_max = [r, g, b, 0.];
+        LutHeader {
+            size: self.size as u32,
+            _padding: [0; 3],
+            domain_min,
+            domain_max,
+        }
+    }
+
+    fn entry(&self, r: usize, g: usize, b: usize) -> Vec3 {
+        self.table[(b * self.size + g) * self.size + r].into()
+    }
+
+    /// CPU reference of `lut_3d.wgsl`.
+    pub fn sample(&self, rgb: Vec3, interpolation: LutInterpolation) -> Vec3 {
+        let (min, max) = (Vec3::from(self.domain_min), Vec3::from(self.domain_max));
+        let last = (self.size - 1) as f32;
+        let position = ((rgb - min) / (max - min)).clamp(Vec3::ZERO, Vec3::ONE) * last;
+
+        let base = position.floor().min(Vec3::splat(last - 1.));
+        let f = position - base;
+        let [r, g, b] = base.to_array().map(|x| x as usize);
+        let c = |dr: usize, dg: usize, db: usize| self.entry(r + dr, g + dg, b + db);
+
+        match interpolation {
+            LutInterpolation::Trilinear => {
+                let c00 = c(0, 0, 0).lerp(c(1, 0, 0), f.x);
+                let c10 = c(0, 1, 0).lerp(c(1, 1, 0), f.x);
+                let c01 = c(0, 0, 1).lerp(c(1, 0, 1), f.x);
+                let c11 = c(0, 1, 1).lerp(c(1, 1, 1), f.x);
+                c00.lerp(c10, f.y).lerp(c01.lerp(c11, f.y), f.z)
+            }
+            LutInterpolation::Tetrahedral => {
+                let (c000, c111) = (c(0, 0, 0), c(1, 1, 1));
+                if f.x > f.y {
+                    if f.y > f.z {
+                        (1. - f.x) * c000
+                            + (f.x - f.y) * c(1, 0, 0)
+                            + (f.y - f.z) * c(1, 1, 0)
+                            + f.z * c111
+                    } else if f.x > f.z {
+                        (1. - f.x) * c000
+                            + (f.x - f.z) * c(1, 0, 0)
+                            + (f.z - f.y) * c(1, 0, 1)
+                            + f.y * c111
+                    } else {
+                        (1. - f.z) * c000
+                            + (f.z - f.x) * c(0, 0, 1)
+                            + (f.x - f.y) * c(1, 0, 1)
+                            + f.y * c111
+                    }
+                } else if f.z > f.y {
+                    (1. - f.z) * c000
+                        + (f.z - f.y) * c(0, 0, 1)
+                        + (f.y - f.x) * c(0, 1, 1)
+                        + f.x * c111
+                } else if f.z > f.x {
+                    (1. - f.y) * c000
+                        + (f.y - f.z) * c(0, 1, 0)
+                        + (f.z - f.x) * c(0, 1, 1)
+                        + f.x * c111
+                } else {
+                    (1. - f.y) * c000
+                        + (f.y - f.x) * c(0, 1, 0)
+                        + (f.x - f.z) * c(1, 1, 0)
+                        + f.z * c111
+                }
+            }
+        }
+    }
+}
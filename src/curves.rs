@@ -0,0 +1,137 @@
+//! Tone curves given by control points, baked into the 1D LUT that
+//! `gamma.wgsl` applies in place of the gamma.
+//!
+//! Curves are monotone cubic (Fritsch-Carlson) splines through their points,
+//! so a curve with rising points never overshoots between them. Input and
+//! output are in 0..1, inputs outside the first and last point take their
+//! values.
+
+use crate::operations::CurvesPush;
+
+/// Entries of every channel of [`crate::operations::Buffers::Curves`].
+pub const CURVE_LUT_SIZE: usize = 1024;
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ToneCurve {
+    /// `[input, output]` pairs, in any order.
+    pub points: Vec<[f32; 2]>,
+}
+
+/// The identity.
+impl Default for ToneCurve {
+    fn default() -> Self {
+        Self {
+            points: vec![[0., 0.], [1., 1.]],
+        }
+    }
+}
+
+impl ToneCurve {
+    /// Prepares the spline for every call, [`bake`] prepares it once.
+    pub fn evaluate(&self, x: f32) -> f32 {
+        Spline::new(self).evaluate(x)
+    }
+}
+
+/// The sorted points of a [`ToneCurve`] with the tangents at them.
+struct Spline {
+    points: Vec<[f32; 2]>,
+    tangents: Vec<f32>,
+}
+
+impl Spline {
+    fn new(curve: &ToneCurve) -> Self {
+        let mut points = curve.points.clone();
+        points.sort_by(|a, b| a[0].total_cmp(&b[0]));
+        points.dedup_by(|a, b| a[0] == b[0]);
+
+        let tangents = if points.len() < 2 {
+            Vec::new()
+        } else {
+            Self::tangents(&points)
+        };
+        Self { points, tangents }
+    }
+
+    /// Tangents of the spline at every point, limited so every segment stays
+    /// monotone.
+    fn tangents(points: &[[f32; 2]]) -> Vec<f32> {
+        let secants = points
+            .windows(2)
+            .map(|pair| (pair[1][1] - pair[0][1]) / (pair[1][0] - pair[0][0]))
+            .collect::<Vec<_>>();
+
+        let last = secants.len();
+        let mut tangents = (0..points.len())
+            .map(|i| match i {
+                0 => secants[0],
+                i if i == last => secants[last - 1],
+                i if secants[i - 1] * secants[i] <= 0. => 0.,
+                i => (secants[i - 1] + secants[i]) / 2.,
+            })
+            .collect::<Vec<_>>();
+
+        for (i, &secant) in secants.iter().enumerate() {
+            if secant == 0. {
+                tangents[i] = 0.;
+                tangents[i + 1] = 0.;
+                continue;
+            }
+            let a = tangents[i] / secant;
+            let b = tangents[i + 1] / secant;
+            let length = a * a + b * b;
+            if length > 9. {
+                let scale = 3. / length.sqrt();
+                tangents[i] = scale * a * secant;
+                tangents[i + 1] = scale * b * secant;
+            }
+        }
+        tangents
+    }
+
+    fn evaluate(&self, x: f32) -> f32 {
+        let points = &self.points;
+        match points.len() {
+            0 => return x,
+            1 => return points[0][1],
+            _ => {}
+        }
+
+        let (first, last) = (points[0], points[points.len() - 1]);
+        if x <= first[0] {
+            return first[1];
+        }
+        if x >= last[0] {
+            return last[1];
+        }
+
+        let tangents = &self.tangents;
+        let k = points.partition_point(|point| point[0] <= x) - 1;
+        let ([x0, y0], [x1, y1]) = (points[k], points[k + 1]);
+        let h = x1 - x0;
+        let t = (x - x0) / h;
+        let (t2, t3) = (t * t, t * t * t);
+
+        (2. * t3 - 3. * t2 + 1.) * y0
+            + (t3 - 2. * t2 + t) * h * tangents[k]
+            + (-2. * t3 + 3. * t2) * y1
+            + (t3 - t2) * h * tangents[k + 1]
+    }
+}
+
+/// The contents of [`crate::operations::Buffers::Curves`]: for
+/// [`CURVE_LUT_SIZE`] inputs evenly spaced over 0..1, the red, green and blue
+/// curves, each followed by the master curve.
+pub fn bake(push: &CurvesPush) -> Vec<[f32; 4]> {
+    let master = Spline::new(&push.master);
+    let channels = [&push.red, &push.green, &push.blue].map(Spline::new);
+    (0..CURVE_LUT_SIZE)
+        .map(|i| {
+            let x = i as f32 / (CURVE_LUT_SIZE - 1) as f32;
+            let channels = channels
+                .each_ref()
+                .map(|curve| master.evaluate(curve.evaluate(x)));
+            [channels[0], channels[1], channels[2], 0.]
+        })
+        .collect()
+}
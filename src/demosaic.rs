@@ -0,0 +1,413 @@
+//! CPU reference implementations of the algorithms in `debayer.wgsl`.
+//!
+//! These mirror the shaders step by step, including the mirrored borders of
+//! the shared-memory tile, so GPU output can be compared against them. RCD
+//! runs in four full-frame passes like the `rcd_*.wgsl` shaders, and mirrors
+//! its borders around the edge sample.
+
+use crate::operations::DemosaicAlgorithm;
+
+/// Demosaics an RGGB frame, returning `[r, g, b, 1]` per pixel like
+/// [`crate::operations::Debayer`] writes to `Buffers::RGB`.
+pub fn demosaic(
+    bayer: &[f32],
+    width: usize,
+    height: usize,
+    algorithm: DemosaicAlgorithm,
+) -> Vec<[f32; 4]> {
+    assert_eq!(
+        bayer.len(),
+        width * height,
+        "Frame size doesn't match dimensions"
+    );
+    let image = Bayer {
+        data: bayer,
+        width: width as i32,
+        height: height as i32,
+    };
+
+    if algorithm == DemosaicAlgorithm::Rcd {
+        return rcd(&image);
+    }
+
+    let mut out = Vec::with_capacity(width * height);
+    for row in 0..height as i32 {
+        for col in 0..width as i32 {
+            let cfa = (row % 2) * 2 + col % 2;
+            let [r, g, b] = match algorithm {
+                DemosaicAlgorithm::Bilinear => image.bilinear(row, col, cfa),
+                DemosaicAlgorithm::Malvar => image.malvar(row, col, cfa),
+                DemosaicAlgorithm::HamiltonAdams => image.colour_difference(row, col, cfa),
+                DemosaicAlgorithm::Rcd => unreachable!(),
+            };
+            out.push([r, g, b, 1.]);
+        }
+    }
+    out
+}
+
+/// Same as `reflect` in utils.wgsl
+fn reflect(idx: i32, max: i32) -> i32 {
+    if idx < 0 {
+        -(idx + 1)
+    } else if idx >= max {
+        2 * max - 1 - idx
+    } else {
+        idx
+    }
+}
+
+struct Bayer<'a> {
+    data: &'a [f32],
+    width: i32,
+    height: i32,
+}
+
+impl Bayer<'_> {
+    fn at(&self, row: i32, col: i32) -> f32 {
+        let row = reflect(row, self.height);
+        let col = reflect(col, self.width);
+        self.data[(row * self.width + col) as usize]
+    }
+
+    fn orthogonal(&self, row: i32, col: i32) -> f32 {
+        self.at(row - 1, col)
+            + self.at(row + 1, col)
+            + self.at(row, col - 1)
+            + self.at(row, col + 1)
+    }
+
+    fn diagonal(&self, row: i32, col: i32) -> f32 {
+        self.at(row - 1, col - 1)
+            + self.at(row - 1, col + 1)
+            + self.at(row + 1, col - 1)
+            + self.at(row + 1, col + 1)
+    }
+
+    fn bilinear(&self, row: i32, col: i32, cfa: i32) -> [f32; 3] {
+        let value = self.at(row, col);
+        let orthogonal = self.orthogonal(row, col) / 4.;
+        let diagonal = self.diagonal(row, col) / 4.;
+        let horizontal = (self.at(row, col - 1) + self.at(row, col + 1)) / 2.;
+        let vertical = (self.at(row - 1, col) + self.at(row + 1, col)) / 2.;
+
+        match cfa {
+            0 => [value, orthogonal, diagonal],
+            1 => [horizontal, value, vertical],
+            2 => [vertical, value, horizontal],
+            _ => [diagonal, orthogonal, value],
+        }
+    }
+
+    fn malvar(&self, row: i32, col: i32, cfa: i32) -> [f32; 3] {
+        let value = self.at(row, col);
+        let distance_two = self.at(row - 2, col)
+            + self.at(row, col - 2)
+            + self.at(row + 2, col)
+            + self.at(row, col + 2);
+        let diagonal = self.diagonal(row, col);
+
+        // Green at red and blue
+        let green = (4. * value - distance_two + 2. * self.orthogonal(row, col)) / 8.;
+        // Blue at red and red at blue
+        let opposite = (6. * value - 3. * distance_two / 2. + 2. * diagonal) / 8.;
+        // The colour left and right of a green pixel
+        let horizontal = (5. * value - self.at(row, col - 2) - self.at(row, col + 2) - diagonal
+            + (self.at(row - 2, col) + self.at(row + 2, col)) / 2.
+            + 4. * (self.at(row, col - 1) + self.at(row, col + 1)))
+            / 8.;
+        // The colour above and below a green pixel
+        let vertical = (5. * value - self.at(row - 2, col) - self.at(row + 2, col) - diagonal
+            + (self.at(row, col - 2) + self.at(row, col + 2)) / 2.
+            + 4. * (self.at(row - 1, col) + self.at(row + 1, col)))
+            / 8.;
+
+        match cfa {
+            0 => [value, green, opposite],
+            1 => [horizontal, value, vertical],
+            2 => [vertical, value, horizontal],
+            _ => [opposite, green, value],
+        }
+    }
+
+    /// Green at a red or blue site, see `green_at` in debayer.wgsl
+    fn green_at(&self, row: i32, col: i32) -> f32 {
+        let value = self.at(row, col);
+
+        let (left, right) = (self.at(row, col - 1), self.at(row, col + 1));
+        let (up, down) = (self.at(row - 1, col), self.at(row + 1, col));
+
+        let laplacian_h = 2. * value - self.at(row, col - 2) - self.at(row, col + 2);
+        let laplacian_v = 2. * value - self.at(row - 2, col) - self.at(row + 2, col);
+
+        let estimate_h = (left + right) / 2. + laplacian_h / 4.;
+        let estimate_v = (up + down) / 2. + laplacian_v / 4.;
+
+        let gradient_h = (left - right).abs() + laplacian_h.abs();
+        let gradient_v = (up - down).abs() + laplacian_v.abs();
+
+        if gradient_h < gradient_v {
+            estimate_h
+        } else if gradient_v < gradient_h {
+            estimate_v
+        } else {
+            (estimate_h + estimate_v) / 2.
+        }
+    }
+
+    fn chroma_at(&self, row: i32, col: i32) -> f32 {
+        self.at(row, col) - self.green_at(row, col)
+    }
+
+    fn colour_difference(&self, row: i32, col: i32, cfa: i32) -> [f32; 3] {
+        if cfa == 1 || cfa == 2 {
+            let green = self.at(row, col);
+            let horizontal = (self.chroma_at(row, col - 1) + self.chroma_at(row, col + 1)) / 2.;
+            let vertical = (self.chroma_at(row - 1, col) + self.chroma_at(row + 1, col)) / 2.;
+            return if cfa == 1 {
+                [green + horizontal, green, green + vertical]
+            } else {
+                [green + vertical, green, green + horizontal]
+            };
+        }
+
+        let value = self.at(row, col);
+        let green = self.green_at(row, col);
+        let diagonal = (self.chroma_at(row - 1, col - 1)
+            + self.chroma_at(row - 1, col + 1)
+            + self.chroma_at(row + 1, col - 1)
+            + self.chroma_at(row + 1, col + 1))
+            / 4.;
+
+        if cfa == 0 {
+            [value, green, green + diagonal]
+        } else {
+            [green + diagonal, green, value]
+        }
+    }
+}
+
+/// Keep the RCD divisions finite on flat areas, where the gradients and the
+/// high-pass energies are zero.
+const RCD_EPS: f32 = 1e-5;
+const RCD_EPS_SQ: f32 = 1e-10;
+
+/// Same as `mirror` in utils.wgsl. Unlike [`reflect`] it mirrors around the
+/// edge sample, so every sample keeps its CFA colour.
+fn mirror(idx: i32, max: i32) -> i32 {
+    if idx < 0 {
+        -idx
+    } else if idx >= max {
+        2 * max - 2 - idx
+    } else {
+        idx
+    }
+}
+
+/// A full frame as one RCD pass reads it.
+struct Mirrored<'a, T> {
+    data: &'a [T],
+    width: i32,
+    height: i32,
+}
+
+impl<T: Copy> Mirrored<'_, T> {
+    fn at(&self, row: i32, col: i32) -> T {
+        let row = mirror(row, self.height);
+        let col = mirror(col, self.width);
+        self.data[(row * self.width + col) as usize]
+    }
+}
+
+/// Same as `blend` in utils.wgsl: the estimate from each side weighted by the
+/// gradient on the other.
+fn blend(gradient_a: f32, estimate_a: f32, gradient_b: f32, estimate_b: f32) -> f32 {
+    (gradient_b * estimate_a + gradient_a * estimate_b) / (gradient_a + gradient_b)
+}
+
+/// Same as `discrimination` in utils.wgsl: the direction of the pixel, unless
+/// its diagonal neighbours agree on a stronger one.
+fn discrimination(central: f32, neighbourhood: f32) -> f32 {
+    if (0.5 - central).abs() < (0.5 - neighbourhood).abs() {
+        neighbourhood
+    } else {
+        central
+    }
+}
+
+/// Ratio Corrected Demosaicing by Luis Sanz Rodríguez, one full-frame pass
+/// per `rcd_*.wgsl` shader.
+fn rcd(image: &Bayer) -> Vec<[f32; 4]> {
+    let (width, height) = (image.width, image.height);
+    let cfa = Mirrored {
+        data: image.data,
+        width,
+        height,
+    };
+    let pass = |f: &dyn Fn(i32, i32) -> [f32; 4]| {
+        (0..height)
+            .flat_map(|row| (0..width).map(move |col| (row, col)))
+            .map(|(row, col)| f(row, col))
+            .collect::<Vec<_>>()
+    };
+
+    let directions = pass(&|row, col| rcd_directions(&cfa, row, col));
+    let directions = Mirrored {
+        data: &directions,
+        width,
+        height,
+    };
+    let green = pass(&|row, col| rcd_green(&cfa, &directions, row, col));
+    let green = Mirrored {
+        data: &green,
+        width,
+        height,
+    };
+    let red_blue = pass(&|row, col| rcd_red_blue(&cfa, &green, row, col));
+    let red_blue = Mirrored {
+        data: &red_blue,
+        width,
+        height,
+    };
+    pass(&|row, col| rcd_green_sites(&red_blue, row, col))
+}
+
+/// rcd_directions.wgsl: the low-pass, and how much the vertical (against
+/// horizontal) and the main diagonal (against the anti-diagonal) high-pass
+/// energy dominates.
+fn rcd_directions(cfa: &Mirrored<f32>, row: i32, col: i32) -> [f32; 4] {
+    let high_pass = |row: i32, col: i32, (dr, dc): (i32, i32)| {
+        let at = |i: i32| cfa.at(row + i * dr, col + i * dc);
+        let value = at(-3) - at(-1) - at(1) + at(3) - 3. * (at(-2) + at(2)) + 6. * at(0);
+        value * value
+    };
+    let energy = |direction: (i32, i32)| {
+        let (dr, dc) = direction;
+        let sum = high_pass(row - dr, col - dc, direction)
+            + high_pass(row, col, direction)
+            + high_pass(row + dr, col + dc, direction);
+        sum.max(RCD_EPS_SQ)
+    };
+
+    let vertical = energy((1, 0));
+    let horizontal = energy((0, 1));
+    let diagonal = energy((1, 1));
+    let anti_diagonal = energy((1, -1));
+
+    let orthogonal =
+        cfa.at(row - 1, col) + cfa.at(row + 1, col) + cfa.at(row, col - 1) + cfa.at(row, col + 1);
+    let corners = cfa.at(row - 1, col - 1)
+        + cfa.at(row - 1, col + 1)
+        + cfa.at(row + 1, col - 1)
+        + cfa.at(row + 1, col + 1);
+    // Black-levelled noise can be negative, which would flip the ratios
+    let low_pass = (cfa.at(row, col) + orthogonal / 2. + corners / 4.).max(0.);
+
+    [
+        low_pass,
+        vertical / (vertical + horizontal),
+        diagonal / (diagonal + anti_diagonal),
+        0.,
+    ]
+}
+
+/// rcd_green.wgsl: green at the red and blue sites from the ratio corrected
+/// neighbours in the four cardinal directions, plus the direction
+/// discriminations the later passes use.
+fn rcd_green(cfa: &Mirrored<f32>, directions: &Mirrored<[f32; 4]>, row: i32, col: i32) -> [f32; 4] {
+    let neighbourhood = |channel: usize| {
+        (directions.at(row - 1, col - 1)[channel]
+            + directions.at(row - 1, col + 1)[channel]
+            + directions.at(row + 1, col - 1)[channel]
+            + directions.at(row + 1, col + 1)[channel])
+            / 4.
+    };
+    let [_, vertical, diagonal, _] = directions.at(row, col);
+    let vh = discrimination(vertical, neighbourhood(1));
+    let pq = discrimination(diagonal, neighbourhood(2));
+
+    let cfa_index = (row % 2) * 2 + col % 2;
+    if cfa_index == 1 || cfa_index == 2 {
+        return [cfa.at(row, col), vh, pq, 0.];
+    }
+
+    // Gradient and estimate towards one side
+    let side = |dr: i32, dc: i32| {
+        let at = |i: i32| cfa.at(row + i * dr, col + i * dc);
+        let low_pass = |i: i32| directions.at(row + i * dr, col + i * dc)[0];
+        let gradient = RCD_EPS
+            + (at(1) - at(-1)).abs()
+            + (at(0) - at(2)).abs()
+            + (at(1) - at(3)).abs()
+            + (at(2) - at(4)).abs();
+        let estimate = at(1) * 2. * low_pass(0) / (RCD_EPS_SQ + low_pass(0) + low_pass(2));
+        (gradient, estimate)
+    };
+    let (north, south) = (side(-1, 0), side(1, 0));
+    let (west, east) = (side(0, -1), side(0, 1));
+    let vertical = blend(north.0, north.1, south.0, south.1);
+    let horizontal = blend(west.0, west.1, east.0, east.1);
+
+    [vertical + vh * (horizontal - vertical), vh, pq, 0.]
+}
+
+/// rcd_red_blue.wgsl: blue at the red sites and red at the blue sites from
+/// the colour differences in the four diagonal directions.
+fn rcd_red_blue(cfa: &Mirrored<f32>, green: &Mirrored<[f32; 4]>, row: i32, col: i32) -> [f32; 4] {
+    let [g, vh, pq, _] = green.at(row, col);
+
+    let cfa_index = (row % 2) * 2 + col % 2;
+    if cfa_index == 1 || cfa_index == 2 {
+        return [0., g, 0., vh];
+    }
+
+    let side = |dr: i32, dc: i32| {
+        let at = |i: i32| cfa.at(row + i * dr, col + i * dc);
+        let green = |i: i32| green.at(row + i * dr, col + i * dc)[0];
+        let gradient =
+            RCD_EPS + (at(1) - at(-1)).abs() + (at(1) - at(3)).abs() + (green(0) - green(2)).abs();
+        (gradient, at(1) - green(1))
+    };
+    let (north_west, south_east) = (side(-1, -1), side(1, 1));
+    let (north_east, south_west) = (side(-1, 1), side(1, -1));
+    let p = blend(north_west.0, north_west.1, south_east.0, south_east.1);
+    let q = blend(north_east.0, north_east.1, south_west.0, south_west.1);
+    let opposite = g + p + pq * (q - p);
+
+    let value = cfa.at(row, col);
+    if cfa_index == 0 {
+        [value, g, opposite, vh]
+    } else {
+        [opposite, g, value, vh]
+    }
+}
+
+/// rcd_green_sites.wgsl: red and blue at the green sites from the colour
+/// differences in the four cardinal directions.
+fn rcd_green_sites(red_blue: &Mirrored<[f32; 4]>, row: i32, col: i32) -> [f32; 4] {
+    let [r, g, b, vh] = red_blue.at(row, col);
+
+    let cfa_index = (row % 2) * 2 + col % 2;
+    if cfa_index == 0 || cfa_index == 3 {
+        return [r, g, b, 1.];
+    }
+
+    let interpolate = |channel: usize| {
+        let side = |dr: i32, dc: i32| {
+            let at = |i: i32| red_blue.at(row + i * dr, col + i * dc)[channel];
+            let green = |i: i32| red_blue.at(row + i * dr, col + i * dc)[1];
+            let gradient = RCD_EPS
+                + (green(0) - green(2)).abs()
+                + (at(1) - at(-1)).abs()
+                + (at(1) - at(3)).abs();
+            (gradient, at(1) - green(1))
+        };
+        let (north, south) = (side(-1, 0), side(1, 0));
+        let (west, east) = (side(0, -1), side(0, 1));
+        let vertical = blend(north.0, north.1, south.0, south.1);
+        let horizontal = blend(west.0, west.1, east.0, east.1);
+        g + vertical + vh * (horizontal - vertical)
+    };
+
+    [interpolate(0), g, interpolate(2), 1.]
+}
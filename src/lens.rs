@@ -0,0 +1,98 @@
+//! The Brown-Conrady lens model of [`crate::operations::LensCorrection`] and
+//! the crop to its valid region.
+//!
+//! Positions are normalised by half the image diagonal around the optical
+//! centre, with x to the right and y down. Every output pixel is mapped
+//! through the model to the position of the input it is resampled from, so
+//! the coefficients describe where the lens put each undistorted point.
+
+use glam::Vec2;
+
+use crate::operations::LensCorrectionPush;
+
+/// Points per image edge tested by [`crop_scale`].
+const CROP_SAMPLES: usize = 64;
+
+/// Halvings of the search in [`crop_scale`].
+const CROP_ITERATIONS: usize = 24;
+
+/// Positions this far outside the image in pixels still count as inside, so
+/// an identity model isn't cropped by rounding. `lens_correction.wgsl` allows
+/// more, so the GPU rounding differently doesn't blacken the crop border.
+const CROP_TOLERANCE: f32 = 1e-4;
+
+/// The normalised input position of the normalised output position `p`.
+pub fn distort(push: &LensCorrectionPush, p: Vec2) -> Vec2 {
+    let r2 = p.length_squared();
+    let radial = 1. + r2 * (push.k1 + r2 * (push.k2 + r2 * push.k3));
+    let tangential = Vec2::new(
+        2. * push.p1 * p.x * p.y + push.p2 * (r2 + 2. * p.x * p.x),
+        push.p1 * (r2 + 2. * p.y * p.y) + 2. * push.p2 * p.x * p.y,
+    );
+    p * radial + tangential
+}
+
+/// The optical centre in pixels and the normalisation length.
+fn frame(push: &LensCorrectionPush, height: i32, width: i32) -> (Vec2, f32) {
+    let half = Vec2::new(width as f32, height as f32) / 2.;
+    let centre = half * (Vec2::ONE + Vec2::new(push.center_x, push.center_y));
+    (centre, half.length())
+}
+
+/// Input position in pixels, `(column, row)` with pixel centres at whole
+/// numbers, that output pixel `(column, row)` of a `height` × `width` image
+/// is resampled from. `crop_scale` zooms in on the centre, `channel_scale`
+/// is [`LensCorrectionPush::red_scale`], 1 or
+/// [`LensCorrectionPush::blue_scale`].
+pub fn source_position(
+    push: &LensCorrectionPush,
+    height: i32,
+    width: i32,
+    pixel: Vec2,
+    crop_scale: f32,
+    channel_scale: f32,
+) -> Vec2 {
+    let (centre, norm) = frame(push, height, width);
+    let p = (pixel + 0.5 - centre) / norm * crop_scale;
+    centre + distort(push, p) * channel_scale * norm - 0.5
+}
+
+/// The largest zoom of at most 1 at which every output pixel samples inside
+/// the input in all three channels. Only the border of the output is tested.
+pub fn crop_scale(push: &LensCorrectionPush, height: i32, width: i32) -> f32 {
+    let max = Vec2::new(width as f32 - 1., height as f32 - 1.);
+    let border = (0..CROP_SAMPLES).flat_map(|i| {
+        let t = i as f32 / (CROP_SAMPLES - 1) as f32;
+        [
+            Vec2::new(t * max.x, 0.),
+            Vec2::new(t * max.x, max.y),
+            Vec2::new(0., t * max.y),
+            Vec2::new(max.x, t * max.y),
+        ]
+    });
+    let border = border.collect::<Vec<_>>();
+
+    let valid = |scale: f32| {
+        border.iter().all(|&pixel| {
+            [push.red_scale, 1., push.blue_scale].iter().all(|&channel_scale| {
+                let source = source_position(push, height, width, pixel, scale, channel_scale);
+                source.cmpge(Vec2::splat(-CROP_TOLERANCE)).all()
+                    && source.cmple(max + CROP_TOLERANCE).all()
+            })
+        })
+    };
+
+    if valid(1.) {
+        return 1.;
+    }
+    let (mut low, mut high) = (0., 1.);
+    for _ in 0..CROP_ITERATIONS {
+        let middle = (low + high) / 2.;
+        if valid(middle) {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    low
+}
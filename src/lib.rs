@@ -1,2 +1,11 @@
+pub mod color;
+pub mod color_adjust;
+pub mod color_checker;
+pub mod cube;
+pub mod curves;
+pub mod demosaic;
+pub mod lens;
+pub mod noise_profile;
 pub mod operations;
 pub mod setup;
+pub mod tone_map;
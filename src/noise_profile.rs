@@ -0,0 +1,202 @@
+//! Estimation of Poisson-Gaussian noise parameters from raw Bayer frames.
+//!
+//! The noise of every CFA channel is modelled as `variance = a * signal + b`.
+//! Estimates are made per patch of RGGB quads, in the same quad layout that
+//! `bayer_to_vec4.wgsl` produces, and `(a, b)` is fitted to the resulting
+//! (mean, variance) pairs by least squares. A profile is only valid for the
+//! gain setting of the frames it was estimated from.
+
+/// Noise model of a single CFA channel: `variance = a * signal + b`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct NoiseParams {
+    pub a: f32,
+    pub b: f32,
+}
+
+/// Per-channel noise model in R, Gr, Gb, B order.
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct NoiseProfile {
+    pub channels: [NoiseParams; 4],
+}
+
+impl NoiseProfile {
+    /// The profile for a signal multiplied by `scale`, e.g. to go from raw
+    /// counts to the normalised units of the pipeline.
+    pub fn scaled(&self, scale: f32) -> Self {
+        Self {
+            channels: self.channels.map(|NoiseParams { a, b }| NoiseParams {
+                a: a * scale,
+                b: b * scale * scale,
+            }),
+        }
+    }
+
+    pub fn a(&self) -> [f32; 4] {
+        self.channels.map(|channel| channel.a)
+    }
+
+    pub fn b(&self) -> [f32; 4] {
+        self.channels.map(|channel| channel.b)
+    }
+}
+
+/// CPU version of `bayer_to_vec4.wgsl`: gathers every RGGB quad of `frame`
+/// into one `[R, Gr, Gb, B]` element of a half-resolution image.
+pub fn bayer_to_quads(frame: &[f32], width: usize, height: usize) -> Vec<[f32; 4]> {
+    assert_eq!(frame.len(), width * height, "Frame size doesn't match dimensions");
+    let (half_height, half_width) = (height / 2, width / 2);
+    let mut quads = Vec::with_capacity(half_height * half_width);
+    for row in 0..half_height {
+        for col in 0..half_width {
+            let top = 2 * row * width + 2 * col;
+            let bottom = top + width;
+            quads.push([frame[top], frame[top + 1], frame[bottom], frame[bottom + 1]]);
+        }
+    }
+    quads
+}
+
+/// Estimates a profile from a single flat-field or dark capture, which should
+/// already be black level corrected.
+///
+/// The variance of every patch of `patch_size`×`patch_size` quads is taken from
+/// differences of horizontally adjacent quads, which cancels smooth shading
+/// across the patch. A capture with a single brightness level only determines
+/// `b`, in which case `a` is zero.
+pub fn estimate_noise_profile(
+    frame: &[f32],
+    width: usize,
+    height: usize,
+    patch_size: usize,
+) -> NoiseProfile {
+    let quads = bayer_to_quads(frame, width, height);
+    let half_width = width / 2;
+
+    let samples = patch_statistics(&quads, half_width, patch_size, |row, col, channel| {
+        let idx = row * half_width + col;
+        let signal = quads[idx][channel];
+        // Var(x - y) = 2 * Var(x) for independent x and y
+        let diff = (quads[idx + 1][channel] - signal) / 2f32.sqrt();
+        (signal, diff)
+    });
+
+    fit_profile(&samples)
+}
+
+/// Estimates a profile from two captures of the same static scene with
+/// identical settings. Scene texture cancels in the difference of the two
+/// frames, so the scene does not need to be flat.
+pub fn estimate_noise_profile_from_pair(
+    first: &[f32],
+    second: &[f32],
+    width: usize,
+    height: usize,
+    patch_size: usize,
+) -> NoiseProfile {
+    let first = bayer_to_quads(first, width, height);
+    let second = bayer_to_quads(second, width, height);
+    let half_width = width / 2;
+
+    let samples = patch_statistics(&first, half_width, patch_size, |row, col, channel| {
+        let idx = row * half_width + col;
+        let (x, y) = (first[idx][channel], second[idx][channel]);
+        ((x + y) / 2., (x - y) / 2f32.sqrt())
+    });
+
+    fit_profile(&samples)
+}
+
+/// Splits the quad image into patches and returns `(mean, variance)` for every
+/// patch and channel. `sample` gives the signal and a zero-mean noise sample
+/// with the variance of a single pixel. The last quad column is left out, so
+/// `sample` may look one quad to the right.
+fn patch_statistics(
+    quads: &[[f32; 4]],
+    half_width: usize,
+    patch_size: usize,
+    sample: impl Fn(usize, usize, usize) -> (f32, f32),
+) -> [Vec<(f64, f64)>; 4] {
+    assert!(patch_size >= 2, "Patches need at least 2x2 quads");
+    let half_height = quads.len() / half_width.max(1);
+    let usable_width = half_width.saturating_sub(1);
+
+    let mut out: [Vec<(f64, f64)>; 4] = Default::default();
+
+    for patch_row in (0..half_height / patch_size).map(|i| i * patch_size) {
+        for patch_col in (0..usable_width / patch_size).map(|i| i * patch_size) {
+            for (channel, samples) in out.iter_mut().enumerate() {
+                let mut signal_sum = 0f64;
+                let mut noise_sum = 0f64;
+                let mut noise_sq_sum = 0f64;
+                for row in patch_row..patch_row + patch_size {
+                    for col in patch_col..patch_col + patch_size {
+                        let (signal, noise) = sample(row, col, channel);
+                        signal_sum += signal as f64;
+                        noise_sum += noise as f64;
+                        noise_sq_sum += (noise as f64).powi(2);
+                    }
+                }
+                let n = (patch_size * patch_size) as f64;
+                let noise_mean = noise_sum / n;
+                let variance = (noise_sq_sum / n - noise_mean * noise_mean) * n / (n - 1.);
+                samples.push((signal_sum / n, variance));
+            }
+        }
+    }
+
+    out
+}
+
+fn fit_profile(samples: &[Vec<(f64, f64)>; 4]) -> NoiseProfile {
+    NoiseProfile {
+        channels: [0, 1, 2, 3].map(|channel| fit_line(&samples[channel])),
+    }
+}
+
+/// Least squares fit of `variance = a * mean + b`, constrained to
+/// non-negative parameters.
+fn fit_line(samples: &[(f64, f64)]) -> NoiseParams {
+    if samples.is_empty() {
+        return NoiseParams::default();
+    }
+    let n = samples.len() as f64;
+    let mean_x = samples.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = samples.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let sxx = samples.iter().map(|(x, _)| (x - mean_x).powi(2)).sum::<f64>();
+    let sxy = samples
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum::<f64>();
+
+    // A single signal level can't separate the two terms
+    let spread = sxx / n;
+    if spread <= 1e-12 * mean_x.abs().max(1.).powi(2) {
+        return NoiseParams {
+            a: 0.,
+            b: mean_y.max(0.) as f32,
+        };
+    }
+
+    let a = sxy / sxx;
+    let b = mean_y - a * mean_x;
+
+    if a < 0. {
+        NoiseParams {
+            a: 0.,
+            b: mean_y.max(0.) as f32,
+        }
+    } else if b < 0. {
+        // Refit through the origin
+        let a = samples.iter().map(|(x, y)| x * y).sum::<f64>()
+            / samples.iter().map(|(x, _)| x * x).sum::<f64>();
+        NoiseParams {
+            a: a.max(0.) as f32,
+            b: 0.,
+        }
+    } else {
+        NoiseParams {
+            a: a as f32,
+            b: b as f32,
+        }
+    }
+}
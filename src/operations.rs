@@ -1,4 +1,4 @@
-use std::mem::size_of;
+use std::{mem::size_of, path::PathBuf};
 
 use bytemuck::bytes_of;
 use gpwgpu::{
@@ -13,11 +13,22 @@ use gpwgpu::{
 use gpwgpu::{parse_shaders, parse_shaders_dyn};
 use macros::{UiAggregation, UiMarker};
 
-use crate::setup::Params;
+use crate::{
+    color::{temperature_to_xy, CameraCalibration, Chromaticities, D65},
+    color_adjust::{HueBands, HUE_BANDS},
+    curves::{ToneCurve, CURVE_LUT_SIZE},
+    lens::crop_scale,
+    noise_profile::NoiseProfile,
+    setup::Params,
+};
 
 parse_shaders!(pub SHADERS, "src/shaders");
 // parse_shaders_dyn!(pub SHADERS, "src/shaders");
 
+/// black_level.wgsl divides the raw values by this, so everything after it
+/// works on data of roughly unit range.
+pub const RAW_SCALE: f32 = 30000.;
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, UiAggregation)]
 pub struct ISPParams {
     pub debayer_push: DebayerPush,
@@ -25,6 +36,116 @@ pub struct ISPParams {
     pub auto_white_balance_push: AutoWhiteBalancePush,
     pub gamma_push: GammaPush,
     pub color_correction_push: ColorCorrectionPush,
+    #[serde(default)]
+    pub crosstalk: CrosstalkKernels,
+    #[serde(default)]
+    pub green_equalization_push: GreenEqualizationPush,
+    #[serde(default)]
+    pub denoise_push: DenoisePush,
+    #[serde(default)]
+    pub noise_profile: Option<NoiseProfile>,
+    #[serde(default)]
+    pub temporal_denoise_push: TemporalDenoisePush,
+    #[serde(default)]
+    pub false_color_suppression_push: FalseColorSuppressionPush,
+    #[serde(default)]
+    pub camera_calibration: CameraCalibration,
+    #[serde(default)]
+    pub statistics_push: StatisticsPush,
+    #[serde(default)]
+    pub auto_exposure_push: AutoExposurePush,
+    #[serde(default)]
+    pub output_transform_push: OutputTransformPush,
+    #[serde(default)]
+    pub tone_map_push: ToneMapPush,
+    #[serde(default)]
+    pub local_tone_map_push: LocalToneMapPush,
+    #[serde(default)]
+    pub lut_push: LutPush,
+    /// `.cube` file applied by [`Lut3D`], loaded with
+    /// [`crate::setup::State::load_lut`].
+    #[serde(default)]
+    pub lut_path: Option<PathBuf>,
+    #[serde(default)]
+    pub curves_push: CurvesPush,
+    #[serde(default)]
+    pub color_adjust_push: ColorAdjustPush,
+    #[serde(default)]
+    pub sharpen_push: SharpenPush,
+    #[serde(default)]
+    pub lens_correction_push: LensCorrectionPush,
+    #[serde(default)]
+    pub highlight_recovery_push: HighlightRecoveryPush,
+    #[serde(default)]
+    pub defringe_push: DefringePush,
+}
+
+impl ISPParams {
+    /// The crosstalk kernels with the legacy `alpha`/`beta` terms of
+    /// [`BlackLevelPush`] folded in, as uploaded to [`Buffers::Crosstalk`].
+    pub fn crosstalk_kernels(&self) -> CrosstalkKernels {
+        let mut kernels = self.crosstalk;
+        // alpha: left neighbour into Gr, beta: upper neighbour into Gb
+        kernels.gr[1][0] += self.black_level_push.alpha;
+        kernels.gb[0][1] += self.black_level_push.beta;
+        kernels
+    }
+
+    /// Per CFA position `(a, b)` of the noise model `variance = a * x + b`, from
+    /// the noise profile if there is one and [`DenoisePush`] otherwise.
+    pub fn noise_model(&self) -> ([f32; 4], [f32; 4]) {
+        match &self.noise_profile {
+            Some(profile) => (profile.a(), profile.b()),
+            None => (
+                [self.denoise_push.noise_a; 4],
+                [self.denoise_push.noise_b; 4],
+            ),
+        }
+    }
+
+    /// Per CFA position white balance gains of the manual modes, `None` when
+    /// [`AutoWhiteBalance`] should estimate them.
+    pub fn manual_white_balance_gains(&self) -> Option<[f32; 4]> {
+        let push = &self.auto_white_balance_push;
+        let [r, g, b] = match push.mode {
+            WhiteBalanceMode::Auto => return None,
+            WhiteBalanceMode::Gains => [push.red_gain, push.green_gain, push.blue_gain],
+            WhiteBalanceMode::Temperature => self
+                .camera_calibration
+                .white_balance_gains(push.temperature, push.tint),
+        };
+        Some([r, g, g, b])
+    }
+
+    /// The matrix [`OutputTransform`] takes the colour corrected image to CIE
+    /// XYZ with, from [`ISPParams::camera_calibration`]. The white of the
+    /// temperature mode of [`AutoWhiteBalancePush`], D65 in the other modes
+    /// as they don't know the illuminant.
+    pub fn camera_to_xyz(&self) -> glam::Mat3 {
+        let push = &self.auto_white_balance_push;
+        let (temperature, white) = match push.mode {
+            WhiteBalanceMode::Temperature => (
+                push.temperature,
+                temperature_to_xy(push.temperature, push.tint),
+            ),
+            WhiteBalanceMode::Auto | WhiteBalanceMode::Gains => (6504., D65),
+        };
+        self.camera_calibration.camera_to_xyz(temperature, white)
+    }
+
+    /// The white level of [`HighlightRecoveryPush`] per channel in the units
+    /// `black_level.wgsl` outputs, before white balance. Green takes the lower
+    /// of its two CFA positions.
+    pub fn normalized_white_levels(&self) -> [f32; 3] {
+        let white_level = self.highlight_recovery_push.white_level;
+        let offsets = &self.black_level_push;
+        [
+            offsets.r_offset,
+            offsets.gr_offset.min(offsets.gb_offset),
+            offsets.b_offset,
+        ]
+        .map(|offset| (white_level + offset) / RAW_SCALE)
+    }
 }
 
 #[derive(Hash, Clone, Copy, PartialEq, Eq, Debug)]
@@ -35,6 +156,24 @@ pub enum Buffers {
     BlackLevel,
     AutoWhiteBalance,
     RGB,
+    Crosstalk,
+    GreenEqualization,
+    Denoise,
+    TemporalHistory,
+    Scratch,
+    AwbMaximum,
+    AwbHistogram,
+    AwbGradients,
+    AwbGradientMean,
+    WhiteBalanceGains,
+    AwbCount,
+    AwbHistory,
+    Statistics,
+    Exposure,
+    ClaheMapping,
+    Lut,
+    Curves,
+    ColorAdjust,
 }
 
 pub struct PT;
@@ -91,8 +230,123 @@ impl Buffers {
             Buffers::RGB => AbstractBuffer {
                 name,
                 memory_req: MemoryReq::Strict,
-                usage: BufferUsages::MAP_READ | BufferUsages::STORAGE | BufferUsages::COPY_SRC,
-                size: (params.byte_size() * 4) as u64,
+                usage: BufferUsages::MAP_READ
+                    | BufferUsages::STORAGE
+                    | BufferUsages::COPY_SRC
+                    | BufferUsages::COPY_DST,
+                size: (params.output_byte_size() * 4) as u64,
+            },
+            // Output of the stages that can't work in place on Buffers::RGB,
+            // and the intermediates of the RCD demosaic
+            Buffers::Scratch => AbstractBuffer {
+                name,
+                memory_req: MemoryReq::Temporary,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+                size: (params.output_byte_size() * 4) as u64,
+            },
+            Buffers::GreenEqualization => AbstractBuffer {
+                name,
+                memory_req: MemoryReq::Temporary,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+                size: params.byte_size() as u64,
+            },
+            Buffers::Denoise => AbstractBuffer {
+                name,
+                memory_req: MemoryReq::Temporary,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+                size: params.byte_size() as u64,
+            },
+            Buffers::TemporalHistory => AbstractBuffer {
+                name,
+                memory_req: MemoryReq::Strict,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+                size: (params.byte_size() * 2) as u64,
+            },
+            Buffers::AwbMaximum => AbstractBuffer {
+                name,
+                memory_req: MemoryReq::Temporary,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+                size: size_of::<[u32; 4]>() as u64,
+            },
+            Buffers::AwbHistogram => AbstractBuffer {
+                name,
+                memory_req: MemoryReq::Temporary,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+                size: (4 * AWB_HISTOGRAM_BINS as usize * size_of::<u32>()) as u64,
+            },
+            Buffers::AwbGradients => AbstractBuffer {
+                name,
+                memory_req: MemoryReq::Temporary,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+                size: params.byte_size() as u64,
+            },
+            Buffers::AwbGradientMean => AbstractBuffer {
+                name,
+                memory_req: MemoryReq::Temporary,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+                size: size_of::<[f32; 4]>() as u64,
+            },
+            Buffers::WhiteBalanceGains => AbstractBuffer {
+                name,
+                memory_req: MemoryReq::Strict,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+                size: size_of::<[f32; 4]>() as u64,
+            },
+            Buffers::AwbCount => AbstractBuffer {
+                name,
+                memory_req: MemoryReq::Strict,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+                size: size_of::<u32>() as u64,
+            },
+            Buffers::AwbHistory => AbstractBuffer {
+                name,
+                memory_req: MemoryReq::Strict,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+                // Smoothed gains and a validity flag, padded to the WGSL struct size
+                size: size_of::<[f32; 8]>() as u64,
+            },
+            Buffers::Statistics => AbstractBuffer {
+                name,
+                memory_req: MemoryReq::Strict,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+                size: (size_of::<[u32; 4]>() + MAX_ZONES * size_of::<ZoneStats>()) as u64,
+            },
+            Buffers::Exposure => AbstractBuffer {
+                name,
+                memory_req: MemoryReq::Strict,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+                size: size_of::<Exposure>() as u64,
+            },
+            Buffers::ClaheMapping => AbstractBuffer {
+                name,
+                memory_req: MemoryReq::Temporary,
+                usage: BufferUsages::STORAGE,
+                size: (MAX_CLAHE_TILES * CLAHE_BINS * size_of::<f32>()) as u64,
+            },
+            Buffers::Lut => AbstractBuffer {
+                name,
+                memory_req: MemoryReq::Strict,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+                size: (size_of::<LutHeader>() + MAX_LUT_SIZE.pow(3) * size_of::<[f32; 4]>())
+                    as u64,
+            },
+            Buffers::Curves => AbstractBuffer {
+                name,
+                memory_req: MemoryReq::Strict,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+                size: (CURVE_LUT_SIZE * size_of::<[f32; 4]>()) as u64,
+            },
+            Buffers::ColorAdjust => AbstractBuffer {
+                name,
+                memory_req: MemoryReq::Strict,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+                size: size_of::<ColorAdjustParams>() as u64,
+            },
+            Buffers::Crosstalk => AbstractBuffer {
+                name,
+                memory_req: MemoryReq::Strict,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+                size: size_of::<CrosstalkKernels>() as u64,
             },
         }
     }
@@ -116,7 +370,6 @@ pub struct BlackLevel {
 )]
 #[repr(C)]
 pub struct BlackLevelPush {
-    #[ui(min = 1, max = 50)]
     pub r_offset: f32,
     pub gr_offset: f32,
     pub gb_offset: f32,
@@ -125,6 +378,28 @@ pub struct BlackLevelPush {
     pub beta: f32,
 }
 
+/// 3×3 crosstalk kernels for each CFA position, indexed `[row][col]` around
+/// the centre pixel. Each output pixel gets the kernel-weighted sum of its
+/// uncorrected neighbourhood added on top of its black level offset, so the
+/// all-zero default applies no correction.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    bytemuck::Pod,
+    bytemuck::Zeroable,
+    Default,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[repr(C)]
+pub struct CrosstalkKernels {
+    pub r: [[f32; 3]; 3],
+    pub gr: [[f32; 3]; 3],
+    pub gb: [[f32; 3]; 3],
+    pub b: [[f32; 3]; 3],
+}
+
 impl SequentialOperation for BlackLevel {
     type PT = PT;
 
@@ -139,7 +414,11 @@ impl SequentialOperation for BlackLevel {
     where
         Self: Sized,
     {
-        vec![Buffers::Raw.init(params), Buffers::BlackLevel.init(params)]
+        vec![
+            Buffers::Raw.init(params),
+            Buffers::BlackLevel.init(params),
+            Buffers::Crosstalk.init(params),
+        ]
     }
 
     fn create(
@@ -152,6 +431,7 @@ impl SequentialOperation for BlackLevel {
     {
         let raw = buffers.get::<Self>(Buffers::Raw);
         let black_level = buffers.get::<Self>(Buffers::BlackLevel);
+        let crosstalk = buffers.get::<Self>(Buffers::Crosstalk);
 
         let dispatch_size = [params.height as u32, params.width as u32, 1];
 
@@ -169,7 +449,7 @@ impl SequentialOperation for BlackLevel {
 
         let pipeline = shader.build(device)?;
 
-        let bindgroup = [(0, raw), (1, black_level)];
+        let bindgroup = [(0, raw), (1, black_level), (2, crosstalk)];
 
         let pass = FullComputePass::new(device, pipeline, &bindgroup);
 
@@ -185,20 +465,24 @@ impl SequentialOperation for BlackLevel {
         // if !args.black_level.enabled {
         //     return;
         // }
-        self.pass
-            .execute(encoder, bytemuck::bytes_of(&args.black_level_push))
+        // alpha and beta are part of the crosstalk kernels, only the offsets are pushed
+        let push = &args.black_level_push;
+        let offsets = [push.r_offset, push.gr_offset, push.gb_offset, push.b_offset];
+        self.pass.execute(encoder, bytemuck::cast_slice(&offsets))
     }
 }
 
 #[derive(Debug)]
-pub struct AutoWhiteBalance {
-    align: FullComputePass,
-    mean: MeanReduce,
-    reduction_length: u32,
-
-    gain_application: FullComputePass,
+pub struct TemporalDenoise {
+    pass: FullComputePass,
+    history_valid: bool,
 }
 
+/// Accumulates consecutive frames into [`Buffers::TemporalHistory`] and
+/// replaces the black level output with the running average. Pixels that
+/// differ from the history by more than `motion_threshold` standard
+/// deviations of the noise model (see [`ISPParams::noise_model`]) fall back
+/// to the new frame. `max_frames` caps the effective averaging length.
 #[derive(
     Clone,
     Copy,
@@ -211,18 +495,30 @@ pub struct AutoWhiteBalance {
     serde::Deserialize,
 )]
 #[repr(C)]
-pub struct AutoWhiteBalancePush {
-    pub gain: f32,
+pub struct TemporalDenoisePush {
+    pub enabled: i32,
+    pub max_frames: f32,
+    pub motion_threshold: f32,
 }
 
-impl SequentialOperation for AutoWhiteBalance {
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct TemporalDenoiseParams {
+    max_frames: f32,
+    motion_threshold: f32,
+    _padding: [f32; 2],
+    noise_a: [f32; 4],
+    noise_b: [f32; 4],
+}
+
+impl SequentialOperation for TemporalDenoise {
     type PT = PT;
 
-    fn enabled(_params: &PipelineParams<Self>) -> bool
+    fn enabled(params: &PipelineParams<Self>) -> bool
     where
         Self: Sized,
     {
-        true
+        !params.preview
     }
 
     fn buffers(params: &PipelineParams<Self>) -> Vec<AbstractBuffer<PT>>
@@ -231,9 +527,7 @@ impl SequentialOperation for AutoWhiteBalance {
     {
         vec![
             Buffers::BlackLevel.init(params),
-            Buffers::TempMean.init(params),
-            Buffers::Mean.init(params),
-            Buffers::AutoWhiteBalance.init(params),
+            Buffers::TemporalHistory.init(params),
         ]
     }
 
@@ -245,111 +539,120 @@ impl SequentialOperation for AutoWhiteBalance {
     where
         Self: Sized,
     {
-        let black_level = buffers.get_from_any(Buffers::BlackLevel);
-        let auto_white_balance = buffers.get_from_any(Buffers::AutoWhiteBalance);
-        let temp_mean = buffers.get_from_any(Buffers::TempMean);
-        let mean_buf = buffers.get_from_any(Buffers::Mean);
+        let frame = buffers.get::<Self>(Buffers::BlackLevel);
+        let history = buffers.get::<Self>(Buffers::TemporalHistory);
 
-        let dispatch_size = [(params.height as u32) / 2, (params.width as u32) / 2, 1];
+        let dispatch_size = [params.height as u32, params.width as u32, 1];
 
         let specs = ShaderSpecs::new((8, 32, 1))
             .direct_dispatcher(&dispatch_size)
             .extend_defs([
                 ("HEIGHT", params.height.into()),
                 ("WIDTH", params.width.into()),
-            ]);
+            ])
+            .push_constants(size_of::<TemporalDenoiseParams>() as u32);
 
         let shader = params
             .shader_processor
-            .process_by_name("bayer_to_vec4", specs)?;
-
-        let pipeline = shader.build(device)?;
-
-        let bindgroup = [(0, black_level), (1, temp_mean)];
-
-        let align = FullComputePass::new(device, pipeline, &bindgroup);
-
-        let mean = MeanReduce::new(
-            device,
-            temp_mean,
-            None,
-            None,
-            mean_buf,
-            8,
-            ShaderSpecs::new((256, 1, 1)),
-            24,
-            InputType::Vec4F32,
-        )?;
-
-        let dispatch_size = [(params.height as u32), (params.width as u32), 1];
-        let specs = ShaderSpecs::new((8, 32, 1))
-            .direct_dispatcher(&dispatch_size)
-            .extend_defs([
-                ("HEIGHT", params.height.into()),
-                ("WIDTH", params.width.into()),
-            ]);
+            .process_by_name("temporal_denoise", specs)?;
 
-        let shader = params
-            .shader_processor
-            .process_by_name("auto_white_balance", specs)?;
         let pipeline = shader.build(device)?;
 
-        let bindgroup = [(0, black_level), (1, auto_white_balance), (2, mean_buf)];
-        let gain_application = FullComputePass::new(device, pipeline, &bindgroup);
+        let bindgroup = [(0, frame), (1, history)];
 
-        let reduction_length = ((params.height * params.width) / 4) as u32;
+        let pass = FullComputePass::new(device, pipeline, &bindgroup);
 
         Ok(Self {
-            align,
-            mean,
-            reduction_length,
-            gain_application,
+            pass,
+            history_valid: false,
         })
     }
 
     fn execute(
         &mut self,
         encoder: &mut gpwgpu::utils::Encoder,
-        _buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+        buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
         args: &PipelineArgs<Self>,
     ) {
-        self.align.execute(encoder, &[]);
-        self.mean.execute(encoder, self.reduction_length);
-        self.gain_application
-            .execute(encoder, bytes_of(&args.auto_white_balance_push));
+        let push = &args.temporal_denoise_push;
+        if push.enabled == 0 {
+            self.history_valid = false;
+            return;
+        }
+        if !self.history_valid {
+            // Frames from before the accumulation was disabled are stale
+            encoder.clear_buffer(buffers.get::<Self>(Buffers::TemporalHistory), 0, None);
+            self.history_valid = true;
+        }
+
+        let (noise_a, noise_b) = args.noise_model();
+        let params = TemporalDenoiseParams {
+            max_frames: push.max_frames.max(1.),
+            motion_threshold: push.motion_threshold.max(1e-3),
+            _padding: [0.; 2],
+            noise_a,
+            noise_b,
+        };
+        self.pass.execute(encoder, bytes_of(&params));
     }
 }
 
 #[derive(Debug)]
-pub struct Debayer {
+pub struct Denoise {
     pass: FullComputePass,
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, UiMarker)]
-pub struct DebayerPush {
+/// Bilateral filter over same-colour Bayer neighbours. The range kernel
+/// follows a signal-dependent noise model, `variance = noise_a * x + noise_b`
+/// in the normalised units of the black level output, scaled by `strength`.
+/// `spatial_sigma` is measured in same-colour steps. A noise profile in
+/// [`ISPParams::noise_profile`] takes precedence over `noise_a` and `noise_b`.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    bytemuck::Pod,
+    bytemuck::Zeroable,
+    Default,
+    UiMarker,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[repr(C)]
+pub struct DenoisePush {
     pub enabled: i32,
+    pub strength: f32,
+    pub spatial_sigma: f32,
+    pub noise_a: f32,
+    pub noise_b: f32,
 }
 
-impl SequentialOperation for Debayer {
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct DenoiseParams {
+    enabled: i32,
+    strength: f32,
+    spatial_sigma: f32,
+    _padding: f32,
+    noise_a: [f32; 4],
+    noise_b: [f32; 4],
+}
+
+impl SequentialOperation for Denoise {
     type PT = PT;
 
-    fn enabled(_params: &PipelineParams<Self>) -> bool
+    fn enabled(params: &PipelineParams<Self>) -> bool
     where
         Self: Sized,
     {
-        true
+        !params.preview
     }
 
-    fn buffers(
-        params: &PipelineParams<Self>,
-    ) -> Vec<gpwgpu::automatic_buffers::AbstractBuffer<PT>>
+    fn buffers(params: &PipelineParams<Self>) -> Vec<AbstractBuffer<PT>>
     where
         Self: Sized,
     {
-        vec![
-            Buffers::AutoWhiteBalance.init(params),
-            Buffers::RGB.init(params),
-        ]
+        vec![Buffers::BlackLevel.init(params), Buffers::Denoise.init(params)]
     }
 
     fn create(
@@ -360,24 +663,28 @@ impl SequentialOperation for Debayer {
     where
         Self: Sized,
     {
-        let bayered = buffers.get::<Self>(Buffers::AutoWhiteBalance);
-        let debayered = buffers.get::<Self>(Buffers::RGB);
+        let input = buffers.get::<Self>(Buffers::BlackLevel);
+        let output = buffers.get::<Self>(Buffers::Denoise);
 
         let dispatch_size = [params.height as u32, params.width as u32, 1];
 
+        let radius = 2;
+
         let specs = ShaderSpecs::new((8, 32, 1))
             .direct_dispatcher(&dispatch_size)
             .extend_defs([
                 ("HEIGHT", params.height.into()),
                 ("WIDTH", params.width.into()),
-                ("PADDING", 2.into()),
-            ]);
+                ("RADIUS", radius.into()),
+                ("PADDING", (2 * radius).into()),
+            ])
+            .push_constants(size_of::<DenoiseParams>() as u32);
 
-        let shader = params.shader_processor.process_by_name("debayer", specs)?;
+        let shader = params.shader_processor.process_by_name("denoise", specs)?;
 
         let pipeline = shader.build(device)?;
 
-        let bindgroup = [(0, bayered), (1, debayered)];
+        let bindgroup = [(0, input), (1, output)];
 
         let pass = FullComputePass::new(device, pipeline, &bindgroup);
 
@@ -390,51 +697,116 @@ impl SequentialOperation for Debayer {
         _buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
         args: &PipelineArgs<Self>,
     ) {
-        // let push = if args.debayer.enabled { 1i32 } else { 0 };
-        self.pass
-            .execute(encoder, bytes_of(&args.debayer_push.enabled));
+        let push = &args.denoise_push;
+        let (noise_a, noise_b) = args.noise_model();
+        let params = DenoiseParams {
+            enabled: push.enabled,
+            strength: push.strength,
+            spatial_sigma: push.spatial_sigma,
+            _padding: 0.,
+            noise_a,
+            noise_b,
+        };
+        self.pass.execute(encoder, bytes_of(&params));
     }
 }
 
 #[derive(Debug)]
-pub struct RGBSpaceOperations {
+pub struct Statistics {
     pass: FullComputePass,
 }
 
+/// Largest statistics grid, [`Buffers::Statistics`] is allocated for it.
+pub const MAX_ZONES_VERTICAL: u32 = 32;
+pub const MAX_ZONES_HORIZONTAL: u32 = 32;
+const MAX_ZONES: usize = (MAX_ZONES_VERTICAL * MAX_ZONES_HORIZONTAL) as usize;
+
+/// Bins of [`ZoneStats::histogram`].
+pub const HISTOGRAM_BINS: usize = 64;
+/// Range of [`ZoneStats::histogram`] in stops of normalised luminance.
+pub const HISTOGRAM_EV_RANGE: (i32, i32) = (-12, 4);
+
+/// Per zone statistics for exposure, white balance and focus control, read
+/// back with [`crate::setup::State::read_stats`]. The frame is split into
+/// `zones_vertical` × `zones_horizontal` zones of whole RGGB quads, at most
+/// [`MAX_ZONES_VERTICAL`] × [`MAX_ZONES_HORIZONTAL`]. Pixels at or above
+/// `saturation` count as clipped, 0 disables the count.
 #[derive(
     Clone,
     Copy,
     Debug,
     bytemuck::Pod,
     bytemuck::Zeroable,
-    Default,
     UiMarker,
     serde::Serialize,
     serde::Deserialize,
 )]
 #[repr(C)]
-pub struct ColorCorrectionPush {
-    pub color_correction_matrix: glam::Mat4,
+#[serde(default)]
+pub struct StatisticsPush {
+    pub enabled: i32,
+    pub zones_vertical: u32,
+    pub zones_horizontal: u32,
+    pub saturation: f32,
 }
 
-#[derive(
-    Clone,
-    Copy,
-    Debug,
-    bytemuck::Pod,
-    bytemuck::Zeroable,
-    Default,
-    UiMarker,
-    serde::Serialize,
-    serde::Deserialize,
-)]
+impl Default for StatisticsPush {
+    fn default() -> Self {
+        Self {
+            enabled: 0,
+            zones_vertical: 12,
+            zones_horizontal: 16,
+            saturation: 0.,
+        }
+    }
+}
+
+/// Statistics of one zone, computed on the black level corrected Bayer data
+/// before white balance. Channels are in R, Gr, Gb, B order.
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
 #[repr(C)]
-pub struct GammaPush {
-    pub gain: f32,
-    pub gamma: f32,
+pub struct ZoneStats {
+    pub mean: [f32; 4],
+    /// Pixels at or above [`StatisticsPush::saturation`].
+    pub clipped: [u32; 4],
+    /// Mean squared Laplacian of green, higher is sharper.
+    pub sharpness: f32,
+    /// RGGB quads in the zone.
+    pub count: u32,
+    /// Quads by log2 of their Rec. 709 luminance, with [`HISTOGRAM_BINS`]
+    /// equal bins over [`HISTOGRAM_EV_RANGE`]. Quads outside the range go to
+    /// the first or last bin.
+    pub histogram: [u32; HISTOGRAM_BINS],
+    pub _padding: [u32; 2],
 }
 
-impl SequentialOperation for RGBSpaceOperations {
+/// The statistics grid from the last execution, zones in row-major order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FrameStatistics {
+    pub zones_vertical: u32,
+    pub zones_horizontal: u32,
+    pub zones: Vec<ZoneStats>,
+}
+
+impl FrameStatistics {
+    /// Parses the contents of [`Buffers::Statistics`].
+    pub fn from_words(words: &[u32]) -> Self {
+        let (zones_vertical, zones_horizontal) = (words[0], words[1]);
+        let len = (zones_vertical * zones_horizontal) as usize;
+        let zones = bytemuck::cast_slice::<u32, ZoneStats>(&words[4..])[..len].to_vec();
+        Self {
+            zones_vertical,
+            zones_horizontal,
+            zones,
+        }
+    }
+
+    pub fn zone(&self, row: u32, col: u32) -> &ZoneStats {
+        &self.zones[(row * self.zones_horizontal + col) as usize]
+    }
+}
+
+impl SequentialOperation for Statistics {
     type PT = PT;
 
     fn enabled(_params: &PipelineParams<Self>) -> bool
@@ -448,7 +820,10 @@ impl SequentialOperation for RGBSpaceOperations {
     where
         Self: Sized,
     {
-        vec![Buffers::RGB.init(params)]
+        vec![
+            Buffers::BlackLevel.init(params),
+            Buffers::Statistics.init(params),
+        ]
     }
 
     fn create(
@@ -459,19 +834,2527 @@ impl SequentialOperation for RGBSpaceOperations {
     where
         Self: Sized,
     {
-        let rgb = buffers.get::<Self>(Buffers::RGB);
-
-        let dispatch_size = [params.height as u32, params.width as u32, 1];
-
-        let specs = ShaderSpecs::new((8, 32, 1))
+        let input = buffers.get::<Self>(Buffers::BlackLevel);
+        let output = buffers.get::<Self>(Buffers::Statistics);
+
+        // One workgroup per zone of the largest grid, unused ones return early
+        let workgroup_size = (16, 16, 1);
+        let dispatch_size = [
+            MAX_ZONES_VERTICAL * workgroup_size.0,
+            MAX_ZONES_HORIZONTAL * workgroup_size.1,
+            1,
+        ];
+
+        let specs = ShaderSpecs::new(workgroup_size)
             .direct_dispatcher(&dispatch_size)
             .extend_defs([
                 ("HEIGHT", params.height.into()),
                 ("WIDTH", params.width.into()),
-            ])
-            .push_constants(100);
-
-        let shader = params.shader_processor.process_by_name("rgb_space", specs)?;
+                ("MAX_ZONES_VERTICAL", (MAX_ZONES_VERTICAL as i32).into()),
+                ("MAX_ZONES_HORIZONTAL", (MAX_ZONES_HORIZONTAL as i32).into()),
+                ("MAX_ZONES", (MAX_ZONES as i32).into()),
+                ("BINS", (HISTOGRAM_BINS as i32).into()),
+                ("MIN_EV", HISTOGRAM_EV_RANGE.0.into()),
+                ("MAX_EV", HISTOGRAM_EV_RANGE.1.into()),
+            ]);
+
+        let shader = params
+            .shader_processor
+            .process_by_name("statistics", specs)?;
+
+        let pipeline = shader.build(device)?;
+
+        let bindgroup = [(0, input), (1, output)];
+
+        let pass = FullComputePass::new(device, pipeline, &bindgroup);
+
+        Ok(Self { pass })
+    }
+
+    fn execute(
+        &mut self,
+        encoder: &mut gpwgpu::utils::Encoder,
+        _buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+        args: &PipelineArgs<Self>,
+    ) {
+        let push = &args.statistics_push;
+        // Auto exposure meters on the statistics
+        if push.enabled == 0 && args.auto_exposure_push.enabled == 0 {
+            return;
+        }
+        let params = [
+            push.zones_vertical,
+            push.zones_horizontal,
+            push.saturation.to_bits(),
+        ];
+        self.pass.execute(encoder, bytemuck::cast_slice(&params));
+    }
+}
+
+#[derive(Debug)]
+pub struct AutoExposure {
+    pass: FullComputePass,
+}
+
+/// Meters the luminance histograms of [`Statistics`], which run whenever this
+/// is enabled, and finds the gain that brings the metered luminance to
+/// `target`. [`MeteringMode::Highlight`] also keeps the `highlight_percentile`
+/// of luminance at or below `highlight_target`. The gain clamped to
+/// `min_gain..max_gain` is applied as digital gain in [`RGBSpaceOperations`],
+/// the unclamped one is a suggestion for the sensor exposure, see
+/// [`crate::setup::State::read_exposure`].
+#[derive(Clone, Copy, Debug, UiMarker, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct AutoExposurePush {
+    pub enabled: i32,
+    pub metering: MeteringMode,
+    pub target: f32,
+    pub highlight_percentile: f32,
+    pub highlight_target: f32,
+    pub min_gain: f32,
+    pub max_gain: f32,
+}
+
+impl Default for AutoExposurePush {
+    fn default() -> Self {
+        Self {
+            enabled: 0,
+            metering: MeteringMode::default(),
+            target: 0.18,
+            highlight_percentile: 99.,
+            highlight_target: 0.9,
+            min_gain: 0.125,
+            max_gain: 8.,
+        }
+    }
+}
+
+/// The discriminants are the values `auto_exposure.wgsl` switches on.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    UiMarker,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[repr(i32)]
+pub enum MeteringMode {
+    /// Mean log luminance over the whole frame.
+    #[default]
+    Average = 0,
+    /// Mean log luminance with a Gaussian weight towards the centre zones.
+    CenterWeighted = 1,
+    /// Average, lowered where it would push the highlights above
+    /// `highlight_target`.
+    Highlight = 2,
+}
+
+/// Result of [`AutoExposure`], kept in [`Buffers::Exposure`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+pub struct Exposure {
+    /// Applied by [`RGBSpaceOperations`], 1 while auto exposure is off.
+    pub digital_gain: f32,
+    /// Factor on the sensor exposure that would reach the target without
+    /// digital gain.
+    pub exposure_ratio: f32,
+    /// Normalised luminance the metering settled on, before any gain.
+    pub metered_luminance: f32,
+    pub _padding: f32,
+}
+
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct AutoExposureParams {
+    enabled: i32,
+    metering: i32,
+    target: f32,
+    highlight_percentile: f32,
+    highlight_target: f32,
+    min_gain: f32,
+    max_gain: f32,
+}
+
+impl SequentialOperation for AutoExposure {
+    type PT = PT;
+
+    fn enabled(_params: &PipelineParams<Self>) -> bool
+    where
+        Self: Sized,
+    {
+        true
+    }
+
+    fn buffers(params: &PipelineParams<Self>) -> Vec<AbstractBuffer<PT>>
+    where
+        Self: Sized,
+    {
+        vec![
+            Buffers::Statistics.init(params),
+            Buffers::Exposure.init(params),
+        ]
+    }
+
+    fn create(
+        device: &gpwgpu::wgpu::Device,
+        params: &PipelineParams<Self>,
+        buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+    ) -> Result<Self, PipelineError<Self>>
+    where
+        Self: Sized,
+    {
+        let stats = buffers.get::<Self>(Buffers::Statistics);
+        let exposure = buffers.get::<Self>(Buffers::Exposure);
+
+        let specs = ShaderSpecs::new((1, 1, 1))
+            .direct_dispatcher(&[1, 1, 1])
+            .extend_defs([
+                ("MAX_ZONES", (MAX_ZONES as i32).into()),
+                ("BINS", (HISTOGRAM_BINS as i32).into()),
+                ("MIN_EV", HISTOGRAM_EV_RANGE.0.into()),
+                ("MAX_EV", HISTOGRAM_EV_RANGE.1.into()),
+            ])
+            .push_constants(size_of::<AutoExposureParams>() as u32);
+
+        let shader = params
+            .shader_processor
+            .process_by_name("auto_exposure", specs)?;
+
+        let pipeline = shader.build(device)?;
+
+        let bindgroup = [(0, stats), (1, exposure)];
+
+        let pass = FullComputePass::new(device, pipeline, &bindgroup);
+
+        Ok(Self { pass })
+    }
+
+    fn execute(
+        &mut self,
+        encoder: &mut gpwgpu::utils::Encoder,
+        _buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+        args: &PipelineArgs<Self>,
+    ) {
+        // Runs when disabled too, to put the digital gain back to 1
+        let push = &args.auto_exposure_push;
+        let params = AutoExposureParams {
+            enabled: push.enabled,
+            metering: push.metering as i32,
+            target: push.target,
+            highlight_percentile: push.highlight_percentile,
+            highlight_target: push.highlight_target,
+            min_gain: push.min_gain,
+            max_gain: push.max_gain,
+        };
+        self.pass.execute(encoder, bytes_of(&params));
+    }
+}
+
+#[derive(Debug)]
+pub struct AutoWhiteBalance {
+    align: FullComputePass,
+    mean: MeanReduce,
+    reduction_length: u32,
+
+    maximum: FullComputePass,
+    histogram: FullComputePass,
+    gradients: FullComputePass,
+    gradient_mean: MeanReduce,
+    gains: FullComputePass,
+
+    gain_application: FullComputePass,
+}
+
+/// Bins per channel of the histogram behind [`WhiteBalanceAlgorithm::Percentile`].
+const AWB_HISTOGRAM_BINS: i32 = 256;
+
+/// White balance from statistics of the RGGB quads. The white point is found
+/// with `algorithm`, and red and blue are scaled to match its green. `gain`
+/// scales all channels. `percentile` is only used by
+/// [`WhiteBalanceAlgorithm::Percentile`].
+///
+/// Only quads inside the region of interest, given by `roi_top`, `roi_left`,
+/// `roi_bottom` and `roi_right` as fractions of the image, are used for the
+/// estimate. Quads with any channel at or above `saturation` or below
+/// `noise_floor` are left out as well, a value of 0 disables either test. The
+/// number of quads that were used is in [`Buffers::AwbCount`].
+///
+/// With a `time_constant` above 0, the estimated gains are smoothed across
+/// executions by an exponential moving average with that time constant in
+/// frames. The average is kept in [`Buffers::AwbHistory`], and
+/// [`crate::setup::State::execute`] starts it again when `algorithm` or `mode`
+/// changes.
+///
+/// The manual modes skip the estimate: [`WhiteBalanceMode::Gains`] applies
+/// `red_gain`, `green_gain` and `blue_gain` as they are, and
+/// [`WhiteBalanceMode::Temperature`] derives gains from `temperature` in
+/// kelvin and `tint` with [`ISPParams::camera_calibration`].
+#[derive(Clone, Copy, Debug, UiMarker, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct AutoWhiteBalancePush {
+    pub gain: f32,
+    pub mode: WhiteBalanceMode,
+    pub algorithm: WhiteBalanceAlgorithm,
+    pub percentile: f32,
+    pub saturation: f32,
+    pub noise_floor: f32,
+    pub roi_top: f32,
+    pub roi_left: f32,
+    pub roi_bottom: f32,
+    pub roi_right: f32,
+    pub red_gain: f32,
+    pub green_gain: f32,
+    pub blue_gain: f32,
+    // color::TEMPERATURE_RANGE
+    #[ui(min = 1667, max = 25000)]
+    pub temperature: f32,
+    pub tint: f32,
+    pub time_constant: f32,
+}
+
+impl Default for AutoWhiteBalancePush {
+    fn default() -> Self {
+        Self {
+            gain: 1.,
+            mode: WhiteBalanceMode::default(),
+            algorithm: WhiteBalanceAlgorithm::default(),
+            percentile: 99.,
+            saturation: 0.,
+            noise_floor: 0.,
+            roi_top: 0.,
+            roi_left: 0.,
+            roi_bottom: 1.,
+            roi_right: 1.,
+            red_gain: 1.,
+            green_gain: 1.,
+            blue_gain: 1.,
+            temperature: 5000.,
+            tint: 0.,
+            time_constant: 0.,
+        }
+    }
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    UiMarker,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum WhiteBalanceMode {
+    /// Estimate the white point with [`AutoWhiteBalancePush::algorithm`].
+    #[default]
+    Auto,
+    /// Explicit R, G and B gains.
+    Gains,
+    /// Colour temperature and tint.
+    Temperature,
+}
+
+/// How [`AutoWhiteBalance`] estimates the white point. The discriminants are
+/// the values `awb_gains.wgsl` switches on.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    UiMarker,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[repr(i32)]
+pub enum WhiteBalanceAlgorithm {
+    /// The mean of the frame is grey.
+    #[default]
+    GrayWorld = 0,
+    /// The brightest value of each channel is white (max-RGB).
+    WhitePatch = 1,
+    /// The `percentile` of each channel is white. Less sensitive to single
+    /// bright pixels than the white patch.
+    Percentile = 2,
+    /// The mean gradient magnitude is grey, so flat coloured areas don't pull
+    /// the balance.
+    GrayEdge = 3,
+}
+
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct StatisticsParams {
+    saturation: f32,
+    noise_floor: f32,
+    _padding: [f32; 2],
+    roi: [f32; 4],
+}
+
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct AwbGainsParams {
+    gain: f32,
+    algorithm: i32,
+    percentile: f32,
+    manual: i32,
+    manual_gains: [f32; 4],
+    time_constant: f32,
+    _padding: [f32; 3],
+}
+
+impl AutoWhiteBalance {
+    /// The preview pipeline skips the denoisers.
+    fn input(params: &Params) -> Buffers {
+        if params.preview {
+            Buffers::BlackLevel
+        } else {
+            Buffers::Denoise
+        }
+    }
+}
+
+impl SequentialOperation for AutoWhiteBalance {
+    type PT = PT;
+
+    fn enabled(_params: &PipelineParams<Self>) -> bool
+    where
+        Self: Sized,
+    {
+        true
+    }
+
+    fn buffers(params: &PipelineParams<Self>) -> Vec<AbstractBuffer<PT>>
+    where
+        Self: Sized,
+    {
+        vec![
+            Self::input(params).init(params),
+            Buffers::TempMean.init(params),
+            Buffers::Mean.init(params),
+            Buffers::AwbMaximum.init(params),
+            Buffers::AwbHistogram.init(params),
+            Buffers::AwbGradients.init(params),
+            Buffers::AwbGradientMean.init(params),
+            Buffers::WhiteBalanceGains.init(params),
+            Buffers::AwbCount.init(params),
+            Buffers::AwbHistory.init(params),
+            Buffers::AutoWhiteBalance.init(params),
+        ]
+    }
+
+    fn create(
+        device: &gpwgpu::wgpu::Device,
+        params: &PipelineParams<Self>,
+        buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+    ) -> Result<Self, PipelineError<Self>>
+    where
+        Self: Sized,
+    {
+        let denoised = buffers.get_from_any(Self::input(params));
+        let auto_white_balance = buffers.get_from_any(Buffers::AutoWhiteBalance);
+        let temp_mean = buffers.get_from_any(Buffers::TempMean);
+        let mean_buf = buffers.get_from_any(Buffers::Mean);
+        let maximum_buf = buffers.get_from_any(Buffers::AwbMaximum);
+        let histogram_buf = buffers.get_from_any(Buffers::AwbHistogram);
+        let gradients_buf = buffers.get_from_any(Buffers::AwbGradients);
+        let gradient_mean_buf = buffers.get_from_any(Buffers::AwbGradientMean);
+        let gains_buf = buffers.get_from_any(Buffers::WhiteBalanceGains);
+        let count_buf = buffers.get_from_any(Buffers::AwbCount);
+        let history_buf = buffers.get_from_any(Buffers::AwbHistory);
+
+        let dispatch_size = [(params.height as u32) / 2, (params.width as u32) / 2, 1];
+
+        let specs = ShaderSpecs::new((8, 32, 1))
+            .direct_dispatcher(&dispatch_size)
+            .extend_defs([
+                ("HEIGHT", params.height.into()),
+                ("WIDTH", params.width.into()),
+            ])
+            .push_constants(size_of::<StatisticsParams>() as u32);
+
+        let shader = params
+            .shader_processor
+            .process_by_name("bayer_to_vec4", specs)?;
+
+        let pipeline = shader.build(device)?;
+
+        let bindgroup = [(0, denoised), (1, temp_mean), (2, count_buf)];
+
+        let align = FullComputePass::new(device, pipeline, &bindgroup);
+
+        let mean = MeanReduce::new(
+            device,
+            temp_mean,
+            None,
+            None,
+            mean_buf,
+            8,
+            ShaderSpecs::new((256, 1, 1)),
+            24,
+            InputType::Vec4F32,
+        )?;
+
+        let reduction_length = ((params.height * params.width) / 4) as u32;
+
+        // The quads as a flat list, for the max and histogram passes
+        let flat_specs = || {
+            ShaderSpecs::new((256, 1, 1))
+                .direct_dispatcher(&[reduction_length, 1, 1])
+                .extend_defs([
+                    ("LENGTH", (reduction_length as i32).into()),
+                    ("BINS", AWB_HISTOGRAM_BINS.into()),
+                ])
+        };
+
+        let shader = params
+            .shader_processor
+            .process_by_name("awb_max", flat_specs())?;
+        let pipeline = shader.build(device)?;
+        let bindgroup = [(0, temp_mean), (1, maximum_buf)];
+        let maximum = FullComputePass::new(device, pipeline, &bindgroup);
+
+        let shader = params
+            .shader_processor
+            .process_by_name("awb_histogram", flat_specs())?;
+        let pipeline = shader.build(device)?;
+        let bindgroup = [(0, temp_mean), (1, maximum_buf), (2, histogram_buf)];
+        let histogram = FullComputePass::new(device, pipeline, &bindgroup);
+
+        let specs = ShaderSpecs::new((8, 32, 1))
+            .direct_dispatcher(&dispatch_size)
+            .extend_defs([
+                ("HEIGHT", params.height.into()),
+                ("WIDTH", params.width.into()),
+            ]);
+
+        let shader = params
+            .shader_processor
+            .process_by_name("awb_gradients", specs)?;
+        let pipeline = shader.build(device)?;
+        let bindgroup = [(0, temp_mean), (1, gradients_buf)];
+        let gradients = FullComputePass::new(device, pipeline, &bindgroup);
+
+        let gradient_mean = MeanReduce::new(
+            device,
+            gradients_buf,
+            None,
+            None,
+            gradient_mean_buf,
+            8,
+            ShaderSpecs::new((256, 1, 1)),
+            24,
+            InputType::Vec4F32,
+        )?;
+
+        let specs = ShaderSpecs::new((1, 1, 1))
+            .direct_dispatcher(&[1, 1, 1])
+            .extend_defs([
+                ("BINS", AWB_HISTOGRAM_BINS.into()),
+                ("LENGTH", (reduction_length as i32).into()),
+            ])
+            .push_constants(size_of::<AwbGainsParams>() as u32);
+
+        let shader = params
+            .shader_processor
+            .process_by_name("awb_gains", specs)?;
+        let pipeline = shader.build(device)?;
+        let bindgroup = [
+            (0, mean_buf),
+            (1, maximum_buf),
+            (2, histogram_buf),
+            (3, gradient_mean_buf),
+            (4, gains_buf),
+            (5, count_buf),
+            (6, history_buf),
+        ];
+        let gains = FullComputePass::new(device, pipeline, &bindgroup);
+
+        let dispatch_size = [(params.height as u32), (params.width as u32), 1];
+        let specs = ShaderSpecs::new((8, 32, 1))
+            .direct_dispatcher(&dispatch_size)
+            .extend_defs([
+                ("HEIGHT", params.height.into()),
+                ("WIDTH", params.width.into()),
+            ]);
+
+        let shader = params
+            .shader_processor
+            .process_by_name("auto_white_balance", specs)?;
+        let pipeline = shader.build(device)?;
+
+        let bindgroup = [(0, denoised), (1, auto_white_balance), (2, gains_buf)];
+        let gain_application = FullComputePass::new(device, pipeline, &bindgroup);
+
+        Ok(Self {
+            align,
+            mean,
+            reduction_length,
+            maximum,
+            histogram,
+            gradients,
+            gradient_mean,
+            gains,
+            gain_application,
+        })
+    }
+
+    fn execute(
+        &mut self,
+        encoder: &mut gpwgpu::utils::Encoder,
+        buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+        args: &PipelineArgs<Self>,
+    ) {
+        let push = &args.auto_white_balance_push;
+
+        let statistics = StatisticsParams {
+            saturation: push.saturation,
+            noise_floor: push.noise_floor,
+            _padding: [0.; 2],
+            roi: [push.roi_top, push.roi_left, push.roi_bottom, push.roi_right],
+        };
+        encoder.clear_buffer(buffers.get::<Self>(Buffers::AwbCount), 0, None);
+        self.align.execute(encoder, bytes_of(&statistics));
+        // GreenEqualization uses the mean regardless of the algorithm
+        self.mean.execute(encoder, self.reduction_length);
+
+        let manual_gains = args.manual_white_balance_gains();
+
+        // The manual modes don't need any statistics besides the mean
+        if manual_gains.is_none() {
+            match push.algorithm {
+                WhiteBalanceAlgorithm::GrayWorld => {}
+                WhiteBalanceAlgorithm::WhitePatch | WhiteBalanceAlgorithm::Percentile => {
+                    encoder.clear_buffer(buffers.get::<Self>(Buffers::AwbMaximum), 0, None);
+                    self.maximum.execute(encoder, &[]);
+                    if push.algorithm == WhiteBalanceAlgorithm::Percentile {
+                        encoder.clear_buffer(buffers.get::<Self>(Buffers::AwbHistogram), 0, None);
+                        self.histogram.execute(encoder, &[]);
+                    }
+                }
+                WhiteBalanceAlgorithm::GrayEdge => {
+                    self.gradients.execute(encoder, &[]);
+                    self.gradient_mean.execute(encoder, self.reduction_length);
+                }
+            }
+        }
+
+        let params = AwbGainsParams {
+            gain: push.gain,
+            algorithm: push.algorithm as i32,
+            percentile: push.percentile,
+            manual: manual_gains.is_some() as i32,
+            manual_gains: manual_gains.unwrap_or([1.; 4]),
+            time_constant: push.time_constant.max(0.),
+            _padding: [0.; 3],
+        };
+        self.gains.execute(encoder, bytes_of(&params));
+
+        self.gain_application.execute(encoder, &[]);
+    }
+}
+
+#[derive(Debug)]
+pub struct GreenEqualization {
+    pass: FullComputePass,
+}
+
+/// Removes the Gr/Gb imbalance before demosaicing. With `local` unset the
+/// green channels are scaled by the global means in [`Buffers::Mean`],
+/// otherwise each green pixel is compared with the other green channel in its
+/// 5×5 neighbourhood and corrected where the relative difference is below
+/// `threshold`.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    bytemuck::Pod,
+    bytemuck::Zeroable,
+    Default,
+    UiMarker,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[repr(C)]
+pub struct GreenEqualizationPush {
+    pub enabled: i32,
+    pub local: i32,
+    pub threshold: f32,
+    pub strength: f32,
+}
+
+impl SequentialOperation for GreenEqualization {
+    type PT = PT;
+
+    fn enabled(params: &PipelineParams<Self>) -> bool
+    where
+        Self: Sized,
+    {
+        !params.preview
+    }
+
+    fn buffers(params: &PipelineParams<Self>) -> Vec<AbstractBuffer<PT>>
+    where
+        Self: Sized,
+    {
+        vec![
+            Buffers::AutoWhiteBalance.init(params),
+            Buffers::Mean.init(params),
+            Buffers::GreenEqualization.init(params),
+        ]
+    }
+
+    fn create(
+        device: &gpwgpu::wgpu::Device,
+        params: &PipelineParams<Self>,
+        buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+    ) -> Result<Self, PipelineError<Self>>
+    where
+        Self: Sized,
+    {
+        let input = buffers.get::<Self>(Buffers::AutoWhiteBalance);
+        let mean = buffers.get::<Self>(Buffers::Mean);
+        let output = buffers.get::<Self>(Buffers::GreenEqualization);
+
+        let dispatch_size = [params.height as u32, params.width as u32, 1];
+
+        let specs = ShaderSpecs::new((8, 32, 1))
+            .direct_dispatcher(&dispatch_size)
+            .extend_defs([
+                ("HEIGHT", params.height.into()),
+                ("WIDTH", params.width.into()),
+                ("PADDING", 2.into()),
+            ]);
+
+        let shader = params
+            .shader_processor
+            .process_by_name("green_equalization", specs)?;
+
+        let pipeline = shader.build(device)?;
+
+        let bindgroup = [(0, input), (1, output), (2, mean)];
+
+        let pass = FullComputePass::new(device, pipeline, &bindgroup);
+
+        Ok(Self { pass })
+    }
+
+    fn execute(
+        &mut self,
+        encoder: &mut gpwgpu::utils::Encoder,
+        _buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+        args: &PipelineArgs<Self>,
+    ) {
+        self.pass
+            .execute(encoder, bytes_of(&args.green_equalization_push));
+    }
+}
+
+#[derive(Debug)]
+pub struct Debayer {
+    pass: FullComputePass,
+    /// The `rcd_*.wgsl` passes, in order, which run instead of `pass` for
+    /// [`DemosaicAlgorithm::Rcd`]
+    rcd: [FullComputePass; 4],
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, UiMarker)]
+pub struct DebayerPush {
+    pub enabled: i32,
+    #[serde(default)]
+    pub algorithm: DemosaicAlgorithm,
+}
+
+/// Interpolation used by [`Debayer`]. The discriminants are the values
+/// `debayer.wgsl` switches on, and [`crate::demosaic`] has a CPU reference
+/// for each of them.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    UiMarker,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[repr(i32)]
+pub enum DemosaicAlgorithm {
+    /// Averages of the nearest samples of each colour. Fastest, softest.
+    Bilinear = 0,
+    /// Malvar-He-Cutler: bilinear with Laplacian corrections from the other
+    /// channels.
+    #[default]
+    Malvar = 1,
+    /// Hamilton-Adams: green is interpolated along the direction with the
+    /// smaller gradient, red and blue from colour differences to green.
+    HamiltonAdams = 2,
+    /// Ratio Corrected Demosaicing: green from ratio corrected neighbours,
+    /// blended by the local vertical and horizontal high-pass energy, then
+    /// red and blue from colour differences along the diagonals and the
+    /// cardinal directions. Sharpest on fine detail, four passes instead of
+    /// one.
+    Rcd = 3,
+}
+
+impl SequentialOperation for Debayer {
+    type PT = PT;
+
+    fn enabled(params: &PipelineParams<Self>) -> bool
+    where
+        Self: Sized,
+    {
+        !params.preview
+    }
+
+    fn buffers(
+        params: &PipelineParams<Self>,
+    ) -> Vec<gpwgpu::automatic_buffers::AbstractBuffer<PT>>
+    where
+        Self: Sized,
+    {
+        vec![
+            Buffers::GreenEqualization.init(params),
+            Buffers::RGB.init(params),
+            Buffers::Scratch.init(params),
+        ]
+    }
+
+    fn create(
+        device: &gpwgpu::wgpu::Device,
+        params: &PipelineParams<Self>,
+        buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+    ) -> Result<Self, PipelineError<Self>>
+    where
+        Self: Sized,
+    {
+        let bayered = buffers.get::<Self>(Buffers::GreenEqualization);
+        let debayered = buffers.get::<Self>(Buffers::RGB);
+        let scratch = buffers.get::<Self>(Buffers::Scratch);
+
+        let dispatch_size = [params.height as u32, params.width as u32, 1];
+
+        let specs = ShaderSpecs::new((8, 32, 1))
+            .direct_dispatcher(&dispatch_size)
+            .extend_defs([
+                ("HEIGHT", params.height.into()),
+                ("WIDTH", params.width.into()),
+                ("PADDING", 3.into()),
+            ]);
+
+        let shader = params.shader_processor.process_by_name("debayer", specs)?;
+
+        let pipeline = shader.build(device)?;
+
+        let bindgroup = [(0, bayered), (1, debayered)];
+
+        let pass = FullComputePass::new(device, pipeline, &bindgroup);
+
+        // The intermediates ping-pong between Buffers::Scratch and
+        // Buffers::RGB, no pass reads the buffer it writes
+        let rcd_pipeline = |name: &str| {
+            let specs = ShaderSpecs::new((8, 32, 1))
+                .direct_dispatcher(&dispatch_size)
+                .extend_defs([
+                    ("HEIGHT", params.height.into()),
+                    ("WIDTH", params.width.into()),
+                ]);
+            let shader = params.shader_processor.process_by_name(name, specs)?;
+            Ok::<_, PipelineError<Self>>(shader.build(device)?)
+        };
+        let rcd = [
+            FullComputePass::new(
+                device,
+                rcd_pipeline("rcd_directions")?,
+                &[(0, bayered), (1, scratch)],
+            ),
+            FullComputePass::new(
+                device,
+                rcd_pipeline("rcd_green")?,
+                &[(0, bayered), (1, scratch), (2, debayered)],
+            ),
+            FullComputePass::new(
+                device,
+                rcd_pipeline("rcd_red_blue")?,
+                &[(0, bayered), (1, debayered), (2, scratch)],
+            ),
+            FullComputePass::new(
+                device,
+                rcd_pipeline("rcd_green_sites")?,
+                &[(0, scratch), (1, debayered)],
+            ),
+        ];
+
+        Ok(Self { pass, rcd })
+    }
+
+    fn execute(
+        &mut self,
+        encoder: &mut gpwgpu::utils::Encoder,
+        _buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+        args: &PipelineArgs<Self>,
+    ) {
+        let push = &args.debayer_push;
+        if push.enabled != 0 && push.algorithm == DemosaicAlgorithm::Rcd {
+            for pass in &mut self.rcd {
+                pass.execute(encoder, &[]);
+            }
+            return;
+        }
+        // let push = if args.debayer.enabled { 1i32 } else { 0 };
+        let push = [push.enabled, push.algorithm as i32];
+        self.pass.execute(encoder, bytemuck::cast_slice(&push));
+    }
+}
+
+/// Preview replacement for [`GreenEqualization`] and [`Debayer`]: every RGGB
+/// quad becomes one pixel of a half resolution RGB image, with the two greens
+/// averaged.
+#[derive(Debug)]
+pub struct BinQuads {
+    pass: FullComputePass,
+}
+
+impl SequentialOperation for BinQuads {
+    type PT = PT;
+
+    fn enabled(params: &PipelineParams<Self>) -> bool
+    where
+        Self: Sized,
+    {
+        params.preview
+    }
+
+    fn buffers(params: &PipelineParams<Self>) -> Vec<AbstractBuffer<PT>>
+    where
+        Self: Sized,
+    {
+        vec![
+            Buffers::AutoWhiteBalance.init(params),
+            Buffers::RGB.init(params),
+        ]
+    }
+
+    fn create(
+        device: &gpwgpu::wgpu::Device,
+        params: &PipelineParams<Self>,
+        buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+    ) -> Result<Self, PipelineError<Self>>
+    where
+        Self: Sized,
+    {
+        let bayered = buffers.get::<Self>(Buffers::AutoWhiteBalance);
+        let rgb = buffers.get::<Self>(Buffers::RGB);
+
+        let dispatch_size = [(params.height as u32) / 2, (params.width as u32) / 2, 1];
+
+        let specs = ShaderSpecs::new((8, 32, 1))
+            .direct_dispatcher(&dispatch_size)
+            .extend_defs([
+                ("HEIGHT", params.height.into()),
+                ("WIDTH", params.width.into()),
+            ]);
+
+        let shader = params.shader_processor.process_by_name("bin_quads", specs)?;
+
+        let pipeline = shader.build(device)?;
+
+        let bindgroup = [(0, bayered), (1, rgb)];
+
+        let pass = FullComputePass::new(device, pipeline, &bindgroup);
+
+        Ok(Self { pass })
+    }
+
+    fn execute(
+        &mut self,
+        encoder: &mut gpwgpu::utils::Encoder,
+        _buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+        _args: &PipelineArgs<Self>,
+    ) {
+        self.pass.execute(encoder, &[]);
+    }
+}
+
+#[derive(Debug)]
+pub struct HighlightRecovery {
+    pass: FullComputePass,
+}
+
+/// Repairs pixels of the demosaiced image where some channels reached the
+/// sensor white level, which the white balance gains otherwise turn into
+/// coloured, usually magenta, highlights. `white_level` is in the units of
+/// the raw input, before [`BlackLevelPush`] and [`RAW_SCALE`], and a channel
+/// counts as clipped from `threshold` times it, with the repair fading in up
+/// to the white level. See [`HighlightMode`] for the repairs.
+#[derive(Clone, Copy, Debug, UiMarker, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct HighlightRecoveryPush {
+    pub enabled: i32,
+    pub mode: HighlightMode,
+    // Up to the 16 bit raw maximum
+    #[ui(min = 0, max = 65535)]
+    pub white_level: f32,
+    pub threshold: f32,
+}
+
+impl Default for HighlightRecoveryPush {
+    fn default() -> Self {
+        Self {
+            enabled: 0,
+            mode: HighlightMode::default(),
+            white_level: RAW_SCALE,
+            threshold: 0.95,
+        }
+    }
+}
+
+/// The discriminants are the values `highlight_recovery.wgsl` switches on.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    UiMarker,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[repr(i32)]
+pub enum HighlightMode {
+    /// Every channel is clipped to the lowest white level after white
+    /// balance, so clipped highlights become neutral white.
+    Clip = 0,
+    /// Clipped pixels are blended towards grey at the mean of their channels,
+    /// which keeps more of their brightness than clipping.
+    Blend = 1,
+    /// Clipped channels are estimated from the unclipped channels of the
+    /// pixel, with the channel ratios of unclipped pixels nearby. Pixels with
+    /// every channel or every neighbour clipped are blended.
+    #[default]
+    Reconstruct = 2,
+}
+
+/// Neighbourhood radius of [`HighlightMode::Reconstruct`].
+const HIGHLIGHT_RADIUS: i32 = 4;
+
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct HighlightRecoveryParams {
+    white_level: [f32; 4],
+    mode: i32,
+    threshold: f32,
+    _padding: [f32; 2],
+}
+
+impl SequentialOperation for HighlightRecovery {
+    type PT = PT;
+
+    fn enabled(_params: &PipelineParams<Self>) -> bool
+    where
+        Self: Sized,
+    {
+        true
+    }
+
+    fn buffers(params: &PipelineParams<Self>) -> Vec<AbstractBuffer<PT>>
+    where
+        Self: Sized,
+    {
+        vec![
+            Buffers::RGB.init(params),
+            Buffers::WhiteBalanceGains.init(params),
+            Buffers::Scratch.init(params),
+        ]
+    }
+
+    fn create(
+        device: &gpwgpu::wgpu::Device,
+        params: &PipelineParams<Self>,
+        buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+    ) -> Result<Self, PipelineError<Self>>
+    where
+        Self: Sized,
+    {
+        let rgb = buffers.get::<Self>(Buffers::RGB);
+        let gains = buffers.get::<Self>(Buffers::WhiteBalanceGains);
+        let output = buffers.get::<Self>(Buffers::Scratch);
+
+        let (height, width) = (params.output_height(), params.output_width());
+        let dispatch_size = [height as u32, width as u32, 1];
+
+        let specs = ShaderSpecs::new((8, 32, 1))
+            .direct_dispatcher(&dispatch_size)
+            .extend_defs([
+                ("HEIGHT", height.into()),
+                ("WIDTH", width.into()),
+                ("PADDING", HIGHLIGHT_RADIUS.into()),
+            ])
+            .push_constants(size_of::<HighlightRecoveryParams>() as u32);
+
+        let shader = params
+            .shader_processor
+            .process_by_name("highlight_recovery", specs)?;
+
+        let pipeline = shader.build(device)?;
+
+        let bindgroup = [(0, rgb), (1, gains), (2, output)];
+
+        let pass = FullComputePass::new(device, pipeline, &bindgroup);
+
+        Ok(Self { pass })
+    }
+
+    fn execute(
+        &mut self,
+        encoder: &mut gpwgpu::utils::Encoder,
+        buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+        args: &PipelineArgs<Self>,
+    ) {
+        let push = &args.highlight_recovery_push;
+        if push.enabled == 0 {
+            return;
+        }
+        let [r, g, b] = args.normalized_white_levels();
+        let params = HighlightRecoveryParams {
+            white_level: [r, g, b, 0.],
+            mode: push.mode as i32,
+            threshold: push.threshold,
+            _padding: [0.; 2],
+        };
+        self.pass.execute(encoder, bytes_of(&params));
+        copy_scratch_to_rgb(encoder, buffers);
+    }
+}
+
+#[derive(Debug)]
+pub struct FalseColorSuppression {
+    pass: FullComputePass,
+}
+
+/// Median filters the R-G and B-G differences of the demosaiced image in a
+/// 3×3 window to remove colour moiré and zipper artifacts. `strength` blends
+/// between the demosaiced chroma (0) and the median (1). Green is untouched.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    bytemuck::Pod,
+    bytemuck::Zeroable,
+    Default,
+    UiMarker,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[repr(C)]
+pub struct FalseColorSuppressionPush {
+    pub enabled: i32,
+    pub strength: f32,
+}
+
+impl SequentialOperation for FalseColorSuppression {
+    type PT = PT;
+
+    fn enabled(params: &PipelineParams<Self>) -> bool
+    where
+        Self: Sized,
+    {
+        !params.preview
+    }
+
+    fn buffers(params: &PipelineParams<Self>) -> Vec<AbstractBuffer<PT>>
+    where
+        Self: Sized,
+    {
+        vec![
+            Buffers::RGB.init(params),
+            Buffers::Scratch.init(params),
+        ]
+    }
+
+    fn create(
+        device: &gpwgpu::wgpu::Device,
+        params: &PipelineParams<Self>,
+        buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+    ) -> Result<Self, PipelineError<Self>>
+    where
+        Self: Sized,
+    {
+        let rgb = buffers.get::<Self>(Buffers::RGB);
+        let output = buffers.get::<Self>(Buffers::Scratch);
+
+        let dispatch_size = [params.height as u32, params.width as u32, 1];
+
+        let specs = ShaderSpecs::new((8, 32, 1))
+            .direct_dispatcher(&dispatch_size)
+            .extend_defs([
+                ("HEIGHT", params.height.into()),
+                ("WIDTH", params.width.into()),
+                ("PADDING", 1.into()),
+            ]);
+
+        let shader = params
+            .shader_processor
+            .process_by_name("false_color_suppression", specs)?;
+
+        let pipeline = shader.build(device)?;
+
+        let bindgroup = [(0, rgb), (1, output)];
+
+        let pass = FullComputePass::new(device, pipeline, &bindgroup);
+
+        Ok(Self { pass })
+    }
+
+    fn execute(
+        &mut self,
+        encoder: &mut gpwgpu::utils::Encoder,
+        buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+        args: &PipelineArgs<Self>,
+    ) {
+        let push = &args.false_color_suppression_push;
+        if push.enabled == 0 {
+            return;
+        }
+        self.pass.execute(encoder, bytes_of(push));
+        copy_scratch_to_rgb(encoder, buffers);
+    }
+}
+
+#[derive(Debug)]
+pub struct LensCorrection {
+    pass: FullComputePass,
+    height: i32,
+    width: i32,
+}
+
+/// Geometric lens correction of [`Buffers::RGB`] with the Brown-Conrady model
+/// of [`crate::lens`]: radial coefficients `k1`, `k2`, `k3` and tangential
+/// coefficients `p1`, `p2`, around an optical centre offset from the image
+/// centre by `center_x` and `center_y` half widths and heights. Lateral
+/// chromatic aberration is corrected by scaling the positions of the red and
+/// blue channels by `red_scale` and `blue_scale` relative to green. With
+/// `crop` the image is zoomed in until no pixel samples outside the input,
+/// otherwise those pixels are black.
+#[derive(Clone, Copy, Debug, UiMarker, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct LensCorrectionPush {
+    pub enabled: i32,
+    pub k1: f32,
+    pub k2: f32,
+    pub k3: f32,
+    pub p1: f32,
+    pub p2: f32,
+    pub center_x: f32,
+    pub center_y: f32,
+    pub red_scale: f32,
+    pub blue_scale: f32,
+    pub filter: ResamplingFilter,
+    pub crop: i32,
+}
+
+impl Default for LensCorrectionPush {
+    fn default() -> Self {
+        Self {
+            enabled: 0,
+            k1: 0.,
+            k2: 0.,
+            k3: 0.,
+            p1: 0.,
+            p2: 0.,
+            center_x: 0.,
+            center_y: 0.,
+            red_scale: 1.,
+            blue_scale: 1.,
+            filter: ResamplingFilter::default(),
+            crop: 1,
+        }
+    }
+}
+
+/// The discriminants are the values `lens_correction.wgsl` switches on.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    UiMarker,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[repr(i32)]
+pub enum ResamplingFilter {
+    #[default]
+    Bilinear = 0,
+    /// Catmull-Rom, sharper than bilinear but may ring at hard edges.
+    Bicubic = 1,
+}
+
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct LensCorrectionParams {
+    k1: f32,
+    k2: f32,
+    k3: f32,
+    p1: f32,
+    p2: f32,
+    center_x: f32,
+    center_y: f32,
+    red_scale: f32,
+    blue_scale: f32,
+    filter: i32,
+    crop_scale: f32,
+    _padding: f32,
+}
+
+impl SequentialOperation for LensCorrection {
+    type PT = PT;
+
+    fn enabled(_params: &PipelineParams<Self>) -> bool
+    where
+        Self: Sized,
+    {
+        true
+    }
+
+    fn buffers(params: &PipelineParams<Self>) -> Vec<AbstractBuffer<PT>>
+    where
+        Self: Sized,
+    {
+        vec![
+            Buffers::RGB.init(params),
+            Buffers::Scratch.init(params),
+        ]
+    }
+
+    fn create(
+        device: &gpwgpu::wgpu::Device,
+        params: &PipelineParams<Self>,
+        buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+    ) -> Result<Self, PipelineError<Self>>
+    where
+        Self: Sized,
+    {
+        let rgb = buffers.get::<Self>(Buffers::RGB);
+        let output = buffers.get::<Self>(Buffers::Scratch);
+
+        let (height, width) = (params.output_height(), params.output_width());
+        let dispatch_size = [height as u32, width as u32, 1];
+
+        let specs = ShaderSpecs::new((8, 32, 1))
+            .direct_dispatcher(&dispatch_size)
+            .extend_defs([("HEIGHT", height.into()), ("WIDTH", width.into())])
+            .push_constants(size_of::<LensCorrectionParams>() as u32);
+
+        let shader = params
+            .shader_processor
+            .process_by_name("lens_correction", specs)?;
+
+        let pipeline = shader.build(device)?;
+
+        let bindgroup = [(0, rgb), (1, output)];
+
+        let pass = FullComputePass::new(device, pipeline, &bindgroup);
+
+        Ok(Self {
+            pass,
+            height,
+            width,
+        })
+    }
+
+    fn execute(
+        &mut self,
+        encoder: &mut gpwgpu::utils::Encoder,
+        buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+        args: &PipelineArgs<Self>,
+    ) {
+        let push = &args.lens_correction_push;
+        if push.enabled == 0 {
+            return;
+        }
+        let crop_scale = if push.crop != 0 {
+            crop_scale(push, self.height, self.width)
+        } else {
+            1.
+        };
+        let params = LensCorrectionParams {
+            k1: push.k1,
+            k2: push.k2,
+            k3: push.k3,
+            p1: push.p1,
+            p2: push.p2,
+            center_x: push.center_x,
+            center_y: push.center_y,
+            red_scale: push.red_scale,
+            blue_scale: push.blue_scale,
+            filter: push.filter as i32,
+            crop_scale,
+            _padding: 0.,
+        };
+        self.pass.execute(encoder, bytes_of(&params));
+        copy_scratch_to_rgb(encoder, buffers);
+    }
+}
+
+#[derive(Debug)]
+pub struct RGBSpaceOperations {
+    pass: FullComputePass,
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    bytemuck::Pod,
+    bytemuck::Zeroable,
+    Default,
+    UiMarker,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[repr(C)]
+pub struct ColorCorrectionPush {
+    pub color_correction_matrix: glam::Mat4,
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    bytemuck::Pod,
+    bytemuck::Zeroable,
+    Default,
+    UiMarker,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[repr(C)]
+pub struct GammaPush {
+    pub gain: f32,
+    pub gamma: f32,
+}
+
+/// Spline tone curves applied by [`Gamma`] instead of the gamma of
+/// [`GammaPush`] while enabled. Every channel goes through its own curve
+/// and then through `master`, see [`crate::curves`]. The curves are baked
+/// into [`Buffers::Curves`] whenever they change.
+#[derive(Clone, Debug, Default, PartialEq, UiMarker, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct CurvesPush {
+    pub enabled: i32,
+    pub master: ToneCurve,
+    pub red: ToneCurve,
+    pub green: ToneCurve,
+    pub blue: ToneCurve,
+}
+
+impl SequentialOperation for RGBSpaceOperations {
+    type PT = PT;
+
+    fn enabled(_params: &PipelineParams<Self>) -> bool
+    where
+        Self: Sized,
+    {
+        true
+    }
+
+    fn buffers(params: &PipelineParams<Self>) -> Vec<AbstractBuffer<PT>>
+    where
+        Self: Sized,
+    {
+        vec![Buffers::RGB.init(params), Buffers::Exposure.init(params)]
+    }
+
+    fn create(
+        device: &gpwgpu::wgpu::Device,
+        params: &PipelineParams<Self>,
+        buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+    ) -> Result<Self, PipelineError<Self>>
+    where
+        Self: Sized,
+    {
+        let rgb = buffers.get::<Self>(Buffers::RGB);
+        let exposure = buffers.get::<Self>(Buffers::Exposure);
+
+        let (height, width) = (params.output_height(), params.output_width());
+        let dispatch_size = [height as u32, width as u32, 1];
+
+        let specs = ShaderSpecs::new((8, 32, 1))
+            .direct_dispatcher(&dispatch_size)
+            .extend_defs([("HEIGHT", height.into()), ("WIDTH", width.into())])
+            .push_constants(size_of::<ColorCorrectionPush>() as u32);
+
+        let shader = params.shader_processor.process_by_name("rgb_space", specs)?;
+
+        let pipeline = shader.build(device)?;
+
+        let bindgroup = [(0, rgb), (1, exposure)];
+
+        let pass = FullComputePass::new(device, pipeline, &bindgroup);
+
+        Ok(Self { pass })
+    }
+
+    fn execute(
+        &mut self,
+        encoder: &mut gpwgpu::utils::Encoder,
+        _buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+        args: &PipelineArgs<Self>,
+    ) {
+        self.pass
+            .execute(encoder, bytes_of(&args.color_correction_push));
+    }
+}
+
+#[derive(Debug)]
+pub struct Defringe {
+    pass: FullComputePass,
+}
+
+/// Neighbourhood radius in which [`Defringe`] looks for edges.
+const DEFRINGE_RADIUS: i32 = 2;
+
+/// Removes the purple and green fringes of longitudinal chromatic aberration
+/// from the colour corrected image. Pixels whose 5×5 neighbourhood spans more
+/// than `threshold` in linear luma are next to an edge, fully so from twice
+/// that. Near edges, pixels whose OkLCh hue is within `purple_width` degrees
+/// of `purple_hue` or `green_width` degrees of `green_hue` lose `strength`
+/// of their chroma, fading out over another 15 degrees outside the bands. It
+/// runs on linear light, before the tone mapping and [`Gamma`].
+#[derive(Clone, Copy, Debug, UiMarker, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct DefringePush {
+    pub enabled: i32,
+    pub strength: f32,
+    pub threshold: f32,
+    #[ui(min = 0, max = 360)]
+    pub purple_hue: f32,
+    pub purple_width: f32,
+    #[ui(min = 0, max = 360)]
+    pub green_hue: f32,
+    pub green_width: f32,
+}
+
+impl Default for DefringePush {
+    fn default() -> Self {
+        Self {
+            enabled: 0,
+            strength: 1.,
+            threshold: 0.2,
+            purple_hue: 305.,
+            purple_width: 35.,
+            green_hue: 135.,
+            green_width: 30.,
+        }
+    }
+}
+
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct DefringeParams {
+    strength: f32,
+    threshold: f32,
+    purple_hue: f32,
+    purple_width: f32,
+    green_hue: f32,
+    green_width: f32,
+}
+
+impl SequentialOperation for Defringe {
+    type PT = PT;
+
+    fn enabled(_params: &PipelineParams<Self>) -> bool
+    where
+        Self: Sized,
+    {
+        true
+    }
+
+    fn buffers(params: &PipelineParams<Self>) -> Vec<AbstractBuffer<PT>>
+    where
+        Self: Sized,
+    {
+        vec![Buffers::RGB.init(params), Buffers::Scratch.init(params)]
+    }
+
+    fn create(
+        device: &gpwgpu::wgpu::Device,
+        params: &PipelineParams<Self>,
+        buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+    ) -> Result<Self, PipelineError<Self>>
+    where
+        Self: Sized,
+    {
+        let rgb = buffers.get::<Self>(Buffers::RGB);
+        let output = buffers.get::<Self>(Buffers::Scratch);
+
+        let (height, width) = (params.output_height(), params.output_width());
+        let dispatch_size = [height as u32, width as u32, 1];
+
+        let specs = ShaderSpecs::new((8, 32, 1))
+            .direct_dispatcher(&dispatch_size)
+            .extend_defs([
+                ("HEIGHT", height.into()),
+                ("WIDTH", width.into()),
+                ("PADDING", DEFRINGE_RADIUS.into()),
+            ])
+            .push_constants(size_of::<DefringeParams>() as u32);
+
+        let shader = params.shader_processor.process_by_name("defringe", specs)?;
+
+        let pipeline = shader.build(device)?;
+
+        let bindgroup = [(0, rgb), (1, output)];
+
+        let pass = FullComputePass::new(device, pipeline, &bindgroup);
+
+        Ok(Self { pass })
+    }
+
+    fn execute(
+        &mut self,
+        encoder: &mut gpwgpu::utils::Encoder,
+        buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+        args: &PipelineArgs<Self>,
+    ) {
+        let push = &args.defringe_push;
+        if push.enabled == 0 {
+            return;
+        }
+        let params = DefringeParams {
+            strength: push.strength,
+            threshold: push.threshold,
+            purple_hue: push.purple_hue,
+            purple_width: push.purple_width,
+            green_hue: push.green_hue,
+            green_width: push.green_width,
+        };
+        self.pass.execute(encoder, bytes_of(&params));
+        copy_scratch_to_rgb(encoder, buffers);
+    }
+}
+
+#[derive(Debug)]
+pub struct ColorAdjust {
+    pass: FullComputePass,
+}
+
+/// Saturation, vibrance and hue adjustments of the colour corrected image in
+/// OkLCh, see [`crate::color_adjust`] for the CPU reference. It runs on linear
+/// sRGB, before the tone mapping and [`Gamma`]. `saturation`
+/// scales the chroma of every colour, while `vibrance` adds to the chroma of
+/// weak colours and leaves already saturated ones alone. `hue_shift` rotates
+/// every hue by that many degrees, and `bands` adjusts hue, saturation and
+/// lightness per range of hues. The parameters are uploaded to
+/// [`Buffers::ColorAdjust`] on every execution.
+#[derive(Clone, Copy, Debug, PartialEq, UiMarker, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ColorAdjustPush {
+    pub enabled: i32,
+    pub saturation: f32,
+    pub vibrance: f32,
+    pub hue_shift: f32,
+    pub bands: HueBands,
+}
+
+impl Default for ColorAdjustPush {
+    fn default() -> Self {
+        Self {
+            enabled: 0,
+            saturation: 1.,
+            vibrance: 0.,
+            hue_shift: 0.,
+            bands: HueBands::default(),
+        }
+    }
+}
+
+impl ColorAdjustPush {
+    /// The contents of [`Buffers::ColorAdjust`].
+    pub fn params(&self) -> ColorAdjustParams {
+        ColorAdjustParams {
+            saturation: self.saturation,
+            vibrance: self.vibrance,
+            hue_shift: self.hue_shift,
+            _padding: 0.,
+            band_hue: self.bands.hue,
+            band_saturation: self.bands.saturation,
+            band_lightness: self.bands.lightness,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+pub struct ColorAdjustParams {
+    pub saturation: f32,
+    pub vibrance: f32,
+    pub hue_shift: f32,
+    pub _padding: f32,
+    pub band_hue: [f32; HUE_BANDS],
+    pub band_saturation: [f32; HUE_BANDS],
+    pub band_lightness: [f32; HUE_BANDS],
+}
+
+impl SequentialOperation for ColorAdjust {
+    type PT = PT;
+
+    fn enabled(_params: &PipelineParams<Self>) -> bool
+    where
+        Self: Sized,
+    {
+        true
+    }
+
+    fn buffers(params: &PipelineParams<Self>) -> Vec<AbstractBuffer<PT>>
+    where
+        Self: Sized,
+    {
+        vec![
+            Buffers::RGB.init(params),
+            Buffers::ColorAdjust.init(params),
+        ]
+    }
+
+    fn create(
+        device: &gpwgpu::wgpu::Device,
+        params: &PipelineParams<Self>,
+        buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+    ) -> Result<Self, PipelineError<Self>>
+    where
+        Self: Sized,
+    {
+        let rgb = buffers.get::<Self>(Buffers::RGB);
+        let color_adjust = buffers.get::<Self>(Buffers::ColorAdjust);
+
+        let (height, width) = (params.output_height(), params.output_width());
+        let dispatch_size = [height as u32, width as u32, 1];
+
+        let specs = ShaderSpecs::new((8, 32, 1))
+            .direct_dispatcher(&dispatch_size)
+            .extend_defs([("HEIGHT", height.into()), ("WIDTH", width.into())]);
+
+        let shader = params.shader_processor.process_by_name("color_adjust", specs)?;
+
+        let pipeline = shader.build(device)?;
+
+        let bindgroup = [(0, rgb), (1, color_adjust)];
+
+        let pass = FullComputePass::new(device, pipeline, &bindgroup);
+
+        Ok(Self { pass })
+    }
+
+    fn execute(
+        &mut self,
+        encoder: &mut gpwgpu::utils::Encoder,
+        _buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+        args: &PipelineArgs<Self>,
+    ) {
+        if args.color_adjust_push.enabled == 0 {
+            return;
+        }
+        self.pass.execute(encoder, &[]);
+    }
+}
+
+#[derive(Debug)]
+pub struct Sharpen {
+    pass: FullComputePass,
+}
+
+/// Largest blur `radius` of [`SharpenPush`], the shared memory tile of
+/// `sharpen.wgsl` is padded for it.
+pub const MAX_SHARPEN_RADIUS: i32 = 2;
+
+/// Unsharp mask of the luma of [`Buffers::RGB`]. The luma is blurred with a
+/// Gaussian whose standard deviation is `radius` pixels, and `amount` times
+/// the difference to the blur is added back where it exceeds `threshold`.
+/// The result may leave the luma range of its 3×3 neighbourhood by
+/// `overshoot` times that range, which limits the halos along edges. Chroma
+/// is untouched. It runs on linear light before the tone mapping and
+/// [`Gamma`], so `threshold` is a linear luma difference.
+#[derive(Clone, Copy, Debug, UiMarker, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SharpenPush {
+    pub enabled: i32,
+    pub radius: f32,
+    pub amount: f32,
+    pub threshold: f32,
+    pub overshoot: f32,
+}
+
+impl Default for SharpenPush {
+    fn default() -> Self {
+        Self {
+            enabled: 0,
+            radius: 1.,
+            amount: 0.5,
+            threshold: 0.,
+            overshoot: 0.1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct SharpenParams {
+    radius: f32,
+    amount: f32,
+    threshold: f32,
+    overshoot: f32,
+}
+
+impl SequentialOperation for Sharpen {
+    type PT = PT;
+
+    fn enabled(_params: &PipelineParams<Self>) -> bool
+    where
+        Self: Sized,
+    {
+        true
+    }
+
+    fn buffers(params: &PipelineParams<Self>) -> Vec<AbstractBuffer<PT>>
+    where
+        Self: Sized,
+    {
+        vec![Buffers::RGB.init(params), Buffers::Scratch.init(params)]
+    }
+
+    fn create(
+        device: &gpwgpu::wgpu::Device,
+        params: &PipelineParams<Self>,
+        buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+    ) -> Result<Self, PipelineError<Self>>
+    where
+        Self: Sized,
+    {
+        let rgb = buffers.get::<Self>(Buffers::RGB);
+        let output = buffers.get::<Self>(Buffers::Scratch);
+
+        let (height, width) = (params.output_height(), params.output_width());
+        let dispatch_size = [height as u32, width as u32, 1];
+
+        let specs = ShaderSpecs::new((8, 32, 1))
+            .direct_dispatcher(&dispatch_size)
+            .extend_defs([
+                ("HEIGHT", height.into()),
+                ("WIDTH", width.into()),
+                ("PADDING", (2 * MAX_SHARPEN_RADIUS).into()),
+            ])
+            .push_constants(size_of::<SharpenParams>() as u32);
+
+        let shader = params.shader_processor.process_by_name("sharpen", specs)?;
+
+        let pipeline = shader.build(device)?;
+
+        let bindgroup = [(0, rgb), (1, output)];
+
+        let pass = FullComputePass::new(device, pipeline, &bindgroup);
+
+        Ok(Self { pass })
+    }
+
+    fn execute(
+        &mut self,
+        encoder: &mut gpwgpu::utils::Encoder,
+        buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+        args: &PipelineArgs<Self>,
+    ) {
+        let push = &args.sharpen_push;
+        if push.enabled == 0 {
+            return;
+        }
+        let params = SharpenParams {
+            radius: push.radius,
+            amount: push.amount,
+            threshold: push.threshold,
+            overshoot: push.overshoot,
+        };
+        self.pass.execute(encoder, bytes_of(&params));
+        copy_scratch_to_rgb(encoder, buffers);
+    }
+}
+
+/// Applies the `.cube` LUT of [`ISPParams::lut_path`], either to the scene
+/// linear image or to the display referred one, see [`LutPosition`]. Both are
+/// in the pipeline and the one at the other position does nothing.
+#[derive(Debug)]
+pub struct Lut3D<const DISPLAY_REFERRED: bool> {
+    pass: FullComputePass,
+}
+
+pub type SceneLut3D = Lut3D<false>;
+pub type DisplayLut3D = Lut3D<true>;
+
+/// Largest `.cube` size, [`Buffers::Lut`] is allocated for it.
+pub const MAX_LUT_SIZE: usize = 65;
+
+#[derive(Clone, Copy, Debug, Default, UiMarker, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct LutPush {
+    pub enabled: i32,
+    pub position: LutPosition,
+    pub interpolation: LutInterpolation,
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    UiMarker,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum LutPosition {
+    /// On linear light after [`Sharpen`], before [`ToneMap`] and [`Gamma`].
+    SceneLinear,
+    /// After [`LocalToneMap`] and [`Gamma`], before [`OutputTransform`].
+    #[default]
+    DisplayReferred,
+}
+
+/// The discriminants are the values `lut_3d.wgsl` switches on.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    UiMarker,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[repr(i32)]
+pub enum LutInterpolation {
+    Trilinear = 0,
+    /// Interpolates within one of the six tetrahedra of every cell, which
+    /// keeps the neutral axis exact.
+    #[default]
+    Tetrahedral = 1,
+}
+
+/// Start of [`Buffers::Lut`], followed by the table with red changing
+/// fastest. A size of 0 means no LUT is loaded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+pub struct LutHeader {
+    pub size: u32,
+    pub _padding: [u32; 3],
+    pub domain_min: [f32; 4],
+    pub domain_max: [f32; 4],
+}
+
+impl<const DISPLAY_REFERRED: bool> SequentialOperation for Lut3D<DISPLAY_REFERRED> {
+    type PT = PT;
+
+    fn enabled(_params: &PipelineParams<Self>) -> bool
+    where
+        Self: Sized,
+    {
+        true
+    }
+
+    fn buffers(params: &PipelineParams<Self>) -> Vec<AbstractBuffer<PT>>
+    where
+        Self: Sized,
+    {
+        vec![Buffers::RGB.init(params), Buffers::Lut.init(params)]
+    }
+
+    fn create(
+        device: &gpwgpu::wgpu::Device,
+        params: &PipelineParams<Self>,
+        buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+    ) -> Result<Self, PipelineError<Self>>
+    where
+        Self: Sized,
+    {
+        let rgb = buffers.get::<Self>(Buffers::RGB);
+        let lut = buffers.get::<Self>(Buffers::Lut);
+
+        let (height, width) = (params.output_height(), params.output_width());
+        let dispatch_size = [height as u32, width as u32, 1];
+
+        let specs = ShaderSpecs::new((8, 32, 1))
+            .direct_dispatcher(&dispatch_size)
+            .extend_defs([("HEIGHT", height.into()), ("WIDTH", width.into())])
+            .push_constants(size_of::<i32>() as u32);
+
+        let shader = params.shader_processor.process_by_name("lut_3d", specs)?;
+
+        let pipeline = shader.build(device)?;
+
+        let bindgroup = [(0, rgb), (1, lut)];
+
+        let pass = FullComputePass::new(device, pipeline, &bindgroup);
+
+        Ok(Self { pass })
+    }
+
+    fn execute(
+        &mut self,
+        encoder: &mut gpwgpu::utils::Encoder,
+        _buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+        args: &PipelineArgs<Self>,
+    ) {
+        let push = &args.lut_push;
+        let display_referred = push.position == LutPosition::DisplayReferred;
+        if push.enabled == 0 || args.lut_path.is_none() || display_referred != DISPLAY_REFERRED {
+            return;
+        }
+        self.pass
+            .execute(encoder, bytes_of(&(push.interpolation as i32)));
+    }
+}
+
+#[derive(Debug)]
+pub struct ToneMap {
+    pass: FullComputePass,
+}
+
+/// Compresses the colour corrected scene linear image into the 0..1 range of
+/// the display with a global `operator`, after multiplying it by 2 to the
+/// power of `exposure`. `white` is the scene value that becomes display white.
+/// [`crate::tone_map`] has the CPU reference of every operator.
+#[derive(Clone, Copy, Debug, UiMarker, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ToneMapPush {
+    pub enabled: i32,
+    pub operator: ToneMapOperator,
+    pub exposure: f32,
+    pub white: f32,
+}
+
+impl Default for ToneMapPush {
+    fn default() -> Self {
+        Self {
+            enabled: 0,
+            operator: ToneMapOperator::default(),
+            exposure: 0.,
+            white: 16.,
+        }
+    }
+}
+
+/// The discriminants are the values `tone_map.wgsl` switches on.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    UiMarker,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[repr(i32)]
+pub enum ToneMapOperator {
+    /// Extended Reinhard, per channel.
+    #[default]
+    Reinhard = 0,
+    /// Hable's filmic curve, per channel.
+    Hable = 1,
+    /// The fitted ACES reference rendering.
+    Aces = 2,
+    /// AgX, which desaturates bright colours towards white instead of
+    /// skewing their hue.
+    Agx = 3,
+}
+
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct ToneMapParams {
+    operator: i32,
+    exposure: f32,
+    white: f32,
+}
+
+impl SequentialOperation for ToneMap {
+    type PT = PT;
+
+    fn enabled(_params: &PipelineParams<Self>) -> bool
+    where
+        Self: Sized,
+    {
+        true
+    }
+
+    fn buffers(params: &PipelineParams<Self>) -> Vec<AbstractBuffer<PT>>
+    where
+        Self: Sized,
+    {
+        vec![Buffers::RGB.init(params)]
+    }
+
+    fn create(
+        device: &gpwgpu::wgpu::Device,
+        params: &PipelineParams<Self>,
+        buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+    ) -> Result<Self, PipelineError<Self>>
+    where
+        Self: Sized,
+    {
+        let rgb = buffers.get::<Self>(Buffers::RGB);
+
+        let (height, width) = (params.output_height(), params.output_width());
+        let dispatch_size = [height as u32, width as u32, 1];
+
+        let specs = ShaderSpecs::new((8, 32, 1))
+            .direct_dispatcher(&dispatch_size)
+            .extend_defs([("HEIGHT", height.into()), ("WIDTH", width.into())])
+            .push_constants(size_of::<ToneMapParams>() as u32);
+
+        let shader = params.shader_processor.process_by_name("tone_map", specs)?;
+
+        let pipeline = shader.build(device)?;
+
+        let bindgroup = [(0, rgb)];
+
+        let pass = FullComputePass::new(device, pipeline, &bindgroup);
+
+        Ok(Self { pass })
+    }
+
+    fn execute(
+        &mut self,
+        encoder: &mut gpwgpu::utils::Encoder,
+        _buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+        args: &PipelineArgs<Self>,
+    ) {
+        let push = &args.tone_map_push;
+        if push.enabled == 0 {
+            return;
+        }
+        let params = ToneMapParams {
+            operator: push.operator as i32,
+            exposure: push.exposure,
+            white: push.white,
+        };
+        self.pass.execute(encoder, bytes_of(&params));
+    }
+}
+
+#[derive(Debug)]
+pub struct LocalToneMap {
+    histogram: FullComputePass,
+    apply: FullComputePass,
+    height: i32,
+    width: i32,
+}
+
+/// Largest CLAHE tile grid, [`Buffers::ClaheMapping`] is allocated for it.
+pub const MAX_CLAHE_TILES_VERTICAL: u32 = 16;
+pub const MAX_CLAHE_TILES_HORIZONTAL: u32 = 16;
+const MAX_CLAHE_TILES: usize = (MAX_CLAHE_TILES_VERTICAL * MAX_CLAHE_TILES_HORIZONTAL) as usize;
+
+/// Bins of the luma histogram of every CLAHE tile.
+pub const CLAHE_BINS: usize = 256;
+
+/// Contrast limited adaptive histogram equalisation of the luma of
+/// [`Buffers::RGB`], which is expected in the 0..1 range of the display, e.g.
+/// after [`ToneMap`]. The image is split into tiles of about `tile_size`
+/// pixels, larger where that would exceed [`MAX_CLAHE_TILES_VERTICAL`] ×
+/// [`MAX_CLAHE_TILES_HORIZONTAL`] tiles. No histogram bin of a tile may hold
+/// more than `clip_limit` times the mean count, the excess is spread over all
+/// bins. Every pixel blends the equalisations of its four nearest tiles, and
+/// `strength` blends between the original (0) and the equalised luma (1).
+/// Colours are scaled with their luma, so hue and saturation are kept.
+#[derive(Clone, Copy, Debug, UiMarker, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct LocalToneMapPush {
+    pub enabled: i32,
+    pub strength: f32,
+    pub tile_size: u32,
+    pub clip_limit: f32,
+}
+
+impl Default for LocalToneMapPush {
+    fn default() -> Self {
+        Self {
+            enabled: 0,
+            strength: 0.5,
+            tile_size: 128,
+            clip_limit: 2.,
+        }
+    }
+}
+
+impl LocalToneMapPush {
+    /// Tiles vertically and horizontally for an image of the given size.
+    pub fn tiles(&self, height: i32, width: i32) -> (u32, u32) {
+        let tile_size = self.tile_size.max(1);
+        let tiles = |pixels: i32, max: u32| (pixels as u32).div_ceil(tile_size).clamp(1, max);
+        (
+            tiles(height, MAX_CLAHE_TILES_VERTICAL),
+            tiles(width, MAX_CLAHE_TILES_HORIZONTAL),
+        )
+    }
+}
+
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct ClaheParams {
+    tiles_vertical: u32,
+    tiles_horizontal: u32,
+    clip_limit: f32,
+    strength: f32,
+}
+
+impl SequentialOperation for LocalToneMap {
+    type PT = PT;
+
+    fn enabled(_params: &PipelineParams<Self>) -> bool
+    where
+        Self: Sized,
+    {
+        true
+    }
+
+    fn buffers(params: &PipelineParams<Self>) -> Vec<AbstractBuffer<PT>>
+    where
+        Self: Sized,
+    {
+        vec![
+            Buffers::RGB.init(params),
+            Buffers::ClaheMapping.init(params),
+        ]
+    }
+
+    fn create(
+        device: &gpwgpu::wgpu::Device,
+        params: &PipelineParams<Self>,
+        buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+    ) -> Result<Self, PipelineError<Self>>
+    where
+        Self: Sized,
+    {
+        let rgb = buffers.get::<Self>(Buffers::RGB);
+        let mapping = buffers.get::<Self>(Buffers::ClaheMapping);
+
+        let (height, width) = (params.output_height(), params.output_width());
+
+        // One workgroup per tile of the largest grid, unused ones return early
+        let workgroup_size = (16, 16, 1);
+        let dispatch_size = [
+            MAX_CLAHE_TILES_VERTICAL * workgroup_size.0,
+            MAX_CLAHE_TILES_HORIZONTAL * workgroup_size.1,
+            1,
+        ];
+
+        let specs = ShaderSpecs::new(workgroup_size)
+            .direct_dispatcher(&dispatch_size)
+            .extend_defs([
+                ("HEIGHT", height.into()),
+                ("WIDTH", width.into()),
+                ("BINS", (CLAHE_BINS as i32).into()),
+            ])
+            .push_constants(size_of::<ClaheParams>() as u32);
+
+        let shader = params
+            .shader_processor
+            .process_by_name("clahe_histogram", specs)?;
+        let pipeline = shader.build(device)?;
+        let bindgroup = [(0, rgb), (1, mapping)];
+        let histogram = FullComputePass::new(device, pipeline, &bindgroup);
+
+        let dispatch_size = [height as u32, width as u32, 1];
+
+        let specs = ShaderSpecs::new((8, 32, 1))
+            .direct_dispatcher(&dispatch_size)
+            .extend_defs([
+                ("HEIGHT", height.into()),
+                ("WIDTH", width.into()),
+                ("BINS", (CLAHE_BINS as i32).into()),
+            ])
+            .push_constants(size_of::<ClaheParams>() as u32);
+
+        let shader = params
+            .shader_processor
+            .process_by_name("clahe_apply", specs)?;
+        let pipeline = shader.build(device)?;
+        let bindgroup = [(0, rgb), (1, mapping)];
+        let apply = FullComputePass::new(device, pipeline, &bindgroup);
+
+        Ok(Self {
+            histogram,
+            apply,
+            height,
+            width,
+        })
+    }
+
+    fn execute(
+        &mut self,
+        encoder: &mut gpwgpu::utils::Encoder,
+        _buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+        args: &PipelineArgs<Self>,
+    ) {
+        let push = &args.local_tone_map_push;
+        if push.enabled == 0 {
+            return;
+        }
+        let (tiles_vertical, tiles_horizontal) = push.tiles(self.height, self.width);
+        let params = ClaheParams {
+            tiles_vertical,
+            tiles_horizontal,
+            clip_limit: push.clip_limit,
+            strength: push.strength,
+        };
+        self.histogram.execute(encoder, bytes_of(&params));
+        self.apply.execute(encoder, bytes_of(&params));
+    }
+}
+
+/// Applies the gain and gamma of [`GammaPush`], or the curves of
+/// [`CurvesPush`] in their place, after the tone mapping. Everything before it
+/// works on linear light, the curves see the 0..1 display range the tone
+/// mapping leaves.
+#[derive(Debug)]
+pub struct Gamma {
+    pass: FullComputePass,
+}
+
+impl SequentialOperation for Gamma {
+    type PT = PT;
+
+    fn enabled(_params: &PipelineParams<Self>) -> bool
+    where
+        Self: Sized,
+    {
+        true
+    }
+
+    fn buffers(params: &PipelineParams<Self>) -> Vec<AbstractBuffer<PT>>
+    where
+        Self: Sized,
+    {
+        vec![Buffers::RGB.init(params), Buffers::Curves.init(params)]
+    }
+
+    fn create(
+        device: &gpwgpu::wgpu::Device,
+        params: &PipelineParams<Self>,
+        buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+    ) -> Result<Self, PipelineError<Self>>
+    where
+        Self: Sized,
+    {
+        let rgb = buffers.get::<Self>(Buffers::RGB);
+        let curves = buffers.get::<Self>(Buffers::Curves);
+
+        let (height, width) = (params.output_height(), params.output_width());
+        let dispatch_size = [height as u32, width as u32, 1];
+
+        let specs = ShaderSpecs::new((8, 32, 1))
+            .direct_dispatcher(&dispatch_size)
+            .extend_defs([
+                ("HEIGHT", height.into()),
+                ("WIDTH", width.into()),
+                ("CURVE_LUT_SIZE", (CURVE_LUT_SIZE as i32).into()),
+            ])
+            .push_constants(size_of::<GammaParams>() as u32);
+
+        let shader = params.shader_processor.process_by_name("gamma", specs)?;
+
+        let pipeline = shader.build(device)?;
+
+        let bindgroup = [(0, rgb), (1, curves)];
+
+        let pass = FullComputePass::new(device, pipeline, &bindgroup);
+
+        Ok(Self { pass })
+    }
+
+    fn execute(
+        &mut self,
+        encoder: &mut gpwgpu::utils::Encoder,
+        _buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+        args: &PipelineArgs<Self>,
+    ) {
+        let params = GammaParams {
+            gain: args.gamma_push.gain,
+            gamma: args.gamma_push.gamma,
+            curves: args.curves_push.enabled,
+        };
+        self.pass.execute(encoder, bytes_of(&params));
+    }
+}
+
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct GammaParams {
+    gain: f32,
+    gamma: f32,
+    curves: i32,
+}
+
+#[derive(Debug)]
+pub struct OutputTransform {
+    pass: FullComputePass,
+}
+
+/// Converts the colour corrected image to CIE XYZ with
+/// [`ISPParams::camera_to_xyz`], then to the `primaries` of the output,
+/// adapting the D65 white where they have another one, and encodes it with
+/// `transfer`. The default [`CameraCalibration`] describes linear sRGB, which
+/// is what [`ColorCorrectionPush`] produces when fitted with
+/// [`crate::color_checker`]. With [`TransferFunction::Pq`], linear 1 is shown
+/// at `white_luminance` cd/m². Leave the gamma of [`GammaPush`] at 1 while
+/// this is enabled.
+///
+/// The encoding ends up in [`Buffers::RGB`] for whoever reads it back. The
+/// viewer draws to an sRGB surface that encodes by itself, so it runs this
+/// with [`TransferFunction::Linear`].
+#[derive(Clone, Copy, Debug, UiMarker, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct OutputTransformPush {
+    pub enabled: i32,
+    pub primaries: OutputPrimaries,
+    pub transfer: TransferFunction,
+    #[ui(min = 80, max = 1000)]
+    pub white_luminance: f32,
+}
+
+impl Default for OutputTransformPush {
+    fn default() -> Self {
+        Self {
+            enabled: 0,
+            primaries: OutputPrimaries::default(),
+            transfer: TransferFunction::default(),
+            // Reference white of BT.2408
+            white_luminance: 203.,
+        }
+    }
+}
+
+impl OutputTransformPush {
+    /// Camera RGB to linear RGB with the output primaries.
+    pub fn matrix(&self, camera_to_xyz: glam::Mat3) -> glam::Mat4 {
+        let xyz_to_output = self.primaries.chromaticities().xyz_d65_to_rgb();
+        glam::Mat4::from_mat3(xyz_to_output * camera_to_xyz)
+    }
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    UiMarker,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum OutputPrimaries {
+    #[default]
+    Srgb,
+    DisplayP3,
+    Rec2020,
+    /// ACES AP1, usually with [`TransferFunction::Linear`].
+    AcesCg,
+}
+
+impl OutputPrimaries {
+    pub fn chromaticities(self) -> Chromaticities {
+        match self {
+            OutputPrimaries::Srgb => Chromaticities::SRGB,
+            OutputPrimaries::DisplayP3 => Chromaticities::DISPLAY_P3,
+            OutputPrimaries::Rec2020 => Chromaticities::REC2020,
+            OutputPrimaries::AcesCg => Chromaticities::ACES_AP1,
+        }
+    }
+}
+
+/// The discriminants are the values `output_transform.wgsl` switches on.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    UiMarker,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[repr(i32)]
+pub enum TransferFunction {
+    Linear = 0,
+    /// The piecewise sRGB curve, also used by Display P3.
+    #[default]
+    Srgb = 1,
+    /// SMPTE ST 2084, for HDR output in Rec.2020.
+    Pq = 2,
+    /// Hybrid log-gamma of BT.2100, with linear 1 at the peak.
+    Hlg = 3,
+}
+
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct OutputTransformParams {
+    matrix: glam::Mat4,
+    transfer: i32,
+    scale: f32,
+    _padding: [f32; 2],
+}
+
+impl SequentialOperation for OutputTransform {
+    type PT = PT;
+
+    fn enabled(_params: &PipelineParams<Self>) -> bool
+    where
+        Self: Sized,
+    {
+        true
+    }
+
+    fn buffers(params: &PipelineParams<Self>) -> Vec<AbstractBuffer<PT>>
+    where
+        Self: Sized,
+    {
+        vec![Buffers::RGB.init(params)]
+    }
+
+    fn create(
+        device: &gpwgpu::wgpu::Device,
+        params: &PipelineParams<Self>,
+        buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+    ) -> Result<Self, PipelineError<Self>>
+    where
+        Self: Sized,
+    {
+        let rgb = buffers.get::<Self>(Buffers::RGB);
+
+        let (height, width) = (params.output_height(), params.output_width());
+        let dispatch_size = [height as u32, width as u32, 1];
+
+        let specs = ShaderSpecs::new((8, 32, 1))
+            .direct_dispatcher(&dispatch_size)
+            .extend_defs([("HEIGHT", height.into()), ("WIDTH", width.into())])
+            .push_constants(size_of::<OutputTransformParams>() as u32);
+
+        let shader = params
+            .shader_processor
+            .process_by_name("output_transform", specs)?;
 
         let pipeline = shader.build(device)?;
 
@@ -488,9 +3371,21 @@ impl SequentialOperation for RGBSpaceOperations {
         _buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
         args: &PipelineArgs<Self>,
     ) {
-        let mut push = bytes_of(&args.color_correction_push).to_vec();
-        push.extend_from_slice(bytes_of(&args.gamma_push));
-        self.pass.execute(encoder, &push);
+        let push = &args.output_transform_push;
+        if push.enabled == 0 {
+            return;
+        }
+        let scale = match push.transfer {
+            TransferFunction::Pq => push.white_luminance,
+            _ => 1.,
+        };
+        let params = OutputTransformParams {
+            matrix: push.matrix(args.camera_to_xyz()),
+            transfer: push.transfer as i32,
+            scale,
+            _padding: [0.; 2],
+        };
+        self.pass.execute(encoder, bytes_of(&params));
     }
 }
 
@@ -534,21 +3429,30 @@ impl SequentialOperation for PreserveRaw {
     }
 }
 
+/// Copies the output of a stage that can't work in place from
+/// [`Buffers::Scratch`] back to [`Buffers::RGB`], where the rest of the
+/// pipeline expects the image.
+fn copy_scratch_to_rgb(
+    encoder: &mut gpwgpu::utils::Encoder,
+    buffers: &gpwgpu::automatic_buffers::BufferSolution<PT>,
+) {
+    let rgb = buffers.get_from_any(Buffers::RGB);
+    encoder.copy_buffer_to_buffer(buffers.get_from_any(Buffers::Scratch), 0, rgb, 0, rgb.size());
+}
+
 pub fn create_to_texture(
     device: &Device,
     params: &Params,
     final_buffer: &Buffer,
     texture: &Texture,
 ) -> Result<FullComputePass, ShaderError> {
-    let dispatch_size = [params.height as u32, params.width as u32, 1];
+    let (height, width) = (params.output_height(), params.output_width());
+    let dispatch_size = [height as u32, width as u32, 1];
 
     let shader = params.shader_processor.process_by_name(
         "to_texture",
         ShaderSpecs::new((8, 32, 1))
-            .extend_defs([
-                ("HEIGHT", (params.height).into()),
-                ("WIDTH", (params.width).into()),
-            ])
+            .extend_defs([("HEIGHT", height.into()), ("WIDTH", width.into())])
             .direct_dispatcher(&dispatch_size),
     )?;
 
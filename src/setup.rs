@@ -1,12 +1,18 @@
 use gpwgpu::{
     automatic_buffers::{AllOperations, Operation},
     shaderpreprocessor::ShaderProcessor,
-    utils::{DebugBundle, FullComputePass, InspectBuffer},
+    utils::{read_buffer, DebugBundle, Encoder, FullComputePass, InspectBuffer},
     wgpu::{Device, Extent3d, Queue, Texture, TextureDescriptor, TextureDimension, TextureUsages},
 };
 
-use crate::operations::{
-    create_to_texture, AutoWhiteBalance, BlackLevel, Buffers, Debayer, ISPParams, PreserveRaw, RGBSpaceOperations, StateError, PT
+use std::path::{Path, PathBuf};
+
+use crate::{
+    cube::{CubeError, CubeLut},
+    curves::bake,
+    operations::{
+        create_to_texture, AutoExposure, AutoWhiteBalance, Exposure, FrameStatistics, BinQuads, BlackLevel, Buffers, ColorAdjust, Debayer, Defringe, Denoise, DisplayLut3D, FalseColorSuppression, Gamma, GreenEqualization, CurvesPush, HighlightRecovery, ISPParams, LensCorrection, LocalToneMap, LutHeader, OutputTransform, PreserveRaw, RGBSpaceOperations, SceneLut3D, Sharpen, StateError, Statistics, TemporalDenoise, ToneMap, WhiteBalanceAlgorithm, WhiteBalanceMode, PT
+    },
 };
 
 #[derive(Debug, Clone)]
@@ -15,12 +21,37 @@ pub struct Params {
     pub height: i32,
 
     pub shader_processor: ShaderProcessor<'static>,
+
+    /// Bin every RGGB quad into one pixel instead of demosaicing, which halves
+    /// the resolution of everything from [`Buffers::RGB`] on.
+    pub preview: bool,
 }
 
 impl Params {
     pub fn byte_size(&self) -> i32 {
         self.width * self.height * std::mem::size_of::<f32>() as i32
     }
+
+    pub fn output_width(&self) -> i32 {
+        if self.preview {
+            self.width / 2
+        } else {
+            self.width
+        }
+    }
+
+    pub fn output_height(&self) -> i32 {
+        if self.preview {
+            self.height / 2
+        } else {
+            self.height
+        }
+    }
+
+    /// Byte size of a single channel of the RGB image.
+    pub fn output_byte_size(&self) -> i32 {
+        self.output_width() * self.output_height() * std::mem::size_of::<f32>() as i32
+    }
 }
 
 pub struct State<'a> {
@@ -30,15 +61,40 @@ pub struct State<'a> {
     pub to_texture: FullComputePass,
     pub texture: Texture,
     pub sequential: AllOperations<PT>,
+    /// The LUT in [`Buffers::Lut`] and the path it was loaded from.
+    lut: Option<(PathBuf, CubeLut)>,
+    /// The curves baked into [`Buffers::Curves`].
+    curves: Option<CurvesPush>,
+    /// White balance algorithm and mode of the last execution. The AWB
+    /// smoothing starts again when either changes.
+    awb_estimator: Option<(WhiteBalanceAlgorithm, WhiteBalanceMode)>,
 }
 
 impl<'a> State<'a> {
     pub fn new(device: &'a Device, queue: &'a Queue, params: Params) -> Result<Self, StateError> {
         let operations = vec![
             Operation::new::<BlackLevel>(),
+            Operation::new::<TemporalDenoise>(),
+            Operation::new::<Statistics>(),
+            Operation::new::<AutoExposure>(),
+            Operation::new::<Denoise>(),
             Operation::new::<AutoWhiteBalance>(),
+            Operation::new::<GreenEqualization>(),
             Operation::new::<Debayer>(),
+            Operation::new::<BinQuads>(),
+            Operation::new::<HighlightRecovery>(),
+            Operation::new::<FalseColorSuppression>(),
+            Operation::new::<LensCorrection>(),
             Operation::new::<RGBSpaceOperations>(),
+            Operation::new::<Defringe>(),
+            Operation::new::<ColorAdjust>(),
+            Operation::new::<Sharpen>(),
+            Operation::new::<SceneLut3D>(),
+            Operation::new::<ToneMap>(),
+            Operation::new::<LocalToneMap>(),
+            Operation::new::<Gamma>(),
+            Operation::new::<DisplayLut3D>(),
+            Operation::new::<OutputTransform>(),
             Operation::new::<PreserveRaw>(),
         ];
 
@@ -48,8 +104,8 @@ impl<'a> State<'a> {
         let texture = device.create_texture(&TextureDescriptor {
             label: None,
             size: Extent3d {
-                width: params.width as _,
-                height: params.height as _,
+                width: params.output_width() as _,
+                height: params.output_height() as _,
                 depth_or_array_layers: 1,
             },
             mip_level_count: 1,
@@ -74,6 +130,9 @@ impl<'a> State<'a> {
             sequential,
             to_texture,
             texture,
+            lut: None,
+            curves: None,
+            awb_estimator: None,
         })
     }
 
@@ -82,9 +141,143 @@ impl<'a> State<'a> {
         self.queue.write_buffer(buf, 0, bytemuck::cast_slice(data));
     }
 
+    /// Uploads the parameters that don't fit in push constants and records the
+    /// full pipeline into `encoder`.
+    pub fn execute(&mut self, encoder: &mut Encoder, args: &ISPParams) {
+        let crosstalk = self.sequential.buffers.get_from_any(Buffers::Crosstalk);
+        self.queue
+            .write_buffer(crosstalk, 0, bytemuck::bytes_of(&args.crosstalk_kernels()));
+
+        let color_adjust = self.sequential.buffers.get_from_any(Buffers::ColorAdjust);
+        self.queue.write_buffer(
+            color_adjust,
+            0,
+            bytemuck::bytes_of(&args.color_adjust_push.params()),
+        );
+
+        // A LUT loaded from another path than the current one isn't applied
+        let header = match &self.lut {
+            Some((path, lut)) if args.lut_path.as_ref() == Some(path) => lut.header(),
+            _ => LutHeader::default(),
+        };
+        let lut = self.sequential.buffers.get_from_any(Buffers::Lut);
+        self.queue.write_buffer(lut, 0, bytemuck::bytes_of(&header));
+
+        if self.curves.as_ref() != Some(&args.curves_push) {
+            let curves = self.sequential.buffers.get_from_any(Buffers::Curves);
+            self.queue
+                .write_buffer(curves, 0, bytemuck::cast_slice(&bake(&args.curves_push)));
+            self.curves = Some(args.curves_push.clone());
+        }
+
+        // Gains from another estimate aren't a history for this one
+        let white_balance = &args.auto_white_balance_push;
+        let estimator = (white_balance.algorithm, white_balance.mode);
+        let previous = self.awb_estimator.replace(estimator);
+        if previous.is_some_and(|previous| previous != estimator) {
+            self.reset_awb_smoothing();
+        }
+
+        self.sequential.execute(encoder, args);
+    }
+
+    /// Loads a `.cube` file into [`Buffers::Lut`]. It is applied while
+    /// [`ISPParams::lut_path`] is the same path.
+    pub fn load_lut(&mut self, path: impl AsRef<Path>) -> Result<(), CubeError> {
+        let lut = CubeLut::load(&path)?;
+        self.upload_lut(path.as_ref().to_path_buf(), lut);
+        Ok(())
+    }
+
+    /// The path of the LUT in [`Buffers::Lut`], if one is loaded.
+    pub fn lut_path(&self) -> Option<&Path> {
+        self.lut.as_ref().map(|(path, _)| path.as_path())
+    }
+
+    fn upload_lut(&mut self, path: PathBuf, lut: CubeLut) {
+        let table = lut
+            .table
+            .iter()
+            .map(|&[r, g, b]| [r, g, b, 0.])
+            .collect::<Vec<_>>();
+        let buffer = self.sequential.buffers.get_from_any(Buffers::Lut);
+        self.queue.write_buffer(
+            buffer,
+            std::mem::size_of::<LutHeader>() as u64,
+            bytemuck::cast_slice(&table),
+        );
+        self.lut = Some((path, lut));
+    }
+
+    /// The frame of the last execution after the black level and crosstalk
+    /// correction, in units of [`crate::operations::RAW_SCALE`]. While
+    /// [`TemporalDenoise`] is enabled it is the temporal average instead, the
+    /// accumulation works in the same buffer.
+    pub fn read_black_level(&self) -> Vec<f32> {
+        let frame = self.sequential.buffers.get_from_any(Buffers::BlackLevel);
+        read_buffer::<f32>(self.device, frame, 0, None)
+    }
+
+    /// The statistics grid of the last execution with
+    /// [`crate::operations::StatisticsPush::enabled`] set.
+    pub fn read_stats(&self) -> FrameStatistics {
+        let stats = self.sequential.buffers.get_from_any(Buffers::Statistics);
+        FrameStatistics::from_words(&read_buffer::<u32>(self.device, stats, 0, None))
+    }
+
+    /// The gains [`AutoExposure`] settled on in the last execution.
+    pub fn read_exposure(&self) -> Exposure {
+        let exposure = self.sequential.buffers.get_from_any(Buffers::Exposure);
+        read_buffer::<Exposure>(self.device, exposure, 0, None)[0]
+    }
+
+    /// How many RGGB quads passed the region of interest and the saturation
+    /// and darkness masks of [`AutoWhiteBalance`] in the last execution.
+    pub fn awb_quad_count(&self) -> u32 {
+        let count = self.sequential.buffers.get_from_any(Buffers::AwbCount);
+        read_buffer::<u32>(self.device, count, 0, None)[0]
+    }
+
+    /// Drops the frames accumulated by [`TemporalDenoise`], e.g. when the
+    /// scene changes. The next frame starts a new history.
+    pub fn reset_temporal_history(&self) {
+        let history = self.sequential.buffers.get_from_any(Buffers::TemporalHistory);
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        encoder.clear_buffer(history, 0, None);
+        self.queue.submit(Some(encoder.finish()));
+    }
+
+    /// Restarts the smoothing of the white balance gains, so the next frame
+    /// uses its own estimate.
+    pub fn reset_awb_smoothing(&self) {
+        let history = self.sequential.buffers.get_from_any(Buffers::AwbHistory);
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        encoder.clear_buffer(history, 0, None);
+        self.queue.submit(Some(encoder.finish()));
+    }
+
     pub fn reload(&self, params: Params) -> Result<Self, StateError> {
         Self::new(&self.device, &self.queue, params)
     }
+
+    /// Like [`State::reload`], but carries the raw frame and the LUT over so
+    /// the new state can be executed right away, e.g. when switching to the
+    /// preview pipeline.
+    pub fn rebuild(&self, params: Params) -> Result<Self, StateError> {
+        let mut new_state = self.reload(params)?;
+        if let Some((path, lut)) = &self.lut {
+            new_state.upload_lut(path.clone(), lut.clone());
+        }
+
+        let old_input = self.sequential.buffers.get_from_any(Buffers::Raw);
+        let new_input = new_state.sequential.buffers.get_from_any(Buffers::Raw);
+
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        encoder.copy_buffer_to_buffer(old_input, 0, new_input, 0, old_input.size());
+        self.queue.submit(Some(encoder.finish()));
+
+        Ok(new_state)
+    }
 }
 
 #[allow(unused)]
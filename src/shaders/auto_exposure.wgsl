@@ -0,0 +1,106 @@
+#import statistics_layout
+
+// Written by statistics.wgsl
+@group(0) @binding(0)
+var<storage, read> stats: Statistics;
+
+// Exposure in operations.rs
+struct Exposure{
+	digital_gain: f32,
+	exposure_ratio: f32,
+	metered_luminance: f32,
+	_padding: f32,
+}
+
+@group(0) @binding(1)
+var<storage, read_write> exposure: Exposure;
+
+struct AutoExposureParams{
+	enabled: i32,
+	// MeteringMode in operations.rs
+	metering: i32,
+	target: f32,
+	highlight_percentile: f32,
+	highlight_target: f32,
+	min_gain: f32,
+	max_gain: f32,
+}
+
+var<push_constant> pc: AutoExposureParams;
+
+const AVERAGE = 0;
+const CENTER_WEIGHTED = 1;
+const HIGHLIGHT = 2;
+
+const MIN_EV = #MIN_EV;
+const MAX_EV = #MAX_EV;
+
+fn bin_ev(bin: u32) -> f32{
+	return f32(MIN_EV) + (f32(bin) + 0.5) * f32(MAX_EV - MIN_EV) / f32(#BINS);
+}
+
+fn zone_weight(zone: vec2<u32>, zones: vec2<u32>) -> f32{
+	if pc.metering != CENTER_WEIGHTED{
+		return 1.;
+	}
+	// Gaussian falloff, a third of the frame size wide
+	let centre = (vec2<f32>(zone) + 0.5) / vec2<f32>(zones) - 0.5;
+	return exp(-dot(centre, centre) / (2. * 0.33 * 0.33));
+}
+
+@compute @workgroup_size(#WG_X, #WG_Y, #WG_Z)
+fn main(){
+	if pc.enabled == 0{
+		exposure = Exposure(1., 1., 0., 0.);
+		return;
+	}
+
+	let zones = stats.zones;
+
+	// Weighted mean of log luminance, and the histogram of the whole frame
+	var histogram: array<f32, #BINS>;
+	var log_sum = 0.;
+	var weight_sum = 0.;
+	for (var row = 0u; row < zones.x; row++){
+		for (var col = 0u; col < zones.y; col++){
+			let zone = row * zones.y + col;
+			let weight = zone_weight(vec2(row, col), zones);
+			for (var bin = 0u; bin < #BINS; bin++){
+				let count = f32(stats.zone[zone].histogram[bin]);
+				histogram[bin] += count;
+				log_sum += weight * count * bin_ev(bin);
+				weight_sum += weight * count;
+			}
+		}
+	}
+
+	if weight_sum <= 0.{
+		exposure = Exposure(1., 1., 0., 0.);
+		return;
+	}
+
+	let metered = exp2(log_sum / weight_sum);
+	var ratio = pc.target / metered;
+
+	if pc.metering == HIGHLIGHT{
+		// Keep the brightest (100 - highlight_percentile)% below highlight_target
+		var total = 0.;
+		for (var bin = 0u; bin < #BINS; bin++){
+			total += histogram[bin];
+		}
+		let target_count = total * clamp(pc.highlight_percentile, 0., 100.) / 100.;
+
+		var cumulative = 0.;
+		var highlight_ev = f32(MAX_EV);
+		for (var bin = 0u; bin < #BINS; bin++){
+			cumulative += histogram[bin];
+			if cumulative >= target_count{
+				highlight_ev = bin_ev(bin);
+				break;
+			}
+		}
+		ratio = min(ratio, pc.highlight_target / exp2(highlight_ev));
+	}
+
+	exposure = Exposure(clamp(ratio, pc.min_gain, pc.max_gain), ratio, metered, 0.);
+}
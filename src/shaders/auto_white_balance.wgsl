@@ -4,14 +4,9 @@ var<storage, read> input: array<f32>;
 @group(0) @binding(1)
 var<storage, read_write> output: array<f32>;
 
+// Per CFA position (R, Gr, Gb, B), written by awb_gains.wgsl
 @group(0) @binding(2)
-var<storage, read> mean: vec4<f32>;
-
-struct AutoWhiteBalanceParams{
-	gain: f32,
-}
-
-var<push_constant> pc: AutoWhiteBalanceParams;
+var<storage, read> gains: vec4<f32>;
 
 #import is_outside_image
 
@@ -25,31 +20,9 @@ fn main(
 		return;
 	}
 
-	let mod_row = global_id.x % 2u;
-	let mod_col = global_id.y % 2u;
+	let cfa = (global_id.x % 2u) * 2u + global_id.y % 2u;
 
 	let global_flat = i32(global_id.x) * #WIDTH + i32(global_id.y);
 
-	var color = input[global_flat];
-	
-	// Red
-	if mod_row == 0u && mod_col == 0u{
-		let red_avg = mean.x;
-		let green_avg = (mean.y + mean.z) / 2.;
-		color *= pc.gain * red_avg / green_avg;
-	
-	// Green
-	} else if (mod_row == 0u && mod_col == 1u) || (mod_row == 1u && mod_col == 0u){
-		color *= pc.gain;
-		
-	// Blue
-	} else {
-		let blue_avg = mean.w;
-		let green_avg = (mean.y + mean.z) / 2.;
-		color *= pc.gain * blue_avg / green_avg;
-	}
-
-	output[global_flat] = color;
+	output[global_flat] = input[global_flat] * gains[cfa];
 }
-
-
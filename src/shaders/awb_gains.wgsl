@@ -0,0 +1,131 @@
+// Mean over all quads, including the zeroed ones
+@group(0) @binding(0)
+var<storage, read_write> mean: vec4<f32>;
+
+@group(0) @binding(1)
+var<storage, read> maximum: array<u32, 4>;
+
+@group(0) @binding(2)
+var<storage, read> histogram: array<u32, #expr{4 * BINS}>;
+
+@group(0) @binding(3)
+var<storage, read> gradient_mean: vec4<f32>;
+
+@group(0) @binding(4)
+var<storage, read_write> gains: vec4<f32>;
+
+// Number of quads that passed the mask in bayer_to_vec4.wgsl
+@group(0) @binding(5)
+var<storage, read> count: u32;
+
+struct AwbHistory{
+	// Smoothed gains, without pc.gain
+	gains: vec4<f32>,
+	// 0 after a reset
+	valid: u32,
+}
+
+@group(0) @binding(6)
+var<storage, read_write> history: AwbHistory;
+
+struct AwbGainsParams{
+	gain: f32,
+	// WhiteBalanceAlgorithm in operations.rs
+	algorithm: i32,
+	percentile: f32,
+	// Use manual_gains instead of estimating the white point
+	manual: i32,
+	manual_gains: vec4<f32>,
+	// In frames, 0 disables the smoothing
+	time_constant: f32,
+}
+
+var<push_constant> pc: AwbGainsParams;
+
+const GRAY_WORLD = 0;
+const WHITE_PATCH = 1;
+const PERCENTILE = 2;
+const GRAY_EDGE = 3;
+
+fn percentile(channel: u32) -> f32{
+	let top = bitcast<f32>(maximum[channel]);
+	let offset = channel * #BINS;
+
+	var total = 0u;
+	for (var i = 0u; i < #BINS; i++){
+		total += histogram[offset + i];
+	}
+
+	let target_count = f32(total) * clamp(pc.percentile, 0., 100.) / 100.;
+
+	var cumulative = 0.;
+	for (var i = 0u; i < #BINS; i++){
+		let count = f32(histogram[offset + i]);
+		if count > 0. && cumulative + count >= target_count{
+			// Assume the values are spread evenly within the bin
+			let fraction = (target_count - cumulative) / count;
+			return (f32(i) + fraction) / f32(#BINS) * top;
+		}
+		cumulative += count;
+	}
+
+	return top;
+}
+
+fn white_point() -> vec4<f32>{
+	switch pc.algorithm{
+		case WHITE_PATCH: {
+			return bitcast<vec4<f32>>(vec4(maximum[0], maximum[1], maximum[2], maximum[3]));
+		}
+		case PERCENTILE: {
+			return vec4(percentile(0u), percentile(1u), percentile(2u), percentile(3u));
+		}
+		case GRAY_EDGE: {
+			return gradient_mean;
+		}
+		default: {
+			return mean;
+		}
+	}
+}
+
+fn relative_gain(numerator: f32, denominator: f32) -> f32{
+	if numerator <= 0. || denominator <= 0.{
+		return 1.;
+	}
+	return numerator / denominator;
+}
+
+@compute @workgroup_size(#WG_X, #WG_Y, #WG_Z)
+fn main(){
+	// Make the mean one over the quads that passed the mask
+	if count > 0u{
+		mean *= f32(#LENGTH) / f32(count);
+	}
+
+	if pc.manual != 0{
+		gains = pc.gain * pc.manual_gains;
+		return;
+	}
+
+	let white = white_point();
+	let green = (white.y + white.z) / 2.;
+
+	// The white point has to end up neutral
+	var current = vec4(
+		relative_gain(green, white.x),
+		1.,
+		1.,
+		relative_gain(green, white.w),
+	);
+
+	if pc.time_constant > 0. && history.valid != 0u{
+		let alpha = 1. - exp(-1. / pc.time_constant);
+		current = mix(history.gains, current, alpha);
+	}
+
+	history.gains = current;
+	history.valid = 1u;
+
+	gains = pc.gain * current;
+}
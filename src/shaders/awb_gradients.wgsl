@@ -0,0 +1,45 @@
+// Quads from bayer_to_vec4.wgsl
+@group(0) @binding(0)
+var<storage, read> input: array<vec4<f32>>;
+
+@group(0) @binding(1)
+var<storage, read_write> output: array<vec4<f32>>;
+
+#import is_outside_image
+
+fn quad(coord: vec2<i32>, bounds: vec2<i32>) -> vec4<f32>{
+	let clamped = clamp(coord, vec2(0), bounds - 1);
+	return input[clamped.x * bounds.y + clamped.y];
+}
+
+// Per channel gradient magnitude, averaged by the gray-edge white balance
+@compute @workgroup_size(#WG_X, #WG_Y, #WG_Z)
+fn main(
+	@builtin(global_invocation_id) global_id: vec3<u32>,
+){
+	let global_bounds = vec2(#HEIGHT / 2, #WIDTH / 2);
+
+	if is_outside_image(global_id, global_bounds){
+		return;
+	}
+
+	let coord = vec2<i32>(global_id.xy);
+	let global_flat = coord.x * global_bounds.y + coord.y;
+
+	let up = quad(coord - vec2(1, 0), global_bounds);
+	let down = quad(coord + vec2(1, 0), global_bounds);
+	let left = quad(coord - vec2(0, 1), global_bounds);
+	let right = quad(coord + vec2(0, 1), global_bounds);
+
+	// Quads left out by bayer_to_vec4.wgsl are zero and would look like edges
+	let zero = vec4(0.);
+	if all(input[global_flat] == zero) || all(up == zero) || all(down == zero) || all(left == zero) || all(right == zero){
+		output[global_flat] = zero;
+		return;
+	}
+
+	let vertical = (down - up) / 2.;
+	let horizontal = (right - left) / 2.;
+
+	output[global_flat] = sqrt(vertical * vertical + horizontal * horizontal);
+}
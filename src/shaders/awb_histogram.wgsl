@@ -0,0 +1,39 @@
+@group(0) @binding(0)
+var<storage, read> input: array<vec4<f32>>;
+
+// Output of awb_max.wgsl
+@group(0) @binding(1)
+var<storage, read> maximum: array<u32, 4>;
+
+// #BINS bins per channel spanning [0, maximum]
+@group(0) @binding(2)
+var<storage, read_write> histogram: array<atomic<u32>, #expr{4 * BINS}>;
+
+var<workgroup> local_histogram: array<atomic<u32>, #expr{4 * BINS}>;
+
+@compute @workgroup_size(#WG_X, #WG_Y, #WG_Z)
+fn main(
+	@builtin(global_invocation_id) global_id: vec3<u32>,
+	@builtin(local_invocation_index) local_index: u32,
+){
+	// Quads left out by bayer_to_vec4.wgsl are zero
+	if global_id.x < u32(#LENGTH) && any(input[global_id.x] != vec4(0.)){
+		let value = max(input[global_id.x], vec4(0.));
+		for (var channel = 0u; channel < 4u; channel++){
+			let top = bitcast<f32>(maximum[channel]);
+			if top > 0.{
+				let bin = min(u32(value[channel] / top * f32(#BINS)), #BINS - 1u);
+				atomicAdd(&local_histogram[channel * #BINS + bin], 1u);
+			}
+		}
+	}
+
+	workgroupBarrier();
+
+	for (var i = local_index; i < #expr{4 * BINS}; i += #WG_X){
+		let count = atomicLoad(&local_histogram[i]);
+		if count > 0u{
+			atomicAdd(&histogram[i], count);
+		}
+	}
+}
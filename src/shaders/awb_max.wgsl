@@ -0,0 +1,27 @@
+@group(0) @binding(0)
+var<storage, read> input: array<vec4<f32>>;
+
+// Per channel maximum as the bits of a non-negative f32, which order like u32
+@group(0) @binding(1)
+var<storage, read_write> maximum: array<atomic<u32>, 4>;
+
+var<workgroup> local_maximum: array<atomic<u32>, 4>;
+
+@compute @workgroup_size(#WG_X, #WG_Y, #WG_Z)
+fn main(
+	@builtin(global_invocation_id) global_id: vec3<u32>,
+	@builtin(local_invocation_index) local_index: u32,
+){
+	if global_id.x < u32(#LENGTH){
+		let value = max(input[global_id.x], vec4(0.));
+		for (var channel = 0; channel < 4; channel++){
+			atomicMax(&local_maximum[channel], bitcast<u32>(value[channel]));
+		}
+	}
+
+	workgroupBarrier();
+
+	if local_index < 4u{
+		atomicMax(&maximum[local_index], atomicLoad(&local_maximum[local_index]));
+	}
+}
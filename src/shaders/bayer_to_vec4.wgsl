@@ -4,6 +4,24 @@ var<storage, read> input: array<f32>;
 @group(0) @binding(1)
 var<storage, read_write> output: array<vec4<f32>>;
 
+// Number of quads that passed the mask
+@group(0) @binding(2)
+var<storage, read_write> count: atomic<u32>;
+
+struct StatisticsParams{
+	// Quads with any channel at or above this are left out, unless it's 0
+	saturation: f32,
+	// Quads with any channel below this are left out, unless it's 0
+	noise_floor: f32,
+	_padding: vec2<f32>,
+	// Top, left, bottom and right edge as fractions of the image
+	roi: vec4<f32>,
+}
+
+var<push_constant> pc: StatisticsParams;
+
+var<workgroup> local_count: atomic<u32>;
+
 var<workgroup> local: array<f32, #expr{(WG_X * 2) * (WG_Y * 2)}>;
 
 const local_height = #expr{WG_X * 2};
@@ -44,19 +62,40 @@ fn main(
 	let global_bounds = vec2(#HEIGHT / 2, #WIDTH / 2);
 
 	setup_local(wg_id, local_index, global_bounds);
-	if is_outside_image(global_id, global_bounds){
-		return;
-	}
 
-	var color: vec4<f32>;
+	// No early return, the count needs another barrier
+	if !is_outside_image(global_id, global_bounds){
+		var color: vec4<f32>;
+
+		let double_local = vec2<i32>(local_id.xy) * 2;
 
-	let double_local = vec2<i32>(local_id.xy) * 2;
+		color.x = access_local(double_local.x + 0, double_local.y + 0);
+		color.y = access_local(double_local.x + 0, double_local.y + 1);
+		color.z = access_local(double_local.x + 1, double_local.y + 0);
+		color.w = access_local(double_local.x + 1, double_local.y + 1);
 
-	color.x = access_local(double_local.x + 0, double_local.y + 0);
-	color.y = access_local(double_local.x + 0, double_local.y + 1);
-	color.z = access_local(double_local.x + 1, double_local.y + 0);
-	color.w = access_local(double_local.x + 1, double_local.y + 1);
+		let bounds = vec2<f32>(global_bounds);
+		let position = vec2<f32>(global_id.xy) + 0.5;
+		let in_roi = all(position >= pc.roi.xy * bounds) && all(position < pc.roi.zw * bounds);
 
-	let global_flat = i32(global_id.x) * global_bounds.y + i32(global_id.y);
-	output[global_flat] = color;
+		let saturated = pc.saturation > 0. && any(color >= vec4(pc.saturation));
+		let dark = pc.noise_floor > 0. && any(color < vec4(pc.noise_floor));
+
+		// Excluded quads are zero, which the statistics passes skip or which
+		// only scale the mean
+		if in_roi && !saturated && !dark{
+			atomicAdd(&local_count, 1u);
+		} else {
+			color = vec4(0.);
+		}
+
+		let global_flat = i32(global_id.x) * global_bounds.y + i32(global_id.y);
+		output[global_flat] = color;
+	}
+
+	workgroupBarrier();
+
+	if local_index == 0u{
+		atomicAdd(&count, atomicLoad(&local_count));
+	}
 }
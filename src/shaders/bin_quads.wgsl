@@ -0,0 +1,29 @@
+@group(0) @binding(0)
+var<storage, read> input: array<f32>;
+
+@group(0) @binding(1)
+var<storage, read_write> output: array<vec4<f32>>;
+
+#import is_outside_image
+
+// Every RGGB quad becomes one RGB pixel of a half resolution image
+@compute @workgroup_size(#WG_X, #WG_Y, #WG_Z)
+fn main(
+	@builtin(global_invocation_id) global_id: vec3<u32>,
+){
+	let global_bounds = vec2(#HEIGHT / 2, #WIDTH / 2);
+
+	if is_outside_image(global_id, global_bounds){
+		return;
+	}
+
+	let top = i32(global_id.x) * 2 * #WIDTH + i32(global_id.y) * 2;
+	let bottom = top + #WIDTH;
+
+	let r = input[top];
+	let g = (input[top + 1] + input[bottom]) / 2.;
+	let b = input[bottom + 1];
+
+	let global_flat = i32(global_id.x) * global_bounds.y + i32(global_id.y);
+	output[global_flat] = vec4(r, g, b, 1.);
+}
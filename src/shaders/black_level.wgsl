@@ -4,13 +4,14 @@ var<storage, read> input: array<f32>;
 @group(0) @binding(1)
 var<storage, read_write> output: array<f32>;
 
+@group(0) @binding(2)
+var<storage, read> crosstalk: array<array<array<f32, 3>, 3>, 4>;
+
 struct BlackLevelParams{
 	r_offset: f32,
 	gr_offset: f32,
 	gb_offset: f32,
 	b_offset: f32,
-	alpha: f32,
-	beta: f32,
 }
 
 var<push_constant> pc: BlackLevelParams;
@@ -43,31 +44,36 @@ fn main(
 	let mod_row = global_id.x % 2u;
 	let mod_col = global_id.y % 2u;
 
-	var new_val = 0.0;
+	var new_val = access_local(local_center.x, local_center.y);
 	
 	// Red
 	if mod_row == 0u && mod_col == 0u{
-		new_val = access_local(local_center.x, local_center.y) + pc.r_offset;
+		new_val += pc.r_offset;
 	
 	// Green (red)
 	} else if mod_row == 0u && mod_col == 1u {
-		new_val = access_local(local_center.x, local_center.y) +
-		pc.gr_offset +
-		pc.alpha * access_local(local_center.x, local_center.y - 1);
+		new_val += pc.gr_offset;
 		
 	// Green (blue)
 	} else if mod_row == 1u && mod_col == 0u {
-		new_val = access_local(local_center.x, local_center.y) +
-		pc.gb_offset +
-		pc.beta * access_local(local_center.x - 1, local_center.y);
+		new_val += pc.gb_offset;
 
 	// Blue
 	} else {
-		new_val = access_local(local_center.x, local_center.y) + pc.b_offset;
+		new_val += pc.b_offset;
+	}
+
+	// Crosstalk from the 3x3 neighbourhood, one kernel per CFA position
+	let cfa = mod_row * 2u + mod_col;
+	for (var i = 0; i < 3; i++){
+		for (var j = 0; j < 3; j++){
+			new_val += crosstalk[cfa][i][j] * access_local(local_center.x + i - 1, local_center.y + j - 1);
+		}
 	}
 
 	let global_flat = (i32(global_id.x) * #WIDTH + i32(global_id.y));
 
+	// RAW_SCALE in operations.rs
 	output[global_flat] = new_val / 30000.;
 }
 
@@ -0,0 +1,55 @@
+@group(0) @binding(0)
+var<storage, read_write> input: array<vec4<f32>>;
+
+@group(0) @binding(1)
+var<storage, read> mapping: array<f32>;
+
+struct ClaheParams{
+	tiles_vertical: u32,
+	tiles_horizontal: u32,
+	clip_limit: f32,
+	strength: f32,
+}
+
+var<push_constant> pc: ClaheParams;
+
+#import is_outside_image
+#import clahe_luma
+
+fn tile_mapping(tile: vec2<i32>, bin: i32) -> f32{
+	return mapping[(tile.x * i32(pc.tiles_horizontal) + tile.y) * #BINS + bin];
+}
+
+@compute @workgroup_size(#WG_X, #WG_Y, #WG_Z)
+fn main(
+	@builtin(global_invocation_id) global_id: vec3<u32>,
+){
+	let global_bounds = vec2(#HEIGHT, #WIDTH);
+
+	if is_outside_image(global_id, global_bounds){
+		return;
+	}
+
+	let global_flat = i32(global_id.x) * #WIDTH + i32(global_id.y);
+
+	let color = input[global_flat];
+	let luma = clahe_luma(color.rgb);
+	let bin = i32(clahe_bin(luma));
+
+	// Bilinear between the mappings of the four nearest tile centres
+	let tiles = vec2(i32(pc.tiles_vertical), i32(pc.tiles_horizontal));
+	let position = (vec2<f32>(global_id.xy) + 0.5) * vec2<f32>(tiles) / vec2<f32>(global_bounds) - 0.5;
+	let first = clamp(vec2<i32>(floor(position)), vec2(0), tiles - 1);
+	let second = min(first + 1, tiles - 1);
+	let weight = clamp(position - vec2<f32>(first), vec2(0.), vec2(1.));
+
+	let top = mix(tile_mapping(first, bin), tile_mapping(vec2(first.x, second.y), bin), weight.y);
+	let bottom = mix(tile_mapping(vec2(second.x, first.y), bin), tile_mapping(second, bin), weight.y);
+	let equalized = mix(top, bottom, weight.x);
+
+	let mapped = mix(luma, equalized, pc.strength);
+	// Scale the colour by the change in linear luminance, so hue and saturation stay
+	let ratio = select(1., pow(mapped / luma, 2.2), luma > 0.);
+
+	input[global_flat] = vec4(color.rgb * ratio, color.w);
+}
@@ -0,0 +1,67 @@
+@group(0) @binding(0)
+var<storage, read> input: array<vec4<f32>>;
+
+// Per tile, the cumulative distribution of the clipped histogram
+@group(0) @binding(1)
+var<storage, read_write> mapping: array<f32>;
+
+struct ClaheParams{
+	tiles_vertical: u32,
+	tiles_horizontal: u32,
+	clip_limit: f32,
+	strength: f32,
+}
+
+var<push_constant> pc: ClaheParams;
+
+const wg_size = #expr{WG_X * WG_Y};
+
+var<workgroup> histogram: array<atomic<u32>, #BINS>;
+var<workgroup> excess: atomic<u32>;
+
+#import clahe_luma
+
+@compute @workgroup_size(#WG_X, #WG_Y, #WG_Z)
+fn main(
+	@builtin(local_invocation_index) local_index: u32,
+	@builtin(workgroup_id) wg_id: vec3<u32>,
+){
+	// One workgroup per tile
+	let tiles = vec2(pc.tiles_vertical, pc.tiles_horizontal);
+	if any(wg_id.xy >= tiles){
+		return;
+	}
+
+	let pixels = vec2<u32>(#HEIGHT, #WIDTH);
+	let start = wg_id.xy * pixels / tiles;
+	let size = (wg_id.xy + 1u) * pixels / tiles - start;
+	let n = size.x * size.y;
+
+	for (var i = local_index; i < n; i += wg_size){
+		let coord = start + vec2(i / size.y, i % size.y);
+		let luma = clahe_luma(input[coord.x * u32(#WIDTH) + coord.y].rgb);
+		atomicAdd(&histogram[clahe_bin(luma)], 1u);
+	}
+	workgroupBarrier();
+
+	// Clip every bin to the limit and hand the excess out evenly
+	let limit = u32(max(pc.clip_limit, 1.) * f32(n) / f32(#BINS));
+	for (var bin = local_index; bin < #BINS; bin += wg_size){
+		let count = atomicLoad(&histogram[bin]);
+		if count > limit{
+			atomicStore(&histogram[bin], limit);
+			atomicAdd(&excess, count - limit);
+		}
+	}
+	workgroupBarrier();
+
+	if local_index == 0u{
+		let redistributed = f32(atomicLoad(&excess)) / f32(#BINS);
+		let tile = (wg_id.x * tiles.y + wg_id.y) * #BINS;
+		var sum = 0.;
+		for (var bin = 0u; bin < #BINS; bin++){
+			sum += f32(atomicLoad(&histogram[bin])) + redistributed;
+			mapping[tile + bin] = sum / f32(max(n, 1u));
+		}
+	}
+}
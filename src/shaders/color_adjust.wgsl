@@ -0,0 +1,81 @@
+@group(0) @binding(0)
+var<storage, read_write> input: array<vec4<f32>>;
+
+// ColorAdjustParams in operations.rs
+struct ColorAdjustParams{
+	saturation: f32,
+	vibrance: f32,
+	// Degrees
+	hue_shift: f32,
+	_padding: f32,
+	band_hue: array<f32, 8>,
+	band_saturation: array<f32, 8>,
+	band_lightness: array<f32, 8>,
+}
+
+@group(0) @binding(1)
+var<storage, read> params: ColorAdjustParams;
+
+// The reference and the constants are in color_adjust.rs
+const BAND_CENTRES = array<f32, 8>(29.23, 52.78, 109.77, 142.5, 194.77, 264.05, 293.77, 328.36);
+const VIBRANCE_CHROMA = 0.25;
+const NEUTRAL_CHROMA = 0.05;
+
+#import is_outside_image
+#import oklab
+
+fn wrap_degrees(x: f32) -> f32{
+	return x - 360. * floor(x / 360.);
+}
+
+// Hue, saturation and lightness offsets at the hue, interpolated between the
+// nearest band centres
+fn band_adjustment(hue: f32) -> vec3<f32>{
+	// Below the first centre it lies between the last and the first band
+	var lower = 7;
+	for (var band = 0; band < 8; band++){
+		if BAND_CENTRES[band] <= hue{
+			lower = band;
+		}
+	}
+	let upper = (lower + 1) % 8;
+
+	let span = wrap_degrees(BAND_CENTRES[upper] - BAND_CENTRES[lower]);
+	let t = wrap_degrees(hue - BAND_CENTRES[lower]) / span;
+
+	let low = vec3(params.band_hue[lower], params.band_saturation[lower], params.band_lightness[lower]);
+	let high = vec3(params.band_hue[upper], params.band_saturation[upper], params.band_lightness[upper]);
+	return mix(low, high, t);
+}
+
+@compute @workgroup_size(#WG_X, #WG_Y, #WG_Z)
+fn main(
+	@builtin(global_invocation_id) global_id: vec3<u32>,
+){
+	let global_bounds = vec2(#HEIGHT, #WIDTH);
+
+	if is_outside_image(global_id, global_bounds){
+		return;
+	}
+
+	let global_flat = i32(global_id.x) * #WIDTH + i32(global_id.y);
+
+	let color = input[global_flat];
+	let lab = linear_srgb_to_oklab(color.rgb);
+
+	let lightness = lab.x;
+	let chroma = length(lab.yz);
+	let hue = wrap_degrees(degrees(atan2(lab.z, lab.y)));
+	let band = band_adjustment(hue);
+
+	let vibrance = 1. + params.vibrance * (1. - min(chroma / VIBRANCE_CHROMA, 1.));
+	let chroma_scale = params.saturation * (1. + band.y) * vibrance;
+
+	let neutral = min(chroma / NEUTRAL_CHROMA, 1.);
+	let new_lightness = lightness * max(1. + band.z * neutral, 0.);
+	let new_chroma = chroma * max(chroma_scale, 0.);
+	let new_hue = radians(hue + params.hue_shift + band.x);
+
+	let adjusted = vec3(new_lightness, new_chroma * cos(new_hue), new_chroma * sin(new_hue));
+	input[global_flat] = vec4(oklab_to_linear_srgb(adjusted), color.w);
+}
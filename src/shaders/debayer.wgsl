@@ -6,8 +6,20 @@ var<storage, read_write> output: array<vec4<f32>>;
 
 var<workgroup> local: array<f32, #expr{(WG_X + 2 * PADDING) * (WG_Y + 2 * PADDING)}>;
 
-// Whether to do debayering if to just bring the image into RGA space without any interpolation
-var<push_constant> pc: i32;
+struct DebayerParams{
+	// Whether to do debayering or to just bring the image into RGB space without any interpolation
+	enabled: i32,
+	// DemosaicAlgorithm in operations.rs
+	algorithm: i32,
+}
+
+var<push_constant> pc: DebayerParams;
+
+const BILINEAR = 0;
+const MALVAR = 1;
+const HAMILTON_ADAMS = 2;
+// RCD runs in the rcd_*.wgsl passes instead of this shader
+const RCD = 3;
 
 #import all_utils
 
@@ -167,6 +179,123 @@ fn malvar_b(center: vec2<i32>) -> vec3<f32>{
 }
 
 
+fn bilinear(center: vec2<i32>, cfa: u32) -> vec3<f32>{
+	let value = access_local(center.x, center.y);
+	let orthogonal = (
+		access_local(center.x - 1, center.y) +
+		access_local(center.x + 1, center.y) +
+		access_local(center.x, center.y - 1) +
+		access_local(center.x, center.y + 1)
+	) / 4.0;
+	let diagonal = (
+		access_local(center.x - 1, center.y - 1) +
+		access_local(center.x - 1, center.y + 1) +
+		access_local(center.x + 1, center.y - 1) +
+		access_local(center.x + 1, center.y + 1)
+	) / 4.0;
+	let horizontal = (access_local(center.x, center.y - 1) + access_local(center.x, center.y + 1)) / 2.0;
+	let vertical = (access_local(center.x - 1, center.y) + access_local(center.x + 1, center.y)) / 2.0;
+
+	switch cfa{
+		case 0u: {
+			return vec3(value, orthogonal, diagonal);
+		}
+		case 1u: {
+			return vec3(horizontal, value, vertical);
+		}
+		case 2u: {
+			return vec3(vertical, value, horizontal);
+		}
+		default: {
+			return vec3(diagonal, orthogonal, value);
+		}
+	}
+}
+
+// Green at a red or blue site, along the direction with the smaller gradient
+fn green_at(center: vec2<i32>) -> f32{
+	let value = access_local(center.x, center.y);
+
+	let left = access_local(center.x, center.y - 1);
+	let right = access_local(center.x, center.y + 1);
+	let up = access_local(center.x - 1, center.y);
+	let down = access_local(center.x + 1, center.y);
+
+	let laplacian_h = 2.0 * value - access_local(center.x, center.y - 2) - access_local(center.x, center.y + 2);
+	let laplacian_v = 2.0 * value - access_local(center.x - 2, center.y) - access_local(center.x + 2, center.y);
+
+	let estimate_h = (left + right) / 2.0 + laplacian_h / 4.0;
+	let estimate_v = (up + down) / 2.0 + laplacian_v / 4.0;
+
+	let gradient_h = abs(left - right) + abs(laplacian_h);
+	let gradient_v = abs(up - down) + abs(laplacian_v);
+
+	if gradient_h < gradient_v{
+		return estimate_h;
+	} else if gradient_v < gradient_h{
+		return estimate_v;
+	}
+	return (estimate_h + estimate_v) / 2.0;
+}
+
+// Difference between the sample at a red or blue site and the interpolated green there
+fn chroma_at(center: vec2<i32>) -> f32{
+	return access_local(center.x, center.y) - green_at(center);
+}
+
+// Hamilton-Adams: green by a directional algorithm, red and blue by
+// averaging their colour differences to green over the nearest samples.
+fn colour_difference(center: vec2<i32>, cfa: u32) -> vec3<f32>{
+	if cfa == 1u || cfa == 2u{
+		let green = access_local(center.x, center.y);
+		let horizontal = (
+			chroma_at(vec2(center.x, center.y - 1)) +
+			chroma_at(vec2(center.x, center.y + 1))
+		) / 2.0;
+		let vertical = (
+			chroma_at(vec2(center.x - 1, center.y)) +
+			chroma_at(vec2(center.x + 1, center.y))
+		) / 2.0;
+		// Red is left and right of green in the red rows
+		if cfa == 1u{
+			return green + vec3(horizontal, 0.0, vertical);
+		}
+		return green + vec3(vertical, 0.0, horizontal);
+	}
+
+	let value = access_local(center.x, center.y);
+	let green = green_at(center);
+	let diagonal = (
+		chroma_at(vec2(center.x - 1, center.y - 1)) +
+		chroma_at(vec2(center.x - 1, center.y + 1)) +
+		chroma_at(vec2(center.x + 1, center.y - 1)) +
+		chroma_at(vec2(center.x + 1, center.y + 1))
+	) / 4.0;
+
+	if cfa == 0u{
+		return vec3(value, green, green + diagonal);
+	}
+	return vec3(green + diagonal, green, value);
+}
+
+fn malvar(center: vec2<i32>, cfa: u32) -> vec3<f32>{
+	switch cfa{
+		case 0u: {
+			return malvar_r(center);
+		}
+		case 1u: {
+			return malvar_gr(center);
+		}
+		case 2u: {
+			return malvar_gb(center);
+		}
+		default: {
+			return malvar_b(center);
+		}
+	}
+}
+
+
 @compute @workgroup_size(#WG_X, #WG_Y, #WG_Z)
 fn main(
 	@builtin(global_invocation_id) global_id: vec3<u32>,
@@ -188,31 +317,23 @@ fn main(
 
 	let mod_row = global_id.x % 2u;
 	let mod_col = global_id.y % 2u;
+	let cfa = mod_row * 2u + mod_col;
 
-	if mod_row == 0u && mod_col == 0u{
-		if pc == 0{
-			color = vec3(access_local(local_center.x, local_center.y), 0., 0.);
-		} else {
-			color = malvar_r(local_center);
-		}
-	} else if mod_row == 0u && mod_col == 1u {
-		if pc == 0{
-			color = vec3(0., access_local(local_center.x, local_center.y), 0.);
-		} else {
-			color = malvar_gr(local_center);
-		}
-	} else if mod_row == 1u && mod_col == 0u {
-		if pc == 0{
-			color = vec3(0., access_local(local_center.x, local_center.y), 0.);
+	if pc.enabled == 0{
+		let value = access_local(local_center.x, local_center.y);
+		if cfa == 0u{
+			color = vec3(value, 0., 0.);
+		} else if cfa == 3u{
+			color = vec3(0., 0., value);
 		} else {
-			color = malvar_gb(local_center);
+			color = vec3(0., value, 0.);
 		}
+	} else if pc.algorithm == BILINEAR{
+		color = bilinear(local_center, cfa);
+	} else if pc.algorithm == MALVAR{
+		color = malvar(local_center, cfa);
 	} else {
-		if pc == 0{
-			color = vec3(0., 0., access_local(local_center.x, local_center.y));
-		} else {
-			color = malvar_b(local_center);
-		}
+		color = colour_difference(local_center, cfa);
 	}
 
 	let global_flat = (i32(global_id.x) * #WIDTH + i32(global_id.y));
@@ -0,0 +1,89 @@
+@group(0) @binding(0)
+var<storage, read> input: array<vec4<f32>>;
+
+@group(0) @binding(1)
+var<storage, read_write> output: array<vec4<f32>>;
+
+struct DefringeParams{
+	strength: f32,
+	// Luma range of the neighbourhood where the edge weight starts
+	threshold: f32,
+	// OkLCh hues and half widths in degrees
+	purple_hue: f32,
+	purple_width: f32,
+	green_hue: f32,
+	green_width: f32,
+}
+
+var<push_constant> pc: DefringeParams;
+
+// Degrees over which the hue weight falls off outside a band
+const HUE_FEATHER = 15.;
+
+var<workgroup> local: array<vec4<f32>, #expr{(WG_X + 2 * PADDING) * (WG_Y + 2 * PADDING)}>;
+
+#import reflect_vec
+#import is_outside_image
+#import setup_local
+#import access_local_vec4
+#import oklab
+
+const local_height = #expr{WG_X + 2 * PADDING};
+const local_width = #expr{WG_Y + 2 * PADDING};
+const local_size = #expr{(WG_X + 2 * PADDING) * (WG_Y + 2 * PADDING)};
+
+fn luma(rgb: vec3<f32>) -> f32{
+	return dot(rgb, vec3(0.2126, 0.7152, 0.0722));
+}
+
+// 1 within width of center, falling to 0 over HUE_FEATHER degrees
+fn hue_weight(hue: f32, center: f32, width: f32) -> f32{
+	let d = abs(hue - center) % 360.;
+	let distance = min(d, 360. - d);
+	return 1. - smoothstep(width, width + HUE_FEATHER, distance);
+}
+
+@compute @workgroup_size(#WG_X, #WG_Y, #WG_Z)
+fn main(
+	@builtin(global_invocation_id) global_id: vec3<u32>,
+	@builtin(local_invocation_id) local_id: vec3<u32>,
+	@builtin(local_invocation_index) local_index: u32,
+	@builtin(workgroup_id) wg_id: vec3<u32>,
+){
+	let global_bounds = vec2(#HEIGHT, #WIDTH);
+
+	setup_local(wg_id, local_index, global_bounds);
+
+	if is_outside_image(global_id, global_bounds){
+		return;
+	}
+
+	let local_center = vec2<i32>(local_id.xy) + vec2(#PADDING);
+	let global_flat = i32(global_id.x) * #WIDTH + i32(global_id.y);
+
+	let color = access_local_vec4(local_center.x, local_center.y);
+
+	// Fringes sit next to edges between much brighter and darker areas
+	var low = luma(color.rgb);
+	var high = low;
+	for (var i = -#PADDING; i <= #PADDING; i++){
+		for (var j = -#PADDING; j <= #PADDING; j++){
+			let neighbour = luma(access_local_vec4(local_center.x + i, local_center.y + j).rgb);
+			low = min(low, neighbour);
+			high = max(high, neighbour);
+		}
+	}
+	let edge = smoothstep(pc.threshold, 2. * pc.threshold, high - low);
+
+	let lab = linear_srgb_to_oklab(color.rgb);
+	let hue = degrees(atan2(lab.z, lab.y));
+	let fringe = max(
+		hue_weight(hue, pc.purple_hue, pc.purple_width),
+		hue_weight(hue, pc.green_hue, pc.green_width),
+	);
+
+	let desaturation = clamp(pc.strength * edge * fringe, 0., 1.);
+	let defringed = vec3(lab.x, lab.yz * (1. - desaturation));
+
+	output[global_flat] = vec4(oklab_to_linear_srgb(defringed), color.a);
+}
@@ -0,0 +1,80 @@
+@group(0) @binding(0)
+var<storage, read> input: array<f32>;
+
+@group(0) @binding(1)
+var<storage, read_write> output: array<f32>;
+
+struct DenoiseParams{
+	enabled: i32,
+	strength: f32,
+	spatial_sigma: f32,
+	_padding: f32,
+	// Per CFA position (R, Gr, Gb, B) noise model: variance = noise_a * signal + noise_b
+	noise_a: vec4<f32>,
+	noise_b: vec4<f32>,
+}
+
+var<push_constant> pc: DenoiseParams;
+
+var<workgroup> local: array<f32, #expr{(WG_X + 2 * PADDING) * (WG_Y + 2 * PADDING)}>;
+
+#import all_cfa_utils
+
+const local_height = #expr{WG_X + 2 * PADDING};
+const local_width = #expr{WG_Y + 2 * PADDING};
+const local_size = #expr{(WG_X + 2 * PADDING) * (WG_Y + 2 * PADDING)};
+
+// Radius in same-colour steps, which are 2 pixels apart
+const radius = #RADIUS;
+
+fn bilateral(center: vec2<i32>, cfa: u32) -> f32{
+	let value = access_local(center.x, center.y);
+
+	let variance = max(pc.noise_a[cfa] * max(value, 0.) + pc.noise_b[cfa], 1e-12);
+	let range_denom = 2. * pc.strength * pc.strength * variance;
+	let spatial_denom = 2. * pc.spatial_sigma * pc.spatial_sigma;
+
+	var sum = 0.;
+	var weight_sum = 0.;
+	for (var i = -radius; i <= radius; i++){
+		for (var j = -radius; j <= radius; j++){
+			let neighbour = access_local(center.x + 2 * i, center.y + 2 * j);
+			let diff = neighbour - value;
+			let spatial = f32(i * i + j * j) / spatial_denom;
+			let range = diff * diff / range_denom;
+			let weight = exp(-spatial - range);
+			sum += weight * neighbour;
+			weight_sum += weight;
+		}
+	}
+
+	return sum / weight_sum;
+}
+
+@compute @workgroup_size(#WG_X, #WG_Y, #WG_Z)
+fn main(
+	@builtin(global_invocation_id) global_id: vec3<u32>,
+	@builtin(local_invocation_id) local_id: vec3<u32>,
+	@builtin(local_invocation_index) local_index: u32,
+	@builtin(workgroup_id) wg_id: vec3<u32>,
+){
+	let global_bounds = vec2(#HEIGHT, #WIDTH);
+
+	setup_local_cfa(wg_id, local_index, global_bounds);
+
+	if is_outside_image(global_id, global_bounds){
+		return;
+	}
+
+	let local_center = vec2<i32>(local_id.xy) + vec2(#PADDING);
+	let global_flat = i32(global_id.x) * #WIDTH + i32(global_id.y);
+
+	if pc.enabled == 0 || pc.strength <= 0. || pc.spatial_sigma <= 0.{
+		output[global_flat] = access_local(local_center.x, local_center.y);
+		return;
+	}
+
+	let cfa = (global_id.x % 2u) * 2u + global_id.y % 2u;
+
+	output[global_flat] = bilateral(local_center, cfa);
+}
@@ -0,0 +1,91 @@
+@group(0) @binding(0)
+var<storage, read> input: array<vec4<f32>>;
+
+@group(0) @binding(1)
+var<storage, read_write> output: array<vec4<f32>>;
+
+struct FalseColorSuppressionParams{
+	enabled: i32,
+	// 0 keeps the demosaiced chroma, 1 replaces it with the median
+	strength: f32,
+}
+
+var<push_constant> pc: FalseColorSuppressionParams;
+
+var<workgroup> local: array<vec4<f32>, #expr{(WG_X + 2 * PADDING) * (WG_Y + 2 * PADDING)}>;
+
+#import reflect_vec
+#import is_outside_image
+#import setup_local
+#import access_local_vec4
+
+const local_height = #expr{WG_X + 2 * PADDING};
+const local_width = #expr{WG_Y + 2 * PADDING};
+const local_size = #expr{(WG_X + 2 * PADDING) * (WG_Y + 2 * PADDING)};
+
+const window_size = #expr{(2 * PADDING + 1) * (2 * PADDING + 1)};
+
+// Median of the R-G and B-G differences in the window around center
+fn chroma_median(center: vec2<i32>) -> vec2<f32>{
+	var red: array<f32, window_size>;
+	var blue: array<f32, window_size>;
+
+	var n = 0;
+	for (var i = -#PADDING; i <= #PADDING; i++){
+		for (var j = -#PADDING; j <= #PADDING; j++){
+			let rgb = access_local_vec4(center.x + i, center.y + j);
+			let r = rgb.r - rgb.g;
+			let b = rgb.b - rgb.g;
+
+			// Insertion sort, the window is small
+			var k = n;
+			while k > 0 && red[k - 1] > r{
+				red[k] = red[k - 1];
+				k--;
+			}
+			red[k] = r;
+
+			k = n;
+			while k > 0 && blue[k - 1] > b{
+				blue[k] = blue[k - 1];
+				k--;
+			}
+			blue[k] = b;
+
+			n++;
+		}
+	}
+
+	return vec2(red[window_size / 2], blue[window_size / 2]);
+}
+
+@compute @workgroup_size(#WG_X, #WG_Y, #WG_Z)
+fn main(
+	@builtin(global_invocation_id) global_id: vec3<u32>,
+	@builtin(local_invocation_id) local_id: vec3<u32>,
+	@builtin(local_invocation_index) local_index: u32,
+	@builtin(workgroup_id) wg_id: vec3<u32>,
+){
+	let global_bounds = vec2(#HEIGHT, #WIDTH);
+
+	setup_local(wg_id, local_index, global_bounds);
+
+	if is_outside_image(global_id, global_bounds){
+		return;
+	}
+
+	let local_center = vec2<i32>(local_id.xy) + vec2(#PADDING);
+	let global_flat = i32(global_id.x) * #WIDTH + i32(global_id.y);
+
+	let rgb = access_local_vec4(local_center.x, local_center.y);
+
+	if pc.enabled == 0 || pc.strength <= 0.{
+		output[global_flat] = rgb;
+		return;
+	}
+
+	let chroma = vec2(rgb.r - rgb.g, rgb.b - rgb.g);
+	let suppressed = mix(chroma, chroma_median(local_center), clamp(pc.strength, 0., 1.));
+
+	output[global_flat] = vec4(rgb.g + suppressed.x, rgb.g, rgb.g + suppressed.y, rgb.a);
+}
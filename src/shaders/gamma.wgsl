@@ -0,0 +1,55 @@
+@group(0) @binding(0)
+var<storage, read_write> input: array<vec4<f32>>;
+
+struct GammaParams{
+	gain: f32,
+	gamma: f32,
+	// CurvesPush::enabled, the curves replace the gamma
+	curves: i32,
+}
+
+var<push_constant> pc: GammaParams;
+
+// Baked by curves.rs: the red, green and blue curves, each followed by the master curve
+@group(0) @binding(1)
+var<storage, read> curves: array<vec4<f32>, #CURVE_LUT_SIZE>;
+
+// Linear interpolation between the entries of the curve LUT
+fn apply_curves(color: vec3<f32>) -> vec3<f32>{
+	let position = clamp(color, vec3(0.), vec3(1.)) * f32(#CURVE_LUT_SIZE - 1);
+	let lower = vec3<u32>(min(floor(position), vec3(f32(#CURVE_LUT_SIZE - 2))));
+	let f = position - vec3<f32>(lower);
+
+	var out: vec3<f32>;
+	for (var channel = 0; channel < 3; channel++){
+		let low = curves[lower[channel]][channel];
+		let high = curves[lower[channel] + 1u][channel];
+		out[channel] = mix(low, high, f[channel]);
+	}
+	return out;
+}
+
+#import is_outside_image
+
+@compute @workgroup_size(#WG_X, #WG_Y, #WG_Z)
+fn main(
+	@builtin(global_invocation_id) global_id: vec3<u32>,
+){
+	let global_bounds = vec2(#HEIGHT, #WIDTH);
+
+	if is_outside_image(global_id, global_bounds){
+		return;
+	}
+
+	let global_flat = i32(global_id.x) * #WIDTH + i32(global_id.y);
+
+	let color = input[global_flat];
+	var rgb: vec3<f32>;
+	if pc.curves != 0{
+		rgb = pc.gain * apply_curves(color.rgb);
+	} else {
+		rgb = pc.gain * pow(color.rgb, vec3(pc.gamma));
+	}
+
+	input[global_flat] = vec4(rgb, color.w);
+}
@@ -0,0 +1,104 @@
+@group(0) @binding(0)
+var<storage, read> input: array<f32>;
+
+@group(0) @binding(1)
+var<storage, read_write> output: array<f32>;
+
+@group(0) @binding(2)
+var<storage, read> mean: vec4<f32>;
+
+struct GreenEqualizationParams{
+	enabled: i32,
+	local: i32,
+	threshold: f32,
+	strength: f32,
+}
+
+var<push_constant> pc: GreenEqualizationParams;
+
+var<workgroup> local: array<f32, #expr{(WG_X + 2 * PADDING) * (WG_Y + 2 * PADDING)}>;
+
+#import all_cfa_utils
+
+const local_height = #expr{WG_X + 2 * PADDING};
+const local_width = #expr{WG_Y + 2 * PADDING};
+const local_size = #expr{(WG_X + 2 * PADDING) * (WG_Y + 2 * PADDING)};
+
+// Pulls a green pixel towards the average of the two green channels, using the
+// global Gr/Gb means.
+fn equalize_global(value: f32, is_gr: bool) -> f32{
+	let green_avg = (mean.y + mean.z) / 2.;
+	var own_avg = mean.z;
+	if is_gr{
+		own_avg = mean.y;
+	}
+	if own_avg <= 0.{
+		return value;
+	}
+	return value * green_avg / own_avg;
+}
+
+// Compares the same-channel greens (centre and the four at distance 2) with the
+// other green channel (the four diagonals). Small relative differences are
+// treated as imbalance and removed, larger ones are assumed to be edges.
+fn equalize_local(center: vec2<i32>) -> f32{
+	let value = access_local(center.x, center.y);
+	let same = (
+		value +
+		access_local(center.x - 2, center.y) +
+		access_local(center.x + 2, center.y) +
+		access_local(center.x, center.y - 2) +
+		access_local(center.x, center.y + 2)
+	) / 5.0;
+	let other = (
+		access_local(center.x - 1, center.y - 1) +
+		access_local(center.x - 1, center.y + 1) +
+		access_local(center.x + 1, center.y - 1) +
+		access_local(center.x + 1, center.y + 1)
+	) / 4.0;
+
+	let target_level = (same + other) / 2.0;
+	let relative_diff = abs(same - other) / max(target_level, 1e-6);
+	let weight = 1.0 - smoothstep(0.5 * pc.threshold, pc.threshold, relative_diff);
+
+	return value + weight * (target_level - same);
+}
+
+@compute @workgroup_size(#WG_X, #WG_Y, #WG_Z)
+fn main(
+	@builtin(global_invocation_id) global_id: vec3<u32>,
+	@builtin(local_invocation_id) local_id: vec3<u32>,
+	@builtin(local_invocation_index) local_index: u32,
+	@builtin(workgroup_id) wg_id: vec3<u32>,
+){
+	let global_bounds = vec2(#HEIGHT, #WIDTH);
+
+	setup_local_cfa(wg_id, local_index, global_bounds);
+
+	if is_outside_image(global_id, global_bounds){
+		return;
+	}
+
+	let local_center = vec2<i32>(local_id.xy) + vec2(#PADDING);
+	let global_flat = i32(global_id.x) * #WIDTH + i32(global_id.y);
+
+	let mod_row = global_id.x % 2u;
+	let mod_col = global_id.y % 2u;
+
+	let value = access_local(local_center.x, local_center.y);
+	let is_green = mod_row != mod_col;
+
+	if pc.enabled == 0 || !is_green{
+		output[global_flat] = value;
+		return;
+	}
+
+	var equalized: f32;
+	if pc.local == 0{
+		equalized = equalize_global(value, mod_row == 0u);
+	} else {
+		equalized = equalize_local(local_center);
+	}
+
+	output[global_flat] = mix(value, equalized, pc.strength);
+}
@@ -0,0 +1,115 @@
+@group(0) @binding(0)
+var<storage, read> input: array<vec4<f32>>;
+
+// Per CFA position (R, Gr, Gb, B), written by awb_gains.wgsl
+@group(0) @binding(1)
+var<storage, read> gains: vec4<f32>;
+
+@group(0) @binding(2)
+var<storage, read_write> output: array<vec4<f32>>;
+
+struct HighlightRecoveryParams{
+	// Per channel, before white balance
+	white_level: vec4<f32>,
+	// HighlightMode in operations.rs
+	mode: i32,
+	threshold: f32,
+}
+
+var<push_constant> pc: HighlightRecoveryParams;
+
+const CLIP = 0;
+const BLEND = 1;
+const RECONSTRUCT = 2;
+
+var<workgroup> local: array<vec4<f32>, #expr{(WG_X + 2 * PADDING) * (WG_Y + 2 * PADDING)}>;
+
+#import reflect_vec
+#import is_outside_image
+#import setup_local
+#import access_local_vec4
+
+const local_height = #expr{WG_X + 2 * PADDING};
+const local_width = #expr{WG_Y + 2 * PADDING};
+const local_size = #expr{(WG_X + 2 * PADDING) * (WG_Y + 2 * PADDING)};
+
+fn blend(rgb: vec3<f32>, clip: vec3<f32>) -> vec3<f32>{
+	let clipped = min(rgb, clip);
+	return vec3((clipped.r + clipped.g + clipped.b) / 3.);
+}
+
+// Estimates the clipped channels from the unclipped ones, with the channel
+// ratios of the unclipped pixels around center weighted by their brightness
+fn reconstruct(center: vec2<i32>, rgb: vec3<f32>, clip: vec3<f32>) -> vec3<f32>{
+	let unclipped = vec3<f32>(rgb < pc.threshold * clip);
+	if all(unclipped == vec3(0.)){
+		return blend(rgb, clip);
+	}
+
+	var ratios = vec3(0.);
+	var weights = 0.;
+	for (var i = -#PADDING; i <= #PADDING; i++){
+		for (var j = -#PADDING; j <= #PADDING; j++){
+			let neighbour = access_local_vec4(center.x + i, center.y + j).rgb;
+			let reference = dot(neighbour, unclipped);
+			if any(neighbour >= pc.threshold * clip) || reference <= 0.{
+				continue;
+			}
+			// Summed like this, the channel ratios to the reference are weighted by it
+			ratios += neighbour;
+			weights += reference;
+		}
+	}
+
+	if weights <= 0.{
+		return blend(rgb, clip);
+	}
+
+	let estimate = dot(rgb, unclipped) * ratios / weights;
+	// A clipped channel was at least at the white level
+	return select(max(estimate, rgb), rgb, unclipped == vec3(1.));
+}
+
+@compute @workgroup_size(#WG_X, #WG_Y, #WG_Z)
+fn main(
+	@builtin(global_invocation_id) global_id: vec3<u32>,
+	@builtin(local_invocation_id) local_id: vec3<u32>,
+	@builtin(local_invocation_index) local_index: u32,
+	@builtin(workgroup_id) wg_id: vec3<u32>,
+){
+	let global_bounds = vec2(#HEIGHT, #WIDTH);
+
+	setup_local(wg_id, local_index, global_bounds);
+
+	if is_outside_image(global_id, global_bounds){
+		return;
+	}
+
+	let local_center = vec2<i32>(local_id.xy) + vec2(#PADDING);
+	let global_flat = i32(global_id.x) * #WIDTH + i32(global_id.y);
+
+	let color = access_local_vec4(local_center.x, local_center.y);
+	let rgb = color.rgb;
+
+	// Where each channel clipped after white balance
+	let clip = pc.white_level.rgb * vec3(gains.x, min(gains.y, gains.z), gains.w);
+
+	if pc.mode == CLIP{
+		let white = min(clip.r, min(clip.g, clip.b));
+		output[global_flat] = vec4(min(rgb, vec3(white)), color.a);
+		return;
+	}
+
+	// 0 up to the threshold, 1 from the white level on
+	let level = max(rgb.r / clip.r, max(rgb.g / clip.g, rgb.b / clip.b));
+	let t = clamp((level - pc.threshold) / max(1. - pc.threshold, 1e-6), 0., 1.);
+
+	var repaired: vec3<f32>;
+	if pc.mode == BLEND{
+		repaired = blend(rgb, clip);
+	} else {
+		repaired = reconstruct(local_center, rgb, clip);
+	}
+
+	output[global_flat] = vec4(mix(rgb, repaired, t), color.a);
+}
@@ -0,0 +1,121 @@
+@group(0) @binding(0)
+var<storage, read> input: array<vec4<f32>>;
+
+@group(0) @binding(1)
+var<storage, read_write> output: array<vec4<f32>>;
+
+struct LensCorrectionParams{
+	k1: f32,
+	k2: f32,
+	k3: f32,
+	p1: f32,
+	p2: f32,
+	center_x: f32,
+	center_y: f32,
+	red_scale: f32,
+	blue_scale: f32,
+	// ResamplingFilter in operations.rs
+	filter: i32,
+	crop_scale: f32,
+	_padding: f32,
+}
+
+var<push_constant> pc: LensCorrectionParams;
+
+const BILINEAR = 0;
+const BICUBIC = 1;
+
+// How far outside the image a sample still counts as inside, more than the
+// crop in lens.rs allows
+const TOLERANCE = 1e-3;
+
+#import is_outside_image
+
+// The Brown-Conrady model of lens.rs, x to the right and y down
+fn distort(p: vec2<f32>) -> vec2<f32>{
+	let r2 = dot(p, p);
+	let radial = 1. + r2 * (pc.k1 + r2 * (pc.k2 + r2 * pc.k3));
+	let tangential = vec2(
+		2. * pc.p1 * p.x * p.y + pc.p2 * (r2 + 2. * p.x * p.x),
+		pc.p1 * (r2 + 2. * p.y * p.y) + 2. * pc.p2 * p.x * p.y,
+	);
+	return p * radial + tangential;
+}
+
+fn load(x: i32, y: i32) -> vec4<f32>{
+	let clamped = clamp(vec2(x, y), vec2(0), vec2(#WIDTH - 1, #HEIGHT - 1));
+	return input[clamped.y * #WIDTH + clamped.x];
+}
+
+fn catmull_rom(t: f32) -> vec4<f32>{
+	let t2 = t * t;
+	let t3 = t2 * t;
+	return vec4(
+		-0.5 * t3 + t2 - 0.5 * t,
+		1.5 * t3 - 2.5 * t2 + 1.,
+		-1.5 * t3 + 2. * t2 + 0.5 * t,
+		0.5 * t3 - 0.5 * t2,
+	);
+}
+
+// Resamples at a position in pixels, with pixel centres at whole numbers
+fn sample(position: vec2<f32>) -> vec4<f32>{
+	let base = floor(position);
+	let f = position - base;
+	let x = i32(base.x);
+	let y = i32(base.y);
+
+	if pc.filter == BICUBIC{
+		let wx = catmull_rom(f.x);
+		let wy = catmull_rom(f.y);
+		var out = vec4(0.);
+		for (var j = 0; j < 4; j++){
+			var row = vec4(0.);
+			for (var i = 0; i < 4; i++){
+				row += wx[i] * load(x + i - 1, y + j - 1);
+			}
+			out += wy[j] * row;
+		}
+		return out;
+	}
+
+	let top = mix(load(x, y), load(x + 1, y), f.x);
+	let bottom = mix(load(x, y + 1), load(x + 1, y + 1), f.x);
+	return mix(top, bottom, f.y);
+}
+
+fn is_inside(position: vec2<f32>) -> bool{
+	let last = vec2(f32(#WIDTH - 1), f32(#HEIGHT - 1));
+	return all(position >= vec2(-TOLERANCE)) && all(position <= last + TOLERANCE);
+}
+
+@compute @workgroup_size(#WG_X, #WG_Y, #WG_Z)
+fn main(
+	@builtin(global_invocation_id) global_id: vec3<u32>,
+){
+	let global_bounds = vec2(#HEIGHT, #WIDTH);
+
+	if is_outside_image(global_id, global_bounds){
+		return;
+	}
+
+	let global_flat = i32(global_id.x) * #WIDTH + i32(global_id.y);
+
+	let half = vec2(f32(#WIDTH), f32(#HEIGHT)) / 2.;
+	let centre = half * (1. + vec2(pc.center_x, pc.center_y));
+	let norm = length(half);
+
+	let pixel = vec2(f32(global_id.y), f32(global_id.x));
+	let distorted = distort((pixel + 0.5 - centre) / norm * pc.crop_scale);
+
+	let scales = vec3(pc.red_scale, 1., pc.blue_scale);
+	var color = vec4(0., 0., 0., 1.);
+	for (var channel = 0; channel < 3; channel++){
+		let position = centre + distorted * scales[channel] * norm - 0.5;
+		if is_inside(position){
+			color[channel] = sample(position)[channel];
+		}
+	}
+
+	output[global_flat] = color;
+}
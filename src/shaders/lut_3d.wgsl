@@ -0,0 +1,89 @@
+@group(0) @binding(0)
+var<storage, read_write> input: array<vec4<f32>>;
+
+// LutHeader in operations.rs, followed by the table with red changing fastest
+struct Lut{
+	size: u32,
+	_padding: array<u32, 3>,
+	domain_min: vec4<f32>,
+	domain_max: vec4<f32>,
+	table: array<vec4<f32>>,
+}
+
+@group(0) @binding(1)
+var<storage, read> lut: Lut;
+
+struct LutParams{
+	// LutInterpolation in operations.rs
+	interpolation: i32,
+}
+
+var<push_constant> pc: LutParams;
+
+const TRILINEAR = 0;
+
+#import is_outside_image
+
+fn entry(base: vec3<u32>, offset: vec3<u32>) -> vec3<f32>{
+	let coord = base + offset;
+	return lut.table[(coord.z * lut.size + coord.y) * lut.size + coord.x].rgb;
+}
+
+fn trilinear(base: vec3<u32>, f: vec3<f32>) -> vec3<f32>{
+	let c00 = mix(entry(base, vec3(0u, 0u, 0u)), entry(base, vec3(1u, 0u, 0u)), f.x);
+	let c10 = mix(entry(base, vec3(0u, 1u, 0u)), entry(base, vec3(1u, 1u, 0u)), f.x);
+	let c01 = mix(entry(base, vec3(0u, 0u, 1u)), entry(base, vec3(1u, 0u, 1u)), f.x);
+	let c11 = mix(entry(base, vec3(0u, 1u, 1u)), entry(base, vec3(1u, 1u, 1u)), f.x);
+	return mix(mix(c00, c10, f.y), mix(c01, c11, f.y), f.z);
+}
+
+fn tetrahedral(base: vec3<u32>, f: vec3<f32>) -> vec3<f32>{
+	let c000 = entry(base, vec3(0u, 0u, 0u));
+	let c111 = entry(base, vec3(1u, 1u, 1u));
+	if f.x > f.y{
+		if f.y > f.z{
+			return (1. - f.x) * c000 + (f.x - f.y) * entry(base, vec3(1u, 0u, 0u)) + (f.y - f.z) * entry(base, vec3(1u, 1u, 0u)) + f.z * c111;
+		} else if f.x > f.z{
+			return (1. - f.x) * c000 + (f.x - f.z) * entry(base, vec3(1u, 0u, 0u)) + (f.z - f.y) * entry(base, vec3(1u, 0u, 1u)) + f.y * c111;
+		} else {
+			return (1. - f.z) * c000 + (f.z - f.x) * entry(base, vec3(0u, 0u, 1u)) + (f.x - f.y) * entry(base, vec3(1u, 0u, 1u)) + f.y * c111;
+		}
+	} else if f.z > f.y{
+		return (1. - f.z) * c000 + (f.z - f.y) * entry(base, vec3(0u, 0u, 1u)) + (f.y - f.x) * entry(base, vec3(0u, 1u, 1u)) + f.x * c111;
+	} else if f.z > f.x{
+		return (1. - f.y) * c000 + (f.y - f.z) * entry(base, vec3(0u, 1u, 0u)) + (f.z - f.x) * entry(base, vec3(0u, 1u, 1u)) + f.x * c111;
+	} else {
+		return (1. - f.y) * c000 + (f.y - f.x) * entry(base, vec3(0u, 1u, 0u)) + (f.x - f.z) * entry(base, vec3(1u, 1u, 0u)) + f.z * c111;
+	}
+}
+
+@compute @workgroup_size(#WG_X, #WG_Y, #WG_Z)
+fn main(
+	@builtin(global_invocation_id) global_id: vec3<u32>,
+){
+	let global_bounds = vec2(#HEIGHT, #WIDTH);
+
+	if is_outside_image(global_id, global_bounds) || lut.size < 2u{
+		return;
+	}
+
+	let global_flat = i32(global_id.x) * #WIDTH + i32(global_id.y);
+
+	let color = input[global_flat];
+
+	// The reference is `Lut3D::sample` in cube.rs
+	let last = f32(lut.size - 1u);
+	let domain = lut.domain_max.rgb - lut.domain_min.rgb;
+	let position = clamp((color.rgb - lut.domain_min.rgb) / domain, vec3(0.), vec3(1.)) * last;
+	let base = min(floor(position), vec3(last - 1.));
+	let f = position - base;
+
+	var rgb: vec3<f32>;
+	if pc.interpolation == TRILINEAR{
+		rgb = trilinear(vec3<u32>(base), f);
+	} else {
+		rgb = tetrahedral(vec3<u32>(base), f);
+	}
+
+	input[global_flat] = vec4(rgb, color.w);
+}
@@ -0,0 +1,80 @@
+@group(0) @binding(0)
+var<storage, read_write> input: array<vec4<f32>>;
+
+struct OutputTransformParams{
+	// Camera RGB to the output primaries
+	matrix: mat4x4<f32>,
+	// TransferFunction in operations.rs
+	transfer: i32,
+	// Linear value of 1 in the units the transfer function expects
+	scale: f32,
+}
+
+var<push_constant> pc: OutputTransformParams;
+
+const LINEAR = 0;
+const SRGB = 1;
+const PQ = 2;
+const HLG = 3;
+
+#import is_outside_image
+
+fn srgb_oetf(linear: vec3<f32>) -> vec3<f32>{
+	let clipped = max(linear, vec3(0.));
+	let curve = 1.055 * pow(clipped, vec3(1. / 2.4)) - 0.055;
+	return select(curve, 12.92 * clipped, clipped <= vec3(0.0031308));
+}
+
+// Luminance in cd/m²
+fn pq_oetf(luminance: vec3<f32>) -> vec3<f32>{
+	let m1 = 2610. / 16384.;
+	let m2 = 2523. / 4096. * 128.;
+	let c1 = 3424. / 4096.;
+	let c2 = 2413. / 4096. * 32.;
+	let c3 = 2392. / 4096. * 32.;
+
+	let y = pow(clamp(luminance / 10000., vec3(0.), vec3(1.)), vec3(m1));
+	return pow((c1 + c2 * y) / (1. + c3 * y), vec3(m2));
+}
+
+fn hlg_oetf(scene: vec3<f32>) -> vec3<f32>{
+	let a = 0.17883277;
+	let b = 0.28466892;
+	let c = 0.5599107;
+
+	let clipped = clamp(scene, vec3(0.), vec3(1.));
+	// The log branch is discarded where its argument isn't positive
+	let curve = a * log(max(12. * clipped - b, vec3(1e-6))) + c;
+	return select(curve, sqrt(3. * clipped), clipped <= vec3(1. / 12.));
+}
+
+@compute @workgroup_size(#WG_X, #WG_Y, #WG_Z)
+fn main(
+	@builtin(global_invocation_id) global_id: vec3<u32>,
+){
+	let global_bounds = vec2(#HEIGHT, #WIDTH);
+
+	if is_outside_image(global_id, global_bounds){
+		return;
+	}
+
+	let global_flat = i32(global_id.x) * #WIDTH + i32(global_id.y);
+
+	let color = input[global_flat];
+	var rgb = (pc.matrix * vec4(color.rgb, 0.)).rgb * pc.scale;
+
+	switch pc.transfer{
+		case SRGB: {
+			rgb = srgb_oetf(rgb);
+		}
+		case PQ: {
+			rgb = pq_oetf(rgb);
+		}
+		case HLG: {
+			rgb = hlg_oetf(rgb);
+		}
+		default: {}
+	}
+
+	input[global_flat] = vec4(rgb, color.w);
+}
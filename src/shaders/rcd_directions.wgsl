@@ -0,0 +1,60 @@
+// Bayer frame
+@group(0) @binding(0)
+var<storage, read> input: array<f32>;
+
+// Low-pass, vertical and diagonal discrimination
+@group(0) @binding(1)
+var<storage, read_write> output: array<vec4<f32>>;
+
+#import is_outside_image
+#import mirror
+#import rcd_utils
+
+fn cfa(coord: vec2<i32>) -> f32{
+	let mirrored = mirror_vec(coord, vec2(#HEIGHT, #WIDTH));
+	return input[mirrored.x * #WIDTH + mirrored.y];
+}
+
+fn high_pass(center: vec2<i32>, direction: vec2<i32>) -> f32{
+	let value = cfa(center - 3 * direction) - cfa(center - direction) - cfa(center + direction) + cfa(center + 3 * direction) -
+		3.0 * (cfa(center - 2 * direction) + cfa(center + 2 * direction)) + 6.0 * cfa(center);
+	return value * value;
+}
+
+fn energy(center: vec2<i32>, direction: vec2<i32>) -> f32{
+	let sum = high_pass(center - direction, direction) + high_pass(center, direction) + high_pass(center + direction, direction);
+	return max(sum, RCD_EPS_SQ);
+}
+
+// First RCD pass: the low-pass, and how much the vertical (against
+// horizontal) and the main diagonal (against the anti-diagonal) high-pass
+// energy dominates
+@compute @workgroup_size(#WG_X, #WG_Y, #WG_Z)
+fn main(
+	@builtin(global_invocation_id) global_id: vec3<u32>,
+){
+	let global_bounds = vec2(#HEIGHT, #WIDTH);
+
+	if is_outside_image(global_id, global_bounds){
+		return;
+	}
+
+	let center = vec2<i32>(global_id.xy);
+
+	let vertical = energy(center, vec2(1, 0));
+	let horizontal = energy(center, vec2(0, 1));
+	let diagonal = energy(center, vec2(1, 1));
+	let anti_diagonal = energy(center, vec2(1, -1));
+
+	let orthogonal = cfa(center - vec2(1, 0)) + cfa(center + vec2(1, 0)) + cfa(center - vec2(0, 1)) + cfa(center + vec2(0, 1));
+	let corners = cfa(center + vec2(-1, -1)) + cfa(center + vec2(-1, 1)) + cfa(center + vec2(1, -1)) + cfa(center + vec2(1, 1));
+	// Black-levelled noise can be negative, which would flip the ratios
+	let low_pass = max(cfa(center) + orthogonal / 2.0 + corners / 4.0, 0.0);
+
+	output[center.x * #WIDTH + center.y] = vec4(
+		low_pass,
+		vertical / (vertical + horizontal),
+		diagonal / (diagonal + anti_diagonal),
+		0.0,
+	);
+}
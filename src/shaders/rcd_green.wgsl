@@ -0,0 +1,81 @@
+// Bayer frame
+@group(0) @binding(0)
+var<storage, read> input: array<f32>;
+
+// From rcd_directions.wgsl
+@group(0) @binding(1)
+var<storage, read> directions: array<vec4<f32>>;
+
+// Green, vertical and diagonal discrimination
+@group(0) @binding(2)
+var<storage, read_write> output: array<vec4<f32>>;
+
+#import is_outside_image
+#import mirror
+#import rcd_utils
+
+fn flat_index(coord: vec2<i32>) -> i32{
+	let mirrored = mirror_vec(coord, vec2(#HEIGHT, #WIDTH));
+	return mirrored.x * #WIDTH + mirrored.y;
+}
+
+fn cfa(coord: vec2<i32>) -> f32{
+	return input[flat_index(coord)];
+}
+
+fn low_pass(coord: vec2<i32>) -> f32{
+	return directions[flat_index(coord)].x;
+}
+
+// Gradient and ratio corrected estimate towards one side
+fn side(center: vec2<i32>, direction: vec2<i32>) -> vec2<f32>{
+	let gradient = RCD_EPS +
+		abs(cfa(center + direction) - cfa(center - direction)) +
+		abs(cfa(center) - cfa(center + 2 * direction)) +
+		abs(cfa(center + direction) - cfa(center + 3 * direction)) +
+		abs(cfa(center + 2 * direction) - cfa(center + 4 * direction));
+	let estimate = cfa(center + direction) * 2.0 * low_pass(center) /
+		(RCD_EPS_SQ + low_pass(center) + low_pass(center + 2 * direction));
+	return vec2(gradient, estimate);
+}
+
+// Second RCD pass: green at the red and blue sites from the ratio corrected
+// neighbours in the four cardinal directions
+@compute @workgroup_size(#WG_X, #WG_Y, #WG_Z)
+fn main(
+	@builtin(global_invocation_id) global_id: vec3<u32>,
+){
+	let global_bounds = vec2(#HEIGHT, #WIDTH);
+
+	if is_outside_image(global_id, global_bounds){
+		return;
+	}
+
+	let center = vec2<i32>(global_id.xy);
+
+	let neighbourhood = (
+		directions[flat_index(center + vec2(-1, -1))] +
+		directions[flat_index(center + vec2(-1, 1))] +
+		directions[flat_index(center + vec2(1, -1))] +
+		directions[flat_index(center + vec2(1, 1))]
+	) / 4.0;
+	let own = directions[flat_index(center)];
+	let vh = discrimination(own.y, neighbourhood.y);
+	let pq = discrimination(own.z, neighbourhood.z);
+
+	let global_flat = center.x * #WIDTH + center.y;
+
+	if is_green(center){
+		output[global_flat] = vec4(cfa(center), vh, pq, 0.0);
+		return;
+	}
+
+	let north = side(center, vec2(-1, 0));
+	let south = side(center, vec2(1, 0));
+	let west = side(center, vec2(0, -1));
+	let east = side(center, vec2(0, 1));
+	let vertical = blend(north.x, north.y, south.x, south.y);
+	let horizontal = blend(west.x, west.y, east.x, east.y);
+
+	output[global_flat] = vec4(vertical + vh * (horizontal - vertical), vh, pq, 0.0);
+}
@@ -0,0 +1,67 @@
+// From rcd_red_blue.wgsl
+@group(0) @binding(0)
+var<storage, read> input: array<vec4<f32>>;
+
+@group(0) @binding(1)
+var<storage, read_write> output: array<vec4<f32>>;
+
+#import is_outside_image
+#import mirror
+#import rcd_utils
+
+fn at(coord: vec2<i32>) -> vec4<f32>{
+	let mirrored = mirror_vec(coord, vec2(#HEIGHT, #WIDTH));
+	return input[mirrored.x * #WIDTH + mirrored.y];
+}
+
+// Gradients and colour differences towards one side, red in x and y, blue
+// in z and w
+fn side(center: vec2<i32>, direction: vec2<i32>) -> vec4<f32>{
+	let near = at(center + direction);
+	let opposite = at(center - direction);
+	let far = at(center + 3 * direction);
+	let green_gradient = abs(at(center).y - at(center + 2 * direction).y);
+	let gradient = RCD_EPS + green_gradient + abs(near.xz - opposite.xz) + abs(near.xz - far.xz);
+	let difference = near.xz - near.y;
+	return vec4(gradient.x, difference.x, gradient.y, difference.y);
+}
+
+fn interpolate(north: vec2<f32>, south: vec2<f32>, west: vec2<f32>, east: vec2<f32>, vh: f32) -> f32{
+	let vertical = blend(north.x, north.y, south.x, south.y);
+	let horizontal = blend(west.x, west.y, east.x, east.y);
+	return vertical + vh * (horizontal - vertical);
+}
+
+// Last RCD pass: red and blue at the green sites from the colour differences
+// in the four cardinal directions
+@compute @workgroup_size(#WG_X, #WG_Y, #WG_Z)
+fn main(
+	@builtin(global_invocation_id) global_id: vec3<u32>,
+){
+	let global_bounds = vec2(#HEIGHT, #WIDTH);
+
+	if is_outside_image(global_id, global_bounds){
+		return;
+	}
+
+	let center = vec2<i32>(global_id.xy);
+	let global_flat = center.x * #WIDTH + center.y;
+
+	let own = input[global_flat];
+
+	if !is_green(center){
+		output[global_flat] = vec4(own.xyz, 1.0);
+		return;
+	}
+
+	let north = side(center, vec2(-1, 0));
+	let south = side(center, vec2(1, 0));
+	let west = side(center, vec2(0, -1));
+	let east = side(center, vec2(0, 1));
+
+	let g = own.y;
+	let red = g + interpolate(north.xy, south.xy, west.xy, east.xy, own.w);
+	let blue = g + interpolate(north.zw, south.zw, west.zw, east.zw, own.w);
+
+	output[global_flat] = vec4(red, g, blue, 1.0);
+}
@@ -0,0 +1,78 @@
+// Bayer frame
+@group(0) @binding(0)
+var<storage, read> input: array<f32>;
+
+// From rcd_green.wgsl
+@group(0) @binding(1)
+var<storage, read> green: array<vec4<f32>>;
+
+// Red, green, blue and vertical discrimination
+@group(0) @binding(2)
+var<storage, read_write> output: array<vec4<f32>>;
+
+#import is_outside_image
+#import mirror
+#import rcd_utils
+
+fn flat_index(coord: vec2<i32>) -> i32{
+	let mirrored = mirror_vec(coord, vec2(#HEIGHT, #WIDTH));
+	return mirrored.x * #WIDTH + mirrored.y;
+}
+
+fn cfa(coord: vec2<i32>) -> f32{
+	return input[flat_index(coord)];
+}
+
+fn green_at(coord: vec2<i32>) -> f32{
+	return green[flat_index(coord)].x;
+}
+
+// Gradient and colour difference towards one diagonal
+fn side(center: vec2<i32>, direction: vec2<i32>) -> vec2<f32>{
+	let gradient = RCD_EPS +
+		abs(cfa(center + direction) - cfa(center - direction)) +
+		abs(cfa(center + direction) - cfa(center + 3 * direction)) +
+		abs(green_at(center) - green_at(center + 2 * direction));
+	return vec2(gradient, cfa(center + direction) - green_at(center + direction));
+}
+
+// Third RCD pass: blue at the red sites and red at the blue sites from the
+// colour differences in the four diagonal directions
+@compute @workgroup_size(#WG_X, #WG_Y, #WG_Z)
+fn main(
+	@builtin(global_invocation_id) global_id: vec3<u32>,
+){
+	let global_bounds = vec2(#HEIGHT, #WIDTH);
+
+	if is_outside_image(global_id, global_bounds){
+		return;
+	}
+
+	let center = vec2<i32>(global_id.xy);
+	let global_flat = center.x * #WIDTH + center.y;
+
+	let own = green[global_flat];
+	let g = own.x;
+	let vh = own.y;
+	let pq = own.z;
+
+	if is_green(center){
+		output[global_flat] = vec4(0.0, g, 0.0, vh);
+		return;
+	}
+
+	let north_west = side(center, vec2(-1, -1));
+	let south_east = side(center, vec2(1, 1));
+	let north_east = side(center, vec2(-1, 1));
+	let south_west = side(center, vec2(1, -1));
+	let p = blend(north_west.x, north_west.y, south_east.x, south_east.y);
+	let q = blend(north_east.x, north_east.y, south_west.x, south_west.y);
+	let opposite = g + p + pq * (q - p);
+
+	let value = cfa(center);
+	if center.x % 2 == 0{
+		output[global_flat] = vec4(value, g, opposite, vh);
+	} else {
+		output[global_flat] = vec4(opposite, g, value, vh);
+	}
+}
@@ -3,12 +3,21 @@ var<storage, read_write> input: array<vec4<f32>>;
 
 struct RGBSpaceParams{
 	color_correction_matrix: mat4x4<f32>,
-	gain: f32,
-	gamma: f32,
 }
 
 var<push_constant> pc: RGBSpaceParams;
 
+// Exposure in operations.rs, written by auto_exposure.wgsl
+struct Exposure{
+	digital_gain: f32,
+	exposure_ratio: f32,
+	metered_luminance: f32,
+	_padding: f32,
+}
+
+@group(0) @binding(1)
+var<storage, read> exposure: Exposure;
+
 #import is_outside_image
 
 @compute @workgroup_size(#WG_X, #WG_Y, #WG_Z)
@@ -25,8 +34,7 @@ fn main(
 
 	var color = input[global_flat];
 	color.w = 1.0;
-	color = pc.color_correction_matrix * color;
-	color = pc.gain * pow(color, vec4(pc.gamma));
+	color = exposure.digital_gain * (pc.color_correction_matrix * color);
 	color.w = 1.0;
 
 	input[global_flat] = color;
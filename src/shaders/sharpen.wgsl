@@ -0,0 +1,88 @@
+@group(0) @binding(0)
+var<storage, read> input: array<vec4<f32>>;
+
+@group(0) @binding(1)
+var<storage, read_write> output: array<vec4<f32>>;
+
+struct SharpenParams{
+	// Standard deviation of the blur in pixels, at most PADDING / 2
+	radius: f32,
+	amount: f32,
+	threshold: f32,
+	// Fraction of the 3×3 luma range the result may leave it by
+	overshoot: f32,
+}
+
+var<push_constant> pc: SharpenParams;
+
+var<workgroup> local: array<vec4<f32>, #expr{(WG_X + 2 * PADDING) * (WG_Y + 2 * PADDING)}>;
+
+#import reflect_vec
+#import is_outside_image
+#import setup_local
+#import access_local_vec4
+
+const local_height = #expr{WG_X + 2 * PADDING};
+const local_width = #expr{WG_Y + 2 * PADDING};
+const local_size = #expr{(WG_X + 2 * PADDING) * (WG_Y + 2 * PADDING)};
+
+fn luma(coord_x: i32, coord_y: i32) -> f32{
+	return dot(access_local_vec4(coord_x, coord_y).rgb, vec3(0.2126, 0.7152, 0.0722));
+}
+
+@compute @workgroup_size(#WG_X, #WG_Y, #WG_Z)
+fn main(
+	@builtin(global_invocation_id) global_id: vec3<u32>,
+	@builtin(local_invocation_id) local_id: vec3<u32>,
+	@builtin(local_invocation_index) local_index: u32,
+	@builtin(workgroup_id) wg_id: vec3<u32>,
+){
+	let global_bounds = vec2(#HEIGHT, #WIDTH);
+
+	setup_local(wg_id, local_index, global_bounds);
+
+	if is_outside_image(global_id, global_bounds){
+		return;
+	}
+
+	let local_center = vec2<i32>(local_id.xy) + vec2(#PADDING);
+	let global_flat = i32(global_id.x) * #WIDTH + i32(global_id.y);
+
+	let rgb = access_local_vec4(local_center.x, local_center.y);
+	let center = luma(local_center.x, local_center.y);
+
+	// Gaussian blur of the luma, truncated at 2 standard deviations
+	let sigma = clamp(pc.radius, 0.1, f32(#PADDING) / 2.);
+	let extent = i32(ceil(2. * sigma));
+	var blurred = 0.;
+	var weights = 0.;
+	for (var i = -extent; i <= extent; i++){
+		for (var j = -extent; j <= extent; j++){
+			let weight = exp(-f32(i * i + j * j) / (2. * sigma * sigma));
+			blurred += weight * luma(local_center.x + i, local_center.y + j);
+			weights += weight;
+		}
+	}
+	blurred /= weights;
+
+	// Detail below the threshold is noise and left alone
+	let detail = center - blurred;
+	let kept = sign(detail) * max(abs(detail) - pc.threshold, 0.);
+	var sharpened = center + pc.amount * kept;
+
+	// Halos are the result leaving the range of its neighbours
+	var low = center;
+	var high = center;
+	for (var i = -1; i <= 1; i++){
+		for (var j = -1; j <= 1; j++){
+			let neighbour = luma(local_center.x + i, local_center.y + j);
+			low = min(low, neighbour);
+			high = max(high, neighbour);
+		}
+	}
+	let margin = pc.overshoot * (high - low);
+	sharpened = clamp(sharpened, low - margin, high + margin);
+
+	// The luma weights sum to 1, so adding to every channel only changes the luma
+	output[global_flat] = vec4(rgb.rgb + (sharpened - center), rgb.a);
+}
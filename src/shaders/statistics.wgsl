@@ -0,0 +1,123 @@
+@group(0) @binding(0)
+var<storage, read> input: array<f32>;
+
+#import statistics_layout
+
+@group(0) @binding(1)
+var<storage, read_write> output: Statistics;
+
+struct StatisticsParams{
+	zones_vertical: u32,
+	zones_horizontal: u32,
+	saturation: f32,
+}
+
+var<push_constant> pc: StatisticsParams;
+
+const MIN_EV = #MIN_EV;
+const MAX_EV = #MAX_EV;
+
+const wg_size = #expr{WG_X * WG_Y};
+
+var<workgroup> sums: array<vec4<f32>, wg_size>;
+var<workgroup> sharpness_sums: array<f32, wg_size>;
+var<workgroup> clipped: array<atomic<u32>, 4>;
+var<workgroup> histogram: array<atomic<u32>, #BINS>;
+
+// The RGGB quad at `coord` in the half resolution quad grid, clamped to the image
+fn quad(coord: vec2<i32>) -> vec4<f32>{
+	let clamped = clamp(coord, vec2(0), vec2(#HEIGHT / 2 - 1, #WIDTH / 2 - 1));
+	let top = clamped.x * 2 * #WIDTH + clamped.y * 2;
+	let bottom = top + #WIDTH;
+	return vec4(input[top], input[top + 1], input[bottom], input[bottom + 1]);
+}
+
+fn green(coord: vec2<i32>) -> f32{
+	let value = quad(coord);
+	return (value.y + value.z) / 2.;
+}
+
+@compute @workgroup_size(#WG_X, #WG_Y, #WG_Z)
+fn main(
+	@builtin(local_invocation_index) local_index: u32,
+	@builtin(workgroup_id) wg_id: vec3<u32>,
+){
+	// One workgroup per zone
+	let zones = clamp(
+		vec2(pc.zones_vertical, pc.zones_horizontal),
+		vec2(1u),
+		vec2(#MAX_ZONES_VERTICAL, #MAX_ZONES_HORIZONTAL),
+	);
+	if any(wg_id.xy >= zones){
+		return;
+	}
+
+	let quads = vec2<u32>(#HEIGHT / 2, #WIDTH / 2);
+	let start = wg_id.xy * quads / zones;
+	let size = (wg_id.xy + 1u) * quads / zones - start;
+	let n = size.x * size.y;
+
+	var sum = vec4(0.);
+	var sharpness = 0.;
+
+	for (var i = local_index; i < n; i += wg_size){
+		let coord = vec2<i32>(start + vec2(i / size.y, i % size.y));
+		let value = quad(coord);
+
+		sum += value;
+
+		if pc.saturation > 0.{
+			for (var channel = 0; channel < 4; channel++){
+				if value[channel] >= pc.saturation{
+					atomicAdd(&clipped[channel], 1u);
+				}
+			}
+		}
+
+		let luminance = dot(vec3(value.x, (value.y + value.z) / 2., value.w), vec3(0.2126, 0.7152, 0.0722));
+		let ev = log2(max(luminance, 1e-12));
+		let bin = clamp(i32(floor((ev - MIN_EV) / (MAX_EV - MIN_EV) * f32(#BINS))), 0, #BINS - 1);
+		atomicAdd(&histogram[bin], 1u);
+
+		let laplacian = 4. * green(coord)
+			- green(coord + vec2(1, 0))
+			- green(coord - vec2(1, 0))
+			- green(coord + vec2(0, 1))
+			- green(coord - vec2(0, 1));
+		sharpness += laplacian * laplacian;
+	}
+
+	sums[local_index] = sum;
+	sharpness_sums[local_index] = sharpness;
+	workgroupBarrier();
+
+	for (var stride = wg_size / 2u; stride > 0u; stride /= 2u){
+		if local_index < stride{
+			sums[local_index] += sums[local_index + stride];
+			sharpness_sums[local_index] += sharpness_sums[local_index + stride];
+		}
+		workgroupBarrier();
+	}
+
+	let zone = wg_id.x * zones.y + wg_id.y;
+	let count = f32(max(n, 1u));
+
+	if local_index == 0u{
+		output.zone[zone].mean = sums[0] / count;
+		output.zone[zone].sharpness = sharpness_sums[0] / count;
+		output.zone[zone].count = n;
+		output.zone[zone].clipped = vec4(
+			atomicLoad(&clipped[0]),
+			atomicLoad(&clipped[1]),
+			atomicLoad(&clipped[2]),
+			atomicLoad(&clipped[3]),
+		);
+		if zone == 0u{
+			output.zones = zones;
+		}
+	}
+
+	if local_index < #BINS{
+		output.zone[zone].histogram[local_index] = atomicLoad(&histogram[local_index]);
+	}
+}
@@ -0,0 +1,47 @@
+@group(0) @binding(0)
+var<storage, read_write> frame: array<f32>;
+
+// Accumulated value and the number of frames that went into it
+@group(0) @binding(1)
+var<storage, read_write> history: array<vec2<f32>>;
+
+struct TemporalDenoiseParams{
+	max_frames: f32,
+	// In standard deviations of the noise model
+	motion_threshold: f32,
+	_padding: vec2<f32>,
+	noise_a: vec4<f32>,
+	noise_b: vec4<f32>,
+}
+
+var<push_constant> pc: TemporalDenoiseParams;
+
+#import is_outside_image
+
+@compute @workgroup_size(#WG_X, #WG_Y, #WG_Z)
+fn main(
+	@builtin(global_invocation_id) global_id: vec3<u32>,
+){
+	let global_bounds = vec2(#HEIGHT, #WIDTH);
+
+	if is_outside_image(global_id, global_bounds){
+		return;
+	}
+
+	let global_flat = i32(global_id.x) * #WIDTH + i32(global_id.y);
+	let cfa = (global_id.x % 2u) * 2u + global_id.y % 2u;
+
+	let value = frame[global_flat];
+	let previous = history[global_flat];
+
+	let sigma = sqrt(max(pc.noise_a[cfa] * max(value, 0.) + pc.noise_b[cfa], 1e-12));
+	let distance = abs(value - previous.x) / sigma;
+	let motion = smoothstep(pc.motion_threshold, 2. * pc.motion_threshold, distance);
+
+	// A cleared history has a count of zero and is ignored
+	let count = previous.y * (1. - motion);
+	let blended = mix(value, previous.x, count / (count + 1.));
+
+	history[global_flat] = vec2(blended, min(count + 1., pc.max_frames));
+	frame[global_flat] = blended;
+}
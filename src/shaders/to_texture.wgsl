@@ -20,9 +20,5 @@ fn main(
 
 
 	let load = buffer[global_flat];
-	var rgb = load.rgb;
-	// rgb /= 30000.;
-	rgb = pow(rgb, vec3(1.0));
-	
-	textureStore(texture, global_id.yx, vec4(rgb, load.w));
+	textureStore(texture, global_id.yx, load);
 }
@@ -0,0 +1,119 @@
+@group(0) @binding(0)
+var<storage, read_write> input: array<vec4<f32>>;
+
+struct ToneMapParams{
+	// ToneMapOperator in operations.rs
+	operator: i32,
+	exposure: f32,
+	white: f32,
+}
+
+var<push_constant> pc: ToneMapParams;
+
+const REINHARD = 0;
+const HABLE = 1;
+const ACES = 2;
+const AGX = 3;
+
+// The reference curves and their constants are in tone_map.rs
+const AGX_MIN_EV = -12.47393;
+
+#import is_outside_image
+
+fn reinhard_extended(x: vec3<f32>, white: f32) -> vec3<f32>{
+	return x * (1. + x / (white * white)) / (1. + x);
+}
+
+fn hable_curve(x: vec3<f32>) -> vec3<f32>{
+	let a = 0.15;
+	let b = 0.5;
+	let c = 0.1;
+	let d = 0.2;
+	let e = 0.02;
+	let f = 0.3;
+	return (x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f) - e / f;
+}
+
+fn hable(x: vec3<f32>, white: f32) -> vec3<f32>{
+	return hable_curve(x) / hable_curve(vec3(white));
+}
+
+fn aces_curve(x: vec3<f32>) -> vec3<f32>{
+	let a = x * (x + 0.0245786) - 0.000090537;
+	let b = x * (0.983729 * x + 0.432951) + 0.238081;
+	return a / b;
+}
+
+fn aces_fitted(rgb: vec3<f32>, white: f32) -> vec3<f32>{
+	// Given row by row, so they multiply from the left
+	let input = mat3x3(
+		0.59719, 0.35458, 0.04823,
+		0.076, 0.90834, 0.01566,
+		0.0284, 0.13383, 0.83777,
+	);
+	let output = mat3x3(
+		1.60475, -0.53108, -0.07367,
+		-0.10208, 1.10813, -0.00605,
+		-0.00327, -0.07276, 1.07602,
+	);
+	let curve = aces_curve(rgb * input) / aces_curve(vec3(white));
+	return curve * output;
+}
+
+fn agx_contrast(x: vec3<f32>) -> vec3<f32>{
+	let x2 = x * x;
+	let x4 = x2 * x2;
+	return 15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x - 0.00232;
+}
+
+fn agx(rgb: vec3<f32>, white: f32) -> vec3<f32>{
+	let inset = mat3x3(
+		0.842479062253094, 0.0423282422610123, 0.0423756549057051,
+		0.0784335999999992, 0.878468636469772, 0.0784336,
+		0.0792237451477643, 0.0791661274605434, 0.879142973793104,
+	);
+	let outset = mat3x3(
+		1.19687900512017, -0.0528968517574562, -0.0529716355144438,
+		-0.0980208811401368, 1.15190312990417, -0.0980434501171241,
+		-0.0990297440797205, -0.0989611768448433, 1.15107367264116,
+	);
+	let max_ev = log2(white);
+	let ev = clamp(log2(max(inset * rgb, vec3(1e-10))), vec3(AGX_MIN_EV), vec3(max_ev));
+	let encoded = agx_contrast((ev - AGX_MIN_EV) / (max_ev - AGX_MIN_EV));
+	// Back to linear from the 2.2 display gamma AgX is made for
+	return pow(max(outset * encoded, vec3(0.)), vec3(2.2));
+}
+
+@compute @workgroup_size(#WG_X, #WG_Y, #WG_Z)
+fn main(
+	@builtin(global_invocation_id) global_id: vec3<u32>,
+){
+	let global_bounds = vec2(#HEIGHT, #WIDTH);
+
+	if is_outside_image(global_id, global_bounds){
+		return;
+	}
+
+	let global_flat = i32(global_id.x) * #WIDTH + i32(global_id.y);
+
+	let color = input[global_flat];
+	let rgb = max(color.rgb * exp2(pc.exposure), vec3(0.));
+
+	var mapped: vec3<f32>;
+	switch pc.operator{
+		case HABLE: {
+			mapped = hable(rgb, pc.white);
+		}
+		case ACES: {
+			mapped = aces_fitted(rgb, pc.white);
+		}
+		case AGX: {
+			mapped = agx(rgb, pc.white);
+		}
+		default: {
+			mapped = reinhard_extended(rgb, pc.white);
+		}
+	}
+
+	input[global_flat] = vec4(clamp(mapped, vec3(0.), vec3(1.)), color.w);
+}
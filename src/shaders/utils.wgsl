@@ -15,6 +15,24 @@
 	}
 }
 
+#export mirror{
+	// Mirrors around the edge sample, unlike reflect, so every sample of a
+	// Bayer frame keeps its CFA colour
+	fn mirror(idx: i32, max: i32) -> i32{
+		if idx < 0{
+			return -idx;
+		} else if idx >= max{
+			return 2 * max - 2 - idx;
+		} else {
+			return idx;
+		}
+	}
+
+	fn mirror_vec(idx: vec2<i32>, max: vec2<i32>) -> vec2<i32>{
+		return vec2(mirror(idx.x, max.x), mirror(idx.y, max.y));
+	}
+}
+
 #export is_outside_image{
 	fn is_outside_image(global_id: vec3<u32>, global_bounds: vec2<i32>) -> bool{
 		return any(global_id < vec3(0u)) || any(global_id >= vec3(vec2<u32>(global_bounds.xy), 1u));
@@ -45,12 +63,132 @@
 	}
 }
 
+#export setup_local_cfa{
+	// setup_local for Bayer frames, the padding is filled with mirror
+	fn setup_local_cfa(wg_id: vec3<u32>, local_index: u32, global_bounds: vec2<i32>){
+		let offset_to_global = vec2<i32>(wg_id.xy) * vec2(i32(#WG_X), i32(#WG_Y)) - vec2(#PADDING);
+
+		var local_flat = i32(local_index);
+
+		while local_flat < local_size{
+			let local_coord = vec2(
+				i32((local_flat) / local_width),
+				i32((local_flat) % local_width),
+			);
+			let global_coord = mirror_vec(local_coord + offset_to_global, global_bounds);
+
+			let global_flat = global_coord.x * #WIDTH + global_coord.y;
+			local[local_flat] = input[global_flat];
+
+			local_flat += i32(#WG_X * #WG_Y);
+		}
+
+		workgroupBarrier();
+	}
+}
+
 #export access_local{
 	fn access_local(coord_x: i32, coord_y: i32) -> f32{
 		return local[coord_x * local_width + coord_y];
 	}
 }
 
+#export access_local_vec4{
+	fn access_local_vec4(coord_x: i32, coord_y: i32) -> vec4<f32>{
+		return local[coord_x * local_width + coord_y];
+	}
+}
+
+#export statistics_layout{
+	// ZoneStats in operations.rs
+	struct ZoneStats{
+		mean: vec4<f32>,
+		clipped: vec4<u32>,
+		sharpness: f32,
+		count: u32,
+		histogram: array<u32, #BINS>,
+	}
+
+	struct Statistics{
+		// Zones vertically and horizontally
+		zones: vec2<u32>,
+		_padding: vec2<u32>,
+		zone: array<ZoneStats, #MAX_ZONES>,
+	}
+}
+
+#export clahe_luma{
+	// Rec. 709 luminance with a 2.2 gamma, clipped to the display range
+	fn clahe_luma(rgb: vec3<f32>) -> f32{
+		let luminance = dot(rgb, vec3(0.2126, 0.7152, 0.0722));
+		return pow(clamp(luminance, 0., 1.), 1. / 2.2);
+	}
+
+	fn clahe_bin(luma: f32) -> u32{
+		return min(u32(luma * f32(#BINS)), u32(#BINS - 1));
+	}
+}
+
+#export oklab{
+	// Björn Ottosson's Oklab from and to linear sRGB, color_adjust.rs has the reference
+	fn cbrt(x: vec3<f32>) -> vec3<f32>{
+		return sign(x) * pow(abs(x), vec3(1. / 3.));
+	}
+
+	fn linear_srgb_to_oklab(rgb: vec3<f32>) -> vec3<f32>{
+		// Given row by row, so they multiply from the left
+		let to_lms = mat3x3(
+			0.4122214708, 0.5363325363, 0.0514459929,
+			0.2119034982, 0.6806995451, 0.1073969566,
+			0.0883024619, 0.2817188376, 0.6299787005,
+		);
+		let to_lab = mat3x3(
+			0.2104542553, 0.7936177850, -0.0040720468,
+			1.9779984951, -2.4285922050, 0.4505937099,
+			0.0259040371, 0.7827717662, -0.8086757660,
+		);
+		return cbrt(rgb * to_lms) * to_lab;
+	}
+
+	fn oklab_to_linear_srgb(lab: vec3<f32>) -> vec3<f32>{
+		let to_lms = mat3x3(
+			1., 0.3963377774, 0.2158037573,
+			1., -0.1055613458, -0.0638541728,
+			1., -0.0894841775, -1.2914855480,
+		);
+		let to_rgb = mat3x3(
+			4.0767416621, -3.3077115913, 0.2309699292,
+			-1.2684380046, 2.6097574011, -0.3413193965,
+			-0.0041960863, -0.7034186147, 1.7076147010,
+		);
+		let lms = lab * to_lms;
+		return (lms * lms * lms) * to_rgb;
+	}
+}
+
+#export rcd_utils{
+	// demosaic.rs has the reference
+	const RCD_EPS = 1e-5;
+	const RCD_EPS_SQ = 1e-10;
+
+	// The estimate from each side weighted by the gradient on the other
+	fn blend(gradient_a: f32, estimate_a: f32, gradient_b: f32, estimate_b: f32) -> f32{
+		return (gradient_b * estimate_a + gradient_a * estimate_b) / (gradient_a + gradient_b);
+	}
+
+	// The direction of the pixel, unless its diagonal neighbours agree on a stronger one
+	fn discrimination(central: f32, neighbourhood: f32) -> f32{
+		if abs(0.5 - central) < abs(0.5 - neighbourhood){
+			return neighbourhood;
+		}
+		return central;
+	}
+
+	fn is_green(coord: vec2<i32>) -> bool{
+		return (coord.x + coord.y) % 2 == 1;
+	}
+}
+
 #export all_utils{
 	#import reflect_vec
 	#import is_outside_image
@@ -58,3 +196,10 @@
 	#import access_local
 }
 
+#export all_cfa_utils{
+	#import mirror
+	#import is_outside_image
+	#import setup_local_cfa
+	#import access_local
+}
+
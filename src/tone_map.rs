@@ -0,0 +1,126 @@
+//! CPU reference of the global tone mapping operators in `tone_map.wgsl`.
+//!
+//! All operators take scene linear RGB and return display linear RGB in
+//! 0..1, leaving the transfer function to the output transform. `white` is
+//! the scene value that reaches display white: Reinhard, Hable and ACES are
+//! normalised so it maps to exactly 1, AgX uses it as the top of its log
+//! encoding range.
+
+use glam::{Mat3, Vec3};
+
+use crate::operations::{ToneMapOperator, ToneMapPush};
+
+/// Lower end of the AgX log encoding in stops, relative to 1.
+const AGX_MIN_EV: f32 = -12.47393;
+
+/// Hable's curve parameters: shoulder strength, linear strength, linear
+/// angle, toe strength, toe numerator and toe denominator.
+const HABLE: [f32; 6] = [0.15, 0.5, 0.1, 0.2, 0.02, 0.3];
+
+/// sRGB to the RRT input space of Stephen Hill's ACES fit, row by row.
+const ACES_INPUT: [[f32; 3]; 3] = [
+    [0.59719, 0.35458, 0.04823],
+    [0.076, 0.90834, 0.01566],
+    [0.0284, 0.13383, 0.83777],
+];
+
+/// ODT output space of the ACES fit to sRGB, row by row.
+const ACES_OUTPUT: [[f32; 3]; 3] = [
+    [1.60475, -0.53108, -0.07367],
+    [-0.10208, 1.10813, -0.00605],
+    [-0.00327, -0.07276, 1.07602],
+];
+
+/// sRGB to the AgX working space, column by column.
+const AGX_INSET: [f32; 9] = [
+    0.84247905,
+    0.042328242,
+    0.042375654,
+    0.0784336,
+    0.87846863,
+    0.0784336,
+    0.079223745,
+    0.07916613,
+    0.879143,
+];
+
+/// The inverse of [`AGX_INSET`], column by column.
+const AGX_OUTSET: [f32; 9] = [
+    1.196879,
+    -0.052896854,
+    -0.052971635,
+    -0.09802088,
+    1.1519032,
+    -0.09804345,
+    -0.09902974,
+    -0.098961174,
+    1.1510737,
+];
+
+/// Applies the exposure and the operator of `push`, whether or not it is
+/// enabled.
+pub fn tone_map(push: &ToneMapPush, rgb: Vec3) -> Vec3 {
+    let rgb = (rgb * push.exposure.exp2()).max(Vec3::ZERO);
+    let white = push.white;
+    let mapped: Vec3 = match push.operator {
+        ToneMapOperator::Reinhard => rgb.to_array().map(|x| reinhard_extended(x, white)).into(),
+        ToneMapOperator::Hable => rgb.to_array().map(|x| hable(x, white)).into(),
+        ToneMapOperator::Aces => aces_fitted(rgb, white),
+        ToneMapOperator::Agx => agx(rgb, white),
+    };
+    mapped.clamp(Vec3::ZERO, Vec3::ONE)
+}
+
+/// Reinhard's operator with the white point term, `x (1 + x / w²) / (1 + x)`.
+pub fn reinhard_extended(x: f32, white: f32) -> f32 {
+    x * (1. + x / (white * white)) / (1. + x)
+}
+
+/// John Hable's filmic curve from Uncharted 2.
+pub fn hable(x: f32, white: f32) -> f32 {
+    hable_curve(x) / hable_curve(white)
+}
+
+fn hable_curve(x: f32) -> f32 {
+    let [a, b, c, d, e, f] = HABLE;
+    (x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f) - e / f
+}
+
+/// Stephen Hill's fit of the ACES reference rendering and sRGB output
+/// transforms.
+pub fn aces_fitted(rgb: Vec3, white: f32) -> Vec3 {
+    let input = row_major(ACES_INPUT);
+    let output = row_major(ACES_OUTPUT);
+    let curve = (input * rgb).to_array().map(aces_curve);
+    output * (Vec3::from(curve) / aces_curve(white))
+}
+
+fn aces_curve(x: f32) -> f32 {
+    let a = x * (x + 0.0245786) - 0.000090537;
+    let b = x * (0.983729 * x + 0.432951) + 0.238081;
+    a / b
+}
+
+/// Troy Sobotka's AgX with the polynomial fit of its default contrast curve,
+/// decoded from the 2.2 display gamma it is designed for back to linear.
+pub fn agx(rgb: Vec3, white: f32) -> Vec3 {
+    let max_ev = white.log2();
+    let inset = Mat3::from_cols_array(&AGX_INSET) * rgb;
+    let encoded = inset.to_array().map(|x| {
+        let ev = x.max(1e-10).log2().clamp(AGX_MIN_EV, max_ev);
+        agx_contrast((ev - AGX_MIN_EV) / (max_ev - AGX_MIN_EV))
+    });
+    let display = Mat3::from_cols_array(&AGX_OUTSET) * Vec3::from(encoded);
+    display.max(Vec3::ZERO).powf(2.2)
+}
+
+fn agx_contrast(x: f32) -> f32 {
+    let x2 = x * x;
+    let x4 = x2 * x2;
+    15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x
+        - 0.00232
+}
+
+fn row_major(rows: [[f32; 3]; 3]) -> Mat3 {
+    Mat3::from_cols_array_2d(&rows).transpose()
+}
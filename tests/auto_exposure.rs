@@ -0,0 +1,61 @@
+mod common;
+
+use common::{default_isp_params, device, params};
+use gpwgpu::utils::DebugEncoder;
+use wgpu_isp::{
+    operations::{ISPParams, MeteringMode, HISTOGRAM_BINS, HISTOGRAM_EV_RANGE, RAW_SCALE},
+    setup::State,
+};
+
+const WIDTH: usize = 64;
+const HEIGHT: usize = 48;
+
+#[test]
+fn meters_flat_frame() {
+    let (device, queue) = device();
+
+    let mut isp_params = default_isp_params();
+
+    let mut state = State::new(&device, &queue, params(WIDTH, HEIGHT, false)).unwrap();
+    state.write_to_input(&vec![0.5 * RAW_SCALE; WIDTH * HEIGHT]);
+
+    let mut run = |isp_params: &ISPParams| {
+        let mut encoder = DebugEncoder::new(&device);
+        state.execute(&mut encoder, isp_params);
+        encoder.submit(&queue);
+        state.read_exposure()
+    };
+
+    // Off leaves the image alone
+    let exposure = run(&isp_params);
+    assert_eq!(exposure.digital_gain, 1.);
+
+    // The metering only knows the histogram bin, half a bin either way
+    let (min, max) = HISTOGRAM_EV_RANGE;
+    let tolerance = 2f32.powf(0.5 * (max - min) as f32 / HISTOGRAM_BINS as f32);
+
+    isp_params.auto_exposure_push.enabled = 1;
+    isp_params.auto_exposure_push.target = 0.18;
+    for metering in [
+        MeteringMode::Average,
+        MeteringMode::CenterWeighted,
+        MeteringMode::Highlight,
+    ] {
+        isp_params.auto_exposure_push.metering = metering;
+        let exposure = run(&isp_params);
+        let ratio = exposure.exposure_ratio / (0.18 / 0.5);
+        assert!(
+            ratio <= tolerance && ratio >= 1. / tolerance,
+            "{metering:?}: {exposure:?}"
+        );
+        assert_eq!(exposure.digital_gain, exposure.exposure_ratio);
+    }
+
+    // Digital gain is limited, the suggested exposure is not
+    isp_params.auto_exposure_push.metering = MeteringMode::Average;
+    isp_params.auto_exposure_push.target = 8.;
+    isp_params.auto_exposure_push.max_gain = 4.;
+    let exposure = run(&isp_params);
+    assert_eq!(exposure.digital_gain, 4.);
+    assert!(exposure.exposure_ratio > 8., "{exposure:?}");
+}
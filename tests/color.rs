@@ -0,0 +1,145 @@
+mod common;
+
+use common::assert_close;
+use glam::Vec2;
+use wgpu_isp::color::{
+    bradford_adaptation, hlg_oetf, planckian_xy, pq_oetf, srgb_oetf, srgb_to_xyz,
+    temperature_to_xy, uv_to_xy, xy_to_uv, xy_to_xyz, CameraCalibration, Chromaticities,
+};
+
+const D65: Vec2 = Vec2::new(0.31271, 0.32902);
+
+#[test]
+fn planckian_locus() {
+    // Reference points of the locus
+    for (temperature, x, y) in [
+        (2856., 0.4476, 0.4074),
+        (5003., 0.3451, 0.3516),
+        (6504., 0.3135, 0.3236),
+    ] {
+        let xy = planckian_xy(temperature);
+        assert_close(xy.x, x, 1e-3);
+        assert_close(xy.y, y, 1e-3);
+    }
+}
+
+#[test]
+fn uv_round_trip() {
+    let xy = uv_to_xy(xy_to_uv(D65));
+    assert_close(xy.x, D65.x, 1e-6);
+    assert_close(xy.y, D65.y, 1e-6);
+}
+
+#[test]
+fn tint_moves_perpendicular_to_locus() {
+    let neutral = xy_to_uv(temperature_to_xy(5000., 0.));
+    let magenta = xy_to_uv(temperature_to_xy(5000., 30.));
+    let green = xy_to_uv(temperature_to_xy(5000., -30.));
+
+    assert_close(neutral.distance(magenta), 0.01, 1e-5);
+    assert!(magenta.y < neutral.y && green.y > neutral.y);
+}
+
+#[test]
+fn srgb_camera_is_neutral_at_d65() {
+    // D65 is about 6504 K, slightly on the green side of the locus
+    let calibration = CameraCalibration::default();
+    let gains = calibration.white_balance_gains(6504., -9.7);
+    for gain in gains {
+        assert_close(gain, 1., 0.01);
+    }
+}
+
+#[test]
+fn matrices_interpolate_in_inverse_temperature() {
+    let calibration = CameraCalibration {
+        color_matrix_1: [[1., 0., 0.], [0., 1., 0.], [0., 0., 1.]],
+        color_matrix_2: [[2., 0., 0.], [0., 2., 0.], [0., 0., 2.]],
+        ..Default::default()
+    };
+
+    let halfway = 2. / (1. / calibration.temperature_1 + 1. / calibration.temperature_2);
+    let matrix = calibration.color_matrix(halfway);
+    assert_close(matrix.x_axis.x, 1.5, 1e-4);
+
+    assert_eq!(calibration.color_matrix(1000.).x_axis.x, 1.);
+    assert_eq!(calibration.color_matrix(20000.).x_axis.x, 2.);
+}
+
+#[test]
+fn camera_to_xyz_takes_neutral_to_d65() {
+    // The default camera records sRGB primaries
+    let calibration = CameraCalibration::default();
+    let matrix = calibration.camera_to_xyz(6504., wgpu_isp::color::D65);
+    let expected = srgb_to_xyz();
+    for (actual, expected) in matrix
+        .to_cols_array()
+        .into_iter()
+        .zip(expected.to_cols_array())
+    {
+        assert_close(actual, expected, 1e-3);
+    }
+
+    let tungsten = temperature_to_xy(2856., 0.);
+    let white = calibration.camera_to_xyz(2856., tungsten) * glam::Vec3::ONE;
+    let expected = xy_to_xyz(wgpu_isp::color::D65);
+    for (actual, expected) in white.to_array().into_iter().zip(expected.to_array()) {
+        assert_close(actual, expected, 1e-3);
+    }
+}
+
+#[test]
+fn srgb_primaries_give_the_standard_matrix() {
+    let matrix = srgb_to_xyz();
+    let expected = [
+        [0.4124564, 0.3575761, 0.1804375],
+        [0.2126729, 0.7151522, 0.072175],
+        [0.0193339, 0.119192, 0.9503041],
+    ];
+    for (row, expected) in expected.iter().enumerate() {
+        for (column, expected) in expected.iter().enumerate() {
+            assert_close(matrix.col(column)[row], *expected, 1e-3);
+        }
+    }
+}
+
+#[test]
+fn output_spaces_keep_d65_white() {
+    let white = srgb_to_xyz() * glam::Vec3::ONE;
+    for space in [
+        Chromaticities::SRGB,
+        Chromaticities::DISPLAY_P3,
+        Chromaticities::REC2020,
+        Chromaticities::ACES_AP1,
+    ] {
+        let rgb = space.xyz_d65_to_rgb() * white;
+        for channel in rgb.to_array() {
+            assert_close(channel, 1., 1e-3);
+        }
+    }
+}
+
+#[test]
+fn bradford_maps_white_to_white() {
+    let aces_white = Chromaticities::ACES_AP1.white;
+    let adapted = bradford_adaptation(D65, aces_white) * xy_to_xyz(D65);
+    let expected = xy_to_xyz(aces_white);
+    for (actual, expected) in adapted.to_array().into_iter().zip(expected.to_array()) {
+        assert_close(actual, expected, 1e-4);
+    }
+}
+
+#[test]
+fn transfer_functions() {
+    assert_eq!(srgb_oetf(-1.), 0.);
+    assert_close(srgb_oetf(0.0031308), 0.04045, 1e-4);
+    assert_close(srgb_oetf(0.18), 0.4614, 1e-3);
+    assert_close(srgb_oetf(1.), 1., 1e-6);
+
+    assert_close(pq_oetf(0.), 0., 1e-6);
+    assert_close(pq_oetf(100.), 0.5081, 1e-3);
+    assert_close(pq_oetf(10000.), 1., 1e-5);
+
+    assert_close(hlg_oetf(1. / 12.), 0.5, 1e-6);
+    assert_close(hlg_oetf(1.), 1., 1e-4);
+}
@@ -0,0 +1,189 @@
+mod common;
+
+use common::{assert_close, bayer, default_isp_params, device, params, read_rgb};
+use glam::Vec3;
+use gpwgpu::utils::DebugEncoder;
+use wgpu_isp::{
+    color_adjust::{
+        color_adjust, linear_srgb_to_oklab, oklab_to_linear_srgb, oklab_to_oklch, HueBands,
+        HUE_BAND_CENTRES,
+    },
+    operations::{ColorAdjustPush, ISPParams, WhiteBalanceMode},
+    setup::State,
+};
+
+fn chroma(rgb: Vec3) -> f32 {
+    oklab_to_oklch(linear_srgb_to_oklab(rgb)).y
+}
+
+fn enabled() -> ColorAdjustPush {
+    ColorAdjustPush {
+        enabled: 1,
+        ..Default::default()
+    }
+}
+
+const SKY: Vec3 = Vec3::new(0.2, 0.4, 0.8);
+const RED: Vec3 = Vec3::new(0.7, 0.1, 0.05);
+
+#[test]
+fn oklab_round_trip() {
+    // White has no chroma and unit lightness
+    assert_close(linear_srgb_to_oklab(Vec3::ONE), Vec3::X, 1e-4);
+
+    for rgb in [SKY, RED, Vec3::new(0.01, 0.5, 0.02), Vec3::new(1.5, -0.05, 0.3)] {
+        assert_close(oklab_to_linear_srgb(linear_srgb_to_oklab(rgb)), rgb, 1e-4);
+    }
+
+    // The band centres are the hues of the primaries
+    let red_hue = oklab_to_oklch(linear_srgb_to_oklab(Vec3::X)).z;
+    assert!((red_hue - HUE_BAND_CENTRES[0]).abs() < 0.05, "{red_hue}");
+}
+
+#[test]
+fn defaults_change_nothing() {
+    for rgb in [SKY, RED, Vec3::splat(0.18)] {
+        assert_close(color_adjust(&enabled(), rgb), rgb, 1e-4);
+    }
+}
+
+#[test]
+fn saturation_and_hue_keep_neutrals() {
+    let grey = Vec3::splat(0.18);
+    let push = ColorAdjustPush {
+        saturation: 1.8,
+        vibrance: 0.7,
+        hue_shift: 90.,
+        ..enabled()
+    };
+    assert_close(color_adjust(&push, grey), grey, 1e-4);
+
+    // No saturation leaves only the lightness
+    let push = ColorAdjustPush {
+        saturation: 0.,
+        ..enabled()
+    };
+    let desaturated = color_adjust(&push, SKY);
+    assert!(chroma(desaturated) < 1e-4, "{desaturated}");
+    assert!((desaturated.x - desaturated.z).abs() < 1e-4, "{desaturated}");
+
+    // A full turn is the identity
+    let push = ColorAdjustPush {
+        hue_shift: 360.,
+        ..enabled()
+    };
+    assert_close(color_adjust(&push, RED), RED, 1e-4);
+}
+
+#[test]
+fn vibrance_protects_saturated_colours() {
+    let push = ColorAdjustPush {
+        vibrance: 0.5,
+        ..enabled()
+    };
+    let muted = Vec3::new(0.3, 0.35, 0.4);
+    let muted_gain = chroma(color_adjust(&push, muted)) / chroma(muted);
+    let saturated_gain = chroma(color_adjust(&push, Vec3::Z)) / chroma(Vec3::Z);
+
+    assert!(muted_gain > 1.4, "{muted_gain}");
+    assert!((saturated_gain - 1.).abs() < 1e-3, "{saturated_gain}");
+}
+
+#[test]
+fn bands_only_touch_their_hues() {
+    let mut bands = HueBands::default();
+    // Blue, the band of the blue primary
+    bands.saturation[5] = -1.;
+    bands.lightness[5] = 0.5;
+    let push = ColorAdjustPush {
+        bands,
+        ..enabled()
+    };
+
+    let adjusted = linear_srgb_to_oklab(color_adjust(&push, Vec3::Z));
+    let original = linear_srgb_to_oklab(Vec3::Z);
+    assert!(adjusted.y.hypot(adjusted.z) < 0.02, "{adjusted}");
+    assert!(adjusted.x > original.x * 1.3, "{adjusted} {original}");
+
+    assert_close(color_adjust(&push, RED), RED, 1e-4);
+
+    // Halfway between two centres gets half of each
+    let (hue, saturation, lightness) = bands.at((HUE_BAND_CENTRES[5] + HUE_BAND_CENTRES[6]) / 2.);
+    assert_eq!(hue, 0.);
+    assert!((saturation + 0.5).abs() < 1e-6, "{saturation}");
+    assert!((lightness - 0.25).abs() < 1e-6, "{lightness}");
+    // Wrapping around from magenta to red
+    bands.hue[0] = 10.;
+    let (hue, _, _) = bands.at((HUE_BAND_CENTRES[7] + HUE_BAND_CENTRES[0] + 360.) / 2. - 360.);
+    assert!((hue - 5.).abs() < 1e-3, "{hue}");
+}
+
+#[test]
+fn shader_matches_reference() {
+    let (device, queue) = device();
+
+    let mut isp_params = default_isp_params();
+    // Gray world would take the colour out
+    isp_params.auto_white_balance_push.mode = WhiteBalanceMode::Gains;
+    let mut bands = HueBands::default();
+    bands.hue[1] = 20.;
+    bands.saturation[2] = 0.4;
+    bands.lightness[1] = -0.3;
+    isp_params.color_adjust_push = ColorAdjustPush {
+        enabled: 1,
+        saturation: 1.3,
+        vibrance: 0.4,
+        hue_shift: -15.,
+        bands,
+    };
+
+    let mut state = State::new(&device, &queue, params(64, 32, true)).unwrap();
+
+    // Bins to a single colour
+    let raw = bayer(64, 32, |_, _| Vec3::new(0.6, 0.4, 0.1));
+
+    let mut run = |isp_params: &ISPParams| {
+        state.write_to_input(&raw);
+        let mut encoder = DebugEncoder::new(&device);
+        state.execute(&mut encoder, isp_params);
+        encoder.submit(&queue);
+
+        read_rgb(&state)[0]
+    };
+
+    let adjusted = run(&isp_params);
+    let push = isp_params.color_adjust_push;
+
+    isp_params.color_adjust_push.enabled = 0;
+    let original = run(&isp_params);
+
+    assert_close(adjusted, color_adjust(&push, original), 1e-3);
+    assert!(adjusted.distance(original) > 0.01, "{adjusted} {original}");
+}
+
+#[test]
+fn adjusts_linear_light_before_the_gamma() {
+    let (device, queue) = device();
+
+    let mut isp_params = default_isp_params();
+    isp_params.auto_white_balance_push.mode = WhiteBalanceMode::Gains;
+    isp_params.gamma_push.gamma = 1. / 2.2;
+    isp_params.color_adjust_push = ColorAdjustPush {
+        enabled: 1,
+        saturation: 0.5,
+        hue_shift: 30.,
+        ..Default::default()
+    };
+
+    let mut state = State::new(&device, &queue, params(64, 32, true)).unwrap();
+
+    let linear = Vec3::new(0.6, 0.4, 0.1);
+    state.write_to_input(&bayer(64, 32, |_, _| linear));
+
+    let mut encoder = DebugEncoder::new(&device);
+    state.execute(&mut encoder, &isp_params);
+    encoder.submit(&queue);
+
+    let expected = color_adjust(&isp_params.color_adjust_push, linear).powf(1. / 2.2);
+    assert_close(read_rgb(&state)[0], expected, 1e-3);
+}
@@ -0,0 +1,124 @@
+use glam::{Mat3, Vec3};
+use wgpu_isp::color_checker::{
+    delta_e_2000, fit_color_correction, linear_srgb_to_lab, reference_colors, CcmFit, PatchRegion,
+    PATCH_COUNT,
+};
+
+const PATCH_SIZE: usize = 10;
+const WIDTH: usize = 6 * PATCH_SIZE;
+const HEIGHT: usize = 4 * PATCH_SIZE;
+
+/// Rows sum to 1, like a camera that is already white balanced.
+fn camera_matrix() -> Mat3 {
+    Mat3::from_cols_array_2d(&[[0.8, 0.15, 0.05], [0.1, 0.8, 0.1], [0.05, 0.25, 0.7]]).transpose()
+}
+
+/// A chart filling the whole image, with `colors` in reading order.
+fn chart(colors: &[Vec3; PATCH_COUNT]) -> Vec<[f32; 4]> {
+    (0..WIDTH * HEIGHT)
+        .map(|i| {
+            let (row, col) = (i / WIDTH / PATCH_SIZE, i % WIDTH / PATCH_SIZE);
+            colors[row * 6 + col].extend(1.).to_array()
+        })
+        .collect()
+}
+
+fn regions() -> [PatchRegion; PATCH_COUNT] {
+    let centre = PATCH_SIZE as f32 / 2.;
+    PatchRegion::grid(
+        [centre, centre],
+        [HEIGHT as f32 - centre, WIDTH as f32 - centre],
+        PATCH_SIZE - 4,
+    )
+}
+
+fn fitted(image: &[[f32; 4]], fit: CcmFit) -> Mat3 {
+    let push = fit_color_correction(image, WIDTH, HEIGHT, &regions(), fit);
+    Mat3::from_mat4(push.color_correction_matrix)
+}
+
+fn mean_delta_e(matrix: Mat3, colors: &[Vec3; PATCH_COUNT]) -> f32 {
+    colors
+        .iter()
+        .zip(reference_colors())
+        .map(|(color, reference)| {
+            delta_e_2000(
+                linear_srgb_to_lab(matrix * *color),
+                linear_srgb_to_lab(reference),
+            )
+        })
+        .sum::<f32>()
+        / PATCH_COUNT as f32
+}
+
+#[test]
+fn delta_e_matches_sharma_data() {
+    let pairs = [
+        ([50., 2.6772, -79.7751], [50., 0., -82.7485], 2.0425),
+        ([50., 3.1571, -77.2803], [50., 0., -82.7485], 2.8615),
+        ([50., 0., 0.], [50., -1., 2.], 2.3669),
+        (
+            [60.2574, -34.0099, 36.2677],
+            [60.4626, -34.1751, 39.4387],
+            1.2644,
+        ),
+    ];
+    for (lab_1, lab_2, expected) in pairs {
+        let delta_e = delta_e_2000(Vec3::from_array(lab_1), Vec3::from_array(lab_2));
+        assert!((delta_e - expected).abs() < 1e-3, "{delta_e} != {expected}");
+    }
+}
+
+#[test]
+fn grid_covers_patch_centres() {
+    let regions = regions();
+    assert_eq!(
+        regions[0],
+        PatchRegion {
+            row: 2,
+            col: 2,
+            height: 6,
+            width: 6
+        }
+    );
+    assert_eq!((regions[23].row, regions[23].col), (32, 52));
+}
+
+#[test]
+fn least_squares_recovers_inverse() {
+    // Darker than the reference, which the fit should ignore
+    let colors = reference_colors().map(|color| 0.4 * (camera_matrix() * color));
+    let matrix = fitted(&chart(&colors), CcmFit::LeastSquares);
+
+    let expected = camera_matrix().inverse();
+    for (actual, expected) in matrix.to_cols_array().iter().zip(expected.to_cols_array()) {
+        assert!((actual - expected).abs() < 1e-3, "{matrix} != {expected}");
+    }
+}
+
+#[test]
+fn white_is_preserved() {
+    // No matrix maps these exactly, the hue of every other patch is off
+    let colors = reference_colors().map(|color| camera_matrix() * color);
+    let colors: [Vec3; PATCH_COUNT] = std::array::from_fn(|i| {
+        let color = colors[i];
+        if i < 18 && i % 2 == 0 {
+            color * Vec3::new(1.1, 1., 0.9)
+        } else {
+            color
+        }
+    });
+
+    let image = chart(&colors);
+    let least_squares = fitted(&image, CcmFit::LeastSquares);
+    let delta_e = fitted(&image, CcmFit::DeltaE2000);
+
+    for matrix in [least_squares, delta_e] {
+        let white = matrix * Vec3::ONE;
+        assert!((white - Vec3::ONE).abs().max_element() < 1e-5, "{matrix}");
+    }
+    assert!(
+        mean_delta_e(delta_e, &colors) < mean_delta_e(least_squares, &colors),
+        "{delta_e} is no better than {least_squares}"
+    );
+}
@@ -0,0 +1,83 @@
+//! Helpers shared by the integration tests. Every test file compiles its own
+//! copy, so not all of them are used everywhere.
+#![allow(dead_code)]
+
+use std::fmt::Display;
+
+use glam::Vec3;
+use gpwgpu::{
+    utils::{default_device, read_buffer},
+    wgpu::{Device, Queue},
+    FutureExt,
+};
+use wgpu_isp::{
+    operations::{Buffers, ISPParams, RAW_SCALE, SHADERS},
+    setup::{Params, State},
+};
+
+pub trait Close: Copy + Display {
+    fn is_close(self, other: Self, tolerance: f32) -> bool;
+}
+
+impl Close for f32 {
+    fn is_close(self, other: Self, tolerance: f32) -> bool {
+        (self - other).abs() <= tolerance
+    }
+}
+
+impl Close for Vec3 {
+    fn is_close(self, other: Self, tolerance: f32) -> bool {
+        self.abs_diff_eq(other, tolerance)
+    }
+}
+
+pub fn assert_close<T: Close>(actual: T, expected: T, tolerance: f32) {
+    assert!(
+        actual.is_close(expected, tolerance),
+        "{actual} is not within {tolerance} of {expected}"
+    );
+}
+
+pub fn device() -> (Device, Queue) {
+    default_device().block_on().unwrap()
+}
+
+pub fn params(width: usize, height: usize, preview: bool) -> Params {
+    Params {
+        width: width as i32,
+        height: height as i32,
+        shader_processor: SHADERS.clone(),
+        preview,
+    }
+}
+
+/// The parameters the viewer starts with.
+pub fn default_isp_params() -> ISPParams {
+    serde_json::from_str(include_str!("../../viewer/default.json")).unwrap()
+}
+
+/// An RGGB frame sampling `color` at every photosite: red from R, green from
+/// Gr and Gb, blue from B, scaled to the raw range.
+pub fn bayer(width: usize, height: usize, color: impl Fn(usize, usize) -> Vec3) -> Vec<f32> {
+    (0..width * height)
+        .map(|i| {
+            let (row, column) = (i / width, i % width);
+            let color = color(row, column);
+            let channel = match (row % 2, column % 2) {
+                (0, 0) => color.x,
+                (1, 1) => color.z,
+                _ => color.y,
+            };
+            channel * RAW_SCALE
+        })
+        .collect()
+}
+
+/// The output of the last execution, one colour per pixel.
+pub fn read_rgb(state: &State) -> Vec<Vec3> {
+    let rgb = state.sequential.buffers.get_from_any(Buffers::RGB);
+    read_buffer::<[f32; 4]>(state.device, rgb, 0, None)
+        .into_iter()
+        .map(|pixel| Vec3::new(pixel[0], pixel[1], pixel[2]))
+        .collect()
+}
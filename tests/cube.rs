@@ -0,0 +1,136 @@
+mod common;
+
+use common::{assert_close, bayer, default_isp_params, device, params, read_rgb};
+use glam::Vec3;
+use gpwgpu::utils::DebugEncoder;
+use wgpu_isp::{
+    cube::{CubeError, CubeLut},
+    operations::{ISPParams, LutInterpolation, LutPosition, WhiteBalanceMode},
+    setup::State,
+};
+
+/// Swaps red and blue, from the size 2 identity with red changing fastest.
+const SWAP_RED_BLUE: &str = r#"# Written by hand
+TITLE "Swap red and blue"
+LUT_3D_SIZE 2
+DOMAIN_MIN 0 0 0
+DOMAIN_MAX 1 1 1
+
+0 0 0
+0 0 1
+0 1 0
+0 1 1
+1 0 0
+1 0 1
+1 1 0
+1 1 1
+"#;
+
+fn parse_error_line(contents: &str) -> usize {
+    match CubeLut::parse(contents) {
+        Err(CubeError::Parse { line, .. }) => line,
+        other => panic!("expected a parse error, got {other:?}"),
+    }
+}
+
+#[test]
+fn parses_header_and_table() {
+    let lut = CubeLut::parse(SWAP_RED_BLUE).unwrap();
+    assert_eq!(lut.title.as_deref(), Some("Swap red and blue"));
+    assert_eq!(lut.size, 2);
+    assert_eq!(lut.domain_min, [0.; 3]);
+    assert_eq!(lut.domain_max, [1.; 3]);
+    assert_eq!(lut.table.len(), 8);
+    assert_eq!(lut.table[1], [0., 0., 1.]);
+}
+
+#[test]
+fn resolve_input_range() {
+    let contents = SWAP_RED_BLUE
+        .replace("DOMAIN_MIN 0 0 0\n", "")
+        .replace("DOMAIN_MAX 1 1 1\n", "LUT_3D_INPUT_RANGE -0.5 2\n");
+    let lut = CubeLut::parse(&contents).unwrap();
+    assert_eq!(lut.domain_min, [-0.5; 3]);
+    assert_eq!(lut.domain_max, [2.; 3]);
+}
+
+#[test]
+fn rejects_invalid_files() {
+    // A bad number is reported on its line
+    assert_eq!(parse_error_line(&SWAP_RED_BLUE.replace("0 1 1", "0 x 1")), 10);
+    // As is a row with the wrong number of values
+    assert_eq!(parse_error_line(&SWAP_RED_BLUE.replace("0 1 1", "0 1")), 10);
+    // Missing rows only show at the end
+    let (short, _) = SWAP_RED_BLUE.trim_end().rsplit_once('\n').unwrap();
+    assert_eq!(parse_error_line(short), 0);
+    assert_eq!(parse_error_line(&SWAP_RED_BLUE.replace("LUT_3D_SIZE 2", "")), 0);
+    assert_eq!(parse_error_line("LUT_1D_SIZE 2\n0 0 0\n1 1 1\n"), 1);
+    assert_eq!(parse_error_line(&SWAP_RED_BLUE.replace("LUT_3D_SIZE 2", "LUT_3D_SIZE 1")), 3);
+}
+
+#[test]
+fn loads_from_file() {
+    let path = std::env::temp_dir().join("wgpu_isp_swap_red_blue.cube");
+    std::fs::write(&path, SWAP_RED_BLUE).unwrap();
+    assert_eq!(CubeLut::load(&path).unwrap(), CubeLut::parse(SWAP_RED_BLUE).unwrap());
+
+    assert!(matches!(
+        CubeLut::load(path.with_extension("missing")),
+        Err(CubeError::Io(_))
+    ));
+}
+
+#[test]
+fn identity_samples_to_input() {
+    let lut = CubeLut::identity(17);
+    for rgb in [
+        Vec3::new(0.1, 0.5, 0.9),
+        Vec3::new(0.73, 0.2, 0.31),
+        Vec3::ONE,
+    ] {
+        for interpolation in [LutInterpolation::Trilinear, LutInterpolation::Tetrahedral] {
+            assert_close(lut.sample(rgb, interpolation), rgb, 1e-5);
+        }
+    }
+    // Outside the domain is clamped
+    let clamped = lut.sample(Vec3::new(-1., 2., 0.5), LutInterpolation::Tetrahedral);
+    assert_close(clamped, Vec3::new(0., 1., 0.5), 1e-5);
+}
+
+#[test]
+fn shader_applies_lut() {
+    let (device, queue) = device();
+
+    let path = std::env::temp_dir().join("wgpu_isp_shader_swap_red_blue.cube");
+    std::fs::write(&path, SWAP_RED_BLUE).unwrap();
+
+    let mut isp_params = default_isp_params();
+    // Gray world would take the colour out
+    isp_params.auto_white_balance_push.mode = WhiteBalanceMode::Gains;
+    isp_params.lut_push.enabled = 1;
+    isp_params.lut_push.position = LutPosition::SceneLinear;
+    isp_params.lut_path = Some(path.clone());
+
+    let mut state = State::new(&device, &queue, params(64, 32, true)).unwrap();
+    state.load_lut(&path).unwrap();
+
+    // Bins to a single colour
+    let raw = bayer(64, 32, |_, _| Vec3::new(0.2, 0.4, 0.6));
+
+    let mut run = |isp_params: &ISPParams| {
+        state.write_to_input(&raw);
+        let mut encoder = DebugEncoder::new(&device);
+        state.execute(&mut encoder, isp_params);
+        encoder.submit(&queue);
+
+        read_rgb(&state)[0]
+    };
+
+    let swapped = run(&isp_params);
+
+    isp_params.lut_push.enabled = 0;
+    let original = run(&isp_params);
+
+    assert_close(swapped, Vec3::new(original.z, original.y, original.x), 1e-5);
+    assert!((original.x - original.z).abs() > 0.05, "{original}");
+}
@@ -0,0 +1,120 @@
+mod common;
+
+use common::{assert_close, default_isp_params, device, params, read_rgb};
+use glam::Vec3;
+use gpwgpu::utils::DebugEncoder;
+use wgpu_isp::{
+    curves::{bake, ToneCurve, CURVE_LUT_SIZE},
+    operations::{CurvesPush, WhiteBalanceMode, RAW_SCALE},
+    setup::State,
+    tone_map::tone_map,
+};
+
+fn curve(points: &[[f32; 2]]) -> ToneCurve {
+    ToneCurve {
+        points: points.to_vec(),
+    }
+}
+
+#[test]
+fn default_is_identity() {
+    let identity = ToneCurve::default();
+    for i in 0..=10 {
+        let x = i as f32 / 10.;
+        assert_close(identity.evaluate(x), x, 1e-6);
+    }
+}
+
+#[test]
+fn passes_through_points_in_any_order() {
+    let s_curve = curve(&[[1., 1.], [0.25, 0.15], [0., 0.], [0.75, 0.85]]);
+    for [x, y] in s_curve.points.clone() {
+        assert_close(s_curve.evaluate(x), y, 1e-6);
+    }
+    // Held constant outside the points
+    let lifted = curve(&[[0.1, 0.2], [0.9, 0.8]]);
+    assert_eq!(lifted.evaluate(0.), 0.2);
+    assert_eq!(lifted.evaluate(1.), 0.8);
+}
+
+#[test]
+fn rising_points_give_a_rising_curve() {
+    // Steep then flat, where an unconstrained spline overshoots
+    let knee = curve(&[[0., 0.], [0.2, 0.9], [0.4, 0.95], [1., 1.]]);
+    let mut previous = 0.;
+    for i in 0..=100 {
+        let y = knee.evaluate(i as f32 / 100.);
+        assert!(y >= previous && y <= 1., "{y} at {i}");
+        previous = y;
+    }
+}
+
+#[test]
+fn master_follows_channel_curves() {
+    let push = CurvesPush {
+        enabled: 1,
+        master: curve(&[[0., 0.], [1., 0.5]]),
+        red: curve(&[[0., 1.], [1., 0.]]),
+        ..Default::default()
+    };
+    let lut = bake(&push);
+    assert_eq!(lut.len(), CURVE_LUT_SIZE);
+    assert_close(lut[0][0], 0.5, 1e-6);
+    assert_close(lut[0][1], 0., 1e-6);
+    assert_close(lut[CURVE_LUT_SIZE - 1][0], 0., 1e-6);
+    assert_close(lut[CURVE_LUT_SIZE - 1][2], 0.5, 1e-6);
+}
+
+#[test]
+fn curves_replace_gamma() {
+    let (device, queue) = device();
+
+    let mut isp_params = default_isp_params();
+    isp_params.auto_white_balance_push.mode = WhiteBalanceMode::Gains;
+    // Ignored while the curves are on
+    isp_params.gamma_push.gamma = 2.;
+    isp_params.curves_push = CurvesPush {
+        enabled: 1,
+        red: curve(&[[0., 1.], [1., 0.]]),
+        green: curve(&[[0., 0.], [0.5, 0.8], [1., 1.]]),
+        ..Default::default()
+    };
+
+    let mut state = State::new(&device, &queue, params(64, 32, true)).unwrap();
+    state.write_to_input(&vec![RAW_SCALE / 2.; 64 * 32]);
+
+    let mut encoder = DebugEncoder::new(&device);
+    state.execute(&mut encoder, &isp_params);
+    encoder.submit(&queue);
+
+    assert_close(read_rgb(&state)[0], Vec3::new(0.5, 0.8, 0.5), 1e-3);
+}
+
+#[test]
+fn hdr_values_are_tone_mapped_before_the_curves() {
+    let (device, queue) = device();
+
+    let mut isp_params = default_isp_params();
+    isp_params.auto_white_balance_push.mode = WhiteBalanceMode::Gains;
+    isp_params.tone_map_push.enabled = 1;
+    // The identity curves
+    isp_params.curves_push.enabled = 1;
+
+    let mut state = State::new(&device, &queue, params(64, 32, true)).unwrap();
+
+    let mut outputs = Vec::new();
+    for level in [2., 4.] {
+        state.write_to_input(&vec![level * RAW_SCALE; 64 * 32]);
+
+        let mut encoder = DebugEncoder::new(&device);
+        state.execute(&mut encoder, &isp_params);
+        encoder.submit(&queue);
+
+        let pixel = read_rgb(&state)[0];
+        let expected = tone_map(&isp_params.tone_map_push, Vec3::splat(level));
+        assert_close(pixel.y, expected.y, 1e-3);
+        outputs.push(pixel.y);
+    }
+    // Clipped to 1 before the tone mapping, both would be the same
+    assert!(outputs[1] > outputs[0] + 0.01, "{outputs:?}");
+}
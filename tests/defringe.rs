@@ -0,0 +1,96 @@
+mod common;
+
+use common::{assert_close, bayer, default_isp_params, device, params, read_rgb};
+use glam::Vec3;
+use gpwgpu::utils::DebugEncoder;
+use wgpu_isp::{
+    operations::{DefringePush, WhiteBalanceMode},
+    setup::State,
+};
+
+const WIDTH: usize = 64;
+const HEIGHT: usize = 32;
+
+const DARK: Vec3 = Vec3::splat(0.05);
+const BRIGHT: Vec3 = Vec3::ONE;
+const PURPLE: Vec3 = Vec3::new(0.3, 0.05, 0.4);
+const GREEN: Vec3 = Vec3::new(0.05, 0.3, 0.1);
+const RED: Vec3 = Vec3::new(0.4, 0.05, 0.05);
+
+/// The binned image: a column of fringe colours just before an edge in the
+/// top half, and the same purple without the edge in the bottom half.
+fn scene(row: usize, column: usize) -> Vec3 {
+    let fringe = WIDTH / 2 - 1;
+    match (row, column) {
+        (_, c) if c == fringe => match row {
+            0..=3 => PURPLE,
+            4..=7 => GREEN,
+            8..=15 => RED,
+            _ => PURPLE,
+        },
+        (0..=15, c) if c > fringe => BRIGHT,
+        _ => DARK,
+    }
+}
+
+fn assert_neutral(rgb: Vec3) {
+    assert!(rgb.max_element() - rgb.min_element() < 1e-3, "{rgb}");
+}
+
+#[test]
+fn desaturates_fringes_at_edges() {
+    let (device, queue) = device();
+
+    let mut isp_params = default_isp_params();
+    isp_params.auto_white_balance_push.mode = WhiteBalanceMode::Gains;
+
+    let mut state = State::new(&device, &queue, params(2 * WIDTH, 2 * HEIGHT, true)).unwrap();
+
+    let raw = bayer(2 * WIDTH, 2 * HEIGHT, |row, column| {
+        scene(row / 2, column / 2)
+    });
+
+    let mut run = |push: DefringePush, gamma: f32| {
+        isp_params.defringe_push = push;
+        isp_params.gamma_push.gamma = gamma;
+        state.write_to_input(&raw);
+
+        let mut encoder = DebugEncoder::new(&device);
+        state.execute(&mut encoder, &isp_params);
+        encoder.submit(&queue);
+
+        read_rgb(&state)
+    };
+    let at = |image: &[Vec3], row: usize| image[row * WIDTH + WIDTH / 2 - 1];
+
+    let original = run(DefringePush::default(), 1.);
+    assert_close(at(&original, 0), PURPLE, 1e-5);
+
+    let enabled = DefringePush {
+        enabled: 1,
+        ..Default::default()
+    };
+    let defringed = run(enabled, 1.);
+    assert_neutral(at(&defringed, 0));
+    assert_neutral(at(&defringed, 6));
+    // Other hues stay, as does purple away from edges
+    assert_close(at(&defringed, 12), RED, 1e-4);
+    assert_close(at(&defringed, HEIGHT - 1), PURPLE, 1e-4);
+
+    // Half the strength keeps half the chroma
+    let halved = run(
+        DefringePush {
+            strength: 0.5,
+            ..enabled
+        },
+        1.,
+    );
+    let purple = at(&halved, 0);
+    assert!(purple.z - purple.y > 0.05 && purple.z - purple.y < 0.3, "{purple}");
+
+    // The fringes are found in linear light, the gamma comes after it
+    let encoded = run(enabled, 1. / 2.2);
+    for row in [0, 6, 12, HEIGHT - 1] {
+        assert_close(at(&encoded, row).powf(2.2), at(&defringed, row), 1e-4);
+    }
+}
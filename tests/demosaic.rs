@@ -0,0 +1,164 @@
+mod common;
+
+use common::{default_isp_params, device, params};
+use gpwgpu::{
+    automatic_buffers::{AllOperations, Operation},
+    bytemuck,
+    utils::{read_buffer, DebugEncoder},
+};
+use wgpu_isp::{
+    demosaic::demosaic,
+    operations::{Buffers, Debayer, DemosaicAlgorithm, PT},
+};
+
+const ALGORITHMS: [DemosaicAlgorithm; 4] = [
+    DemosaicAlgorithm::Bilinear,
+    DemosaicAlgorithm::Malvar,
+    DemosaicAlgorithm::HamiltonAdams,
+    DemosaicAlgorithm::Rcd,
+];
+
+const WIDTH: usize = 40;
+const HEIGHT: usize = 24;
+
+fn image(mut f: impl FnMut(usize, usize) -> f32) -> Vec<f32> {
+    (0..WIDTH * HEIGHT)
+        .map(|i| f(i / WIDTH, i % WIDTH))
+        .collect()
+}
+
+fn pseudo_random() -> Vec<f32> {
+    let mut state = 0x2545f4914f6cdd1du64;
+    image(|_, _| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state >> 40) as f32 / (1u64 << 24) as f32
+    })
+}
+
+#[test]
+fn flat_field_stays_flat() {
+    let bayer = image(|_, _| 0.3);
+    for algorithm in ALGORITHMS {
+        for (i, pixel) in demosaic(&bayer, WIDTH, HEIGHT, algorithm)
+            .iter()
+            .enumerate()
+        {
+            for channel in &pixel[..3] {
+                assert!(
+                    (channel - 0.3).abs() < 1e-6,
+                    "{algorithm:?} at {i}: {pixel:?}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn grey_ramp_is_reconstructed_away_from_borders() {
+    let ramp = |row: usize, col: usize| 0.1 + 0.01 * col as f32 + 0.005 * row as f32;
+    let bayer = image(ramp);
+    for algorithm in ALGORITHMS {
+        // RCD reaches five pixels into the mirrored border, where the ramp
+        // bends
+        let margin = if algorithm == DemosaicAlgorithm::Rcd {
+            5
+        } else {
+            3
+        };
+        let rgb = demosaic(&bayer, WIDTH, HEIGHT, algorithm);
+        for row in margin..HEIGHT - margin {
+            for col in margin..WIDTH - margin {
+                let pixel = rgb[row * WIDTH + col];
+                for channel in &pixel[..3] {
+                    assert!(
+                        (channel - ramp(row, col)).abs() < 1e-5,
+                        "{algorithm:?} at ({row}, {col}): {pixel:?}"
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Root mean square error against `scene`, away from the borders.
+fn rms_error(algorithm: DemosaicAlgorithm, scene: impl Fn(usize, usize) -> f32) -> f32 {
+    let bayer = image(&scene);
+    let rgb = demosaic(&bayer, WIDTH, HEIGHT, algorithm);
+    let mut sum = 0.;
+    let mut count = 0;
+    for row in 5..HEIGHT - 5 {
+        for col in 5..WIDTH - 5 {
+            for channel in &rgb[row * WIDTH + col][..3] {
+                sum += (channel - scene(row, col)).powi(2);
+                count += 1;
+            }
+        }
+    }
+    (sum / count as f32).sqrt()
+}
+
+#[test]
+fn rcd_resolves_diagonal_detail() {
+    // Grey stripes at 45°, where picking a horizontal or vertical direction
+    // can't help
+    let stripes = |row: usize, col: usize| 0.5 + 0.3 * ((row + col) as f32 * 0.9).sin();
+    let hamilton_adams = rms_error(DemosaicAlgorithm::HamiltonAdams, stripes);
+    let rcd = rms_error(DemosaicAlgorithm::Rcd, stripes);
+    assert!(rcd < hamilton_adams / 2., "{rcd} {hamilton_adams}");
+}
+
+#[test]
+fn native_samples_are_kept() {
+    let bayer = pseudo_random();
+    for algorithm in ALGORITHMS {
+        let rgb = demosaic(&bayer, WIDTH, HEIGHT, algorithm);
+        for (i, (pixel, value)) in rgb.iter().zip(&bayer).enumerate() {
+            let channel = match ((i / WIDTH) % 2, (i % WIDTH) % 2) {
+                (0, 0) => 0,
+                (1, 1) => 2,
+                _ => 1,
+            };
+            assert_eq!(pixel[channel], *value, "{algorithm:?} at {i}");
+        }
+    }
+}
+
+#[test]
+fn gpu_matches_reference() {
+    let (device, queue) = device();
+
+    let params = params(WIDTH, HEIGHT, false);
+
+    let mut sequential =
+        AllOperations::<PT>::new(&params, vec![Operation::new::<Debayer>()]).unwrap();
+    sequential.finalize(&device, &params).unwrap();
+
+    let mut isp_params = default_isp_params();
+
+    let bayer = pseudo_random();
+    let input = sequential.buffers.get_from_any(Buffers::GreenEqualization);
+    queue.write_buffer(input, 0, bytemuck::cast_slice(&bayer));
+
+    for algorithm in ALGORITHMS {
+        isp_params.debayer_push.algorithm = algorithm;
+
+        let mut encoder = DebugEncoder::new(&device);
+        sequential.execute(&mut encoder, &isp_params);
+        encoder.submit(&queue);
+
+        let output = sequential.buffers.get_from_any(Buffers::RGB);
+        let gpu = read_buffer::<[f32; 4]>(&device, output, 0, None);
+        let cpu = demosaic(&bayer, WIDTH, HEIGHT, algorithm);
+
+        for (i, (gpu, cpu)) in gpu.iter().zip(&cpu).enumerate() {
+            for (g, c) in gpu.iter().zip(cpu) {
+                assert!(
+                    (g - c).abs() < 1e-4,
+                    "{algorithm:?} at {i}: {gpu:?} != {cpu:?}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,126 @@
+mod common;
+
+use common::{assert_close, default_isp_params, device, params};
+use gpwgpu::{
+    automatic_buffers::{AllOperations, Operation},
+    bytemuck,
+    utils::{read_buffer, DebugEncoder},
+};
+use wgpu_isp::operations::{Buffers, Denoise, DenoisePush, PT};
+
+const WIDTH: usize = 64;
+const HEIGHT: usize = 64;
+
+const DARK: f32 = 0.2;
+const LIGHT: f32 = 0.6;
+/// Standard deviation of the added noise, the same in every channel.
+const SIGMA: f32 = 0.01;
+
+/// A vertical edge between two flat halves, with uniform noise of standard
+/// deviation [`SIGMA`].
+fn noisy_edge() -> Vec<f32> {
+    let mut state = 0x2545f4914f6cdd1du64;
+    (0..WIDTH * HEIGHT)
+        .map(|i| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let uniform = (state >> 40) as f32 / (1u64 << 24) as f32;
+            let level = if i % WIDTH < WIDTH / 2 { DARK } else { LIGHT };
+            level + (2. * uniform - 1.) * 3f32.sqrt() * SIGMA
+        })
+        .collect()
+}
+
+/// Runs only the denoiser on a frame that has been through the black level.
+fn denoise(frame: &[f32], push: DenoisePush) -> Vec<f32> {
+    let (device, queue) = device();
+    let params = params(WIDTH, HEIGHT, false);
+
+    let mut sequential =
+        AllOperations::<PT>::new(&params, vec![Operation::new::<Denoise>()]).unwrap();
+    sequential.finalize(&device, &params).unwrap();
+
+    let input = sequential.buffers.get_from_any(Buffers::BlackLevel);
+    queue.write_buffer(input, 0, bytemuck::cast_slice(frame));
+
+    let mut isp_params = default_isp_params();
+    isp_params.denoise_push = push;
+
+    let mut encoder = DebugEncoder::new(&device);
+    sequential.execute(&mut encoder, &isp_params);
+    encoder.submit(&queue);
+
+    let output = sequential.buffers.get_from_any(Buffers::Denoise);
+    read_buffer::<f32>(&device, output, 0, None)
+}
+
+/// Mean and variance of a column, away from the top and bottom borders.
+fn column_stats(frame: &[f32], column: usize) -> (f32, f32) {
+    let values = (4..HEIGHT - 4)
+        .map(|row| frame[row * WIDTH + column])
+        .collect::<Vec<_>>();
+    let mean = values.iter().sum::<f32>() / values.len() as f32;
+    let variance = values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f32>()
+        / values.len() as f32;
+    (mean, variance)
+}
+
+#[test]
+fn smooths_noise_and_keeps_edges() {
+    let frame = noisy_edge();
+
+    // Off passes the frame through
+    assert_eq!(denoise(&frame, DenoisePush::default()), frame);
+
+    let push = DenoisePush {
+        enabled: 1,
+        strength: 1.,
+        spatial_sigma: 1.,
+        noise_a: 0.,
+        noise_b: SIGMA * SIGMA,
+    };
+    let denoised = denoise(&frame, push);
+
+    // Flat areas lose most of their noise
+    for column in [8, 9, WIDTH - 10, WIDTH - 9] {
+        let (_, before) = column_stats(&frame, column);
+        let (mean, after) = column_stats(&denoised, column);
+        assert!(after < before / 3., "{after} {before} at {column}");
+        let level = if column < WIDTH / 2 { DARK } else { LIGHT };
+        assert_close(mean, level, 3e-3);
+    }
+
+    // The edge is 40 sigma high, so nothing leaks across it
+    for column in WIDTH / 2 - 2..WIDTH / 2 + 2 {
+        let (mean, _) = column_stats(&denoised, column);
+        let level = if column < WIDTH / 2 { DARK } else { LIGHT };
+        assert_close(mean, level, 3e-3);
+    }
+}
+
+#[test]
+fn borders_only_average_the_same_colour() {
+    // The channels are a sigma apart, close enough to be averaged together
+    let rggb = [0.2, 0.21, 0.19, 0.2 + SIGMA / 2.];
+    let frame = (0..WIDTH * HEIGHT)
+        .map(|i| rggb[(i / WIDTH % 2) * 2 + i % 2])
+        .collect::<Vec<_>>();
+
+    let push = DenoisePush {
+        enabled: 1,
+        strength: 1.,
+        spatial_sigma: 1.,
+        noise_a: 0.,
+        noise_b: SIGMA * SIGMA,
+    };
+    let denoised = denoise(&frame, push);
+
+    // Every neighbour past the edges has the colour of the centre too
+    for (i, (denoised, original)) in denoised.iter().zip(&frame).enumerate() {
+        assert!((denoised - original).abs() < 1e-6, "{denoised} at {i}");
+    }
+}
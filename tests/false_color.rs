@@ -0,0 +1,89 @@
+mod common;
+
+use common::{assert_close, default_isp_params, device, params};
+use glam::Vec3;
+use gpwgpu::{
+    automatic_buffers::{AllOperations, Operation},
+    bytemuck,
+    utils::{read_buffer, DebugEncoder},
+};
+use wgpu_isp::operations::{Buffers, FalseColorSuppression, FalseColorSuppressionPush, PT};
+
+const WIDTH: usize = 32;
+const HEIGHT: usize = 16;
+
+const DARK: Vec3 = Vec3::splat(0.2);
+const LIGHT: Vec3 = Vec3::splat(0.6);
+/// Red minus green and blue minus green of the zipper along the edge.
+const ZIPPER: Vec3 = Vec3::new(0.1, 0., -0.1);
+const PATCH: Vec3 = Vec3::new(0.8, 0.6, 0.4);
+
+/// A neutral vertical edge with zipper colours alternating along its dark
+/// side, and a coloured patch on the light side.
+fn scene(row: usize, column: usize) -> Vec3 {
+    match (row, column) {
+        (_, c) if c == WIDTH / 2 - 1 => DARK + if row % 2 == 0 { ZIPPER } else { -ZIPPER },
+        (4..=11, 22..=29) => PATCH,
+        (_, c) if c < WIDTH / 2 => DARK,
+        _ => LIGHT,
+    }
+}
+
+/// Runs only the false colour suppression on the demosaiced scene.
+fn suppress(push: FalseColorSuppressionPush) -> Vec<Vec3> {
+    let (device, queue) = device();
+    let params = params(WIDTH, HEIGHT, false);
+
+    let mut sequential =
+        AllOperations::<PT>::new(&params, vec![Operation::new::<FalseColorSuppression>()]).unwrap();
+    sequential.finalize(&device, &params).unwrap();
+
+    let input = (0..WIDTH * HEIGHT)
+        .map(|i| scene(i / WIDTH, i % WIDTH).extend(1.).to_array())
+        .collect::<Vec<_>>();
+    let rgb = sequential.buffers.get_from_any(Buffers::RGB);
+    queue.write_buffer(rgb, 0, bytemuck::cast_slice(&input));
+
+    let mut isp_params = default_isp_params();
+    isp_params.false_color_suppression_push = push;
+
+    let mut encoder = DebugEncoder::new(&device);
+    sequential.execute(&mut encoder, &isp_params);
+    encoder.submit(&queue);
+
+    let rgb = sequential.buffers.get_from_any(Buffers::RGB);
+    read_buffer::<[f32; 4]>(&device, rgb, 0, None)
+        .into_iter()
+        .map(|pixel| Vec3::new(pixel[0], pixel[1], pixel[2]))
+        .collect()
+}
+
+#[test]
+fn removes_zipper_and_keeps_real_colour() {
+    let at = |image: &[Vec3], row: usize, column: usize| image[row * WIDTH + column];
+    let zipper = WIDTH / 2 - 1;
+
+    let original = suppress(FalseColorSuppressionPush::default());
+    assert_close(at(&original, 6, zipper), DARK + ZIPPER, 1e-6);
+
+    let push = FalseColorSuppressionPush {
+        enabled: 1,
+        strength: 1.,
+    };
+    let suppressed = suppress(push);
+    for row in 0..HEIGHT {
+        // Only a third of each window has the zipper colour, so the median
+        // is neutral and the green, the luminance, stays as it was
+        assert_close(at(&suppressed, row, zipper), DARK, 1e-6);
+        // The edge itself has no chroma to lose
+        assert_close(at(&suppressed, row, zipper + 1), LIGHT, 1e-6);
+    }
+    // Inside the patch every neighbour has the same colour
+    assert_close(at(&suppressed, 8, 26), PATCH, 1e-6);
+
+    let halved = suppress(FalseColorSuppressionPush {
+        strength: 0.5,
+        ..push
+    });
+    assert_close(at(&halved, 6, zipper), DARK + ZIPPER / 2., 1e-6);
+}
@@ -0,0 +1,138 @@
+mod common;
+
+use common::{assert_close, default_isp_params, device, params};
+use gpwgpu::{
+    automatic_buffers::{AllOperations, Operation},
+    bytemuck,
+    utils::{read_buffer, DebugEncoder},
+};
+use wgpu_isp::operations::{Buffers, GreenEqualization, GreenEqualizationPush, PT};
+
+const WIDTH: usize = 32;
+const HEIGHT: usize = 16;
+
+/// An RGGB frame with the given value for every photosite of each channel.
+fn flat(rggb: [f32; 4]) -> Vec<f32> {
+    (0..WIDTH * HEIGHT)
+        .map(|i| rggb[(i / WIDTH % 2) * 2 + i % 2])
+        .collect()
+}
+
+/// Runs only the green equalisation, with `mean` standing in for the Gr/Gb
+/// means from the white balance.
+fn equalize(frame: &[f32], mean: [f32; 4], push: GreenEqualizationPush) -> Vec<f32> {
+    let (device, queue) = device();
+    let params = params(WIDTH, HEIGHT, false);
+
+    let mut sequential =
+        AllOperations::<PT>::new(&params, vec![Operation::new::<GreenEqualization>()]).unwrap();
+    sequential.finalize(&device, &params).unwrap();
+
+    let input = sequential.buffers.get_from_any(Buffers::AutoWhiteBalance);
+    queue.write_buffer(input, 0, bytemuck::cast_slice(frame));
+    let means = sequential.buffers.get_from_any(Buffers::Mean);
+    queue.write_buffer(means, 0, bytemuck::cast_slice(&mean));
+
+    let mut isp_params = default_isp_params();
+    isp_params.green_equalization_push = push;
+
+    let mut encoder = DebugEncoder::new(&device);
+    sequential.execute(&mut encoder, &isp_params);
+    encoder.submit(&queue);
+
+    let output = sequential.buffers.get_from_any(Buffers::GreenEqualization);
+    read_buffer::<f32>(&device, output, 0, None)
+}
+
+/// The value at an interior photosite of each channel, in RGGB order.
+fn channels(frame: &[f32]) -> [f32; 4] {
+    let (row, column) = (HEIGHT / 2, WIDTH / 2);
+    [
+        frame[row * WIDTH + column],
+        frame[row * WIDTH + column + 1],
+        frame[(row + 1) * WIDTH + column],
+        frame[(row + 1) * WIDTH + column + 1],
+    ]
+}
+
+fn assert_channels(actual: [f32; 4], expected: [f32; 4]) {
+    for (actual, expected) in actual.into_iter().zip(expected) {
+        assert_close(actual, expected, 1e-5);
+    }
+}
+
+const IMBALANCED: [f32; 4] = [0.3, 0.5, 0.4, 0.2];
+
+#[test]
+fn disabled_leaves_the_frame_alone() {
+    let frame = flat(IMBALANCED);
+    assert_eq!(equalize(&frame, IMBALANCED, Default::default()), frame);
+}
+
+#[test]
+fn global_means_meet_in_the_middle() {
+    let push = GreenEqualizationPush {
+        enabled: 1,
+        strength: 1.,
+        ..Default::default()
+    };
+    let frame = flat(IMBALANCED);
+    let output = equalize(&frame, IMBALANCED, push);
+    // Red and blue are never touched
+    for (i, value) in output.iter().enumerate() {
+        let expected = match (i / WIDTH % 2, i % 2) {
+            (0, 0) => 0.3,
+            (1, 1) => 0.2,
+            _ => 0.45,
+        };
+        assert_close(*value, expected, 1e-5);
+    }
+
+    let halfway = GreenEqualizationPush {
+        strength: 0.5,
+        ..push
+    };
+    let output = equalize(&frame, IMBALANCED, halfway);
+    assert_channels(channels(&output), [0.3, 0.475, 0.425, 0.2]);
+}
+
+#[test]
+fn local_correction_keeps_real_differences() {
+    let push = GreenEqualizationPush {
+        enabled: 1,
+        local: 1,
+        threshold: 0.5,
+        strength: 1.,
+    };
+    // The means are only used by the global correction
+    let mean = [1.; 4];
+
+    // A 22% difference is below the threshold and removed
+    let output = equalize(&flat(IMBALANCED), mean, push);
+    assert_channels(channels(&output), [0.3, 0.45, 0.45, 0.2]);
+
+    // A fourfold one is taken to be detail and kept
+    let detail = [0.3, 0.8, 0.2, 0.2];
+    let output = equalize(&flat(detail), mean, push);
+    assert_channels(channels(&output), detail);
+}
+
+#[test]
+fn local_correction_reaches_the_borders() {
+    let push = GreenEqualizationPush {
+        enabled: 1,
+        local: 1,
+        threshold: 0.5,
+        strength: 1.,
+    };
+    let output = equalize(&flat(IMBALANCED), [1.; 4], push);
+    // The diagonal neighbours past the edges are the other green as well
+    for (i, value) in output.iter().enumerate() {
+        let expected = match (i / WIDTH % 2, i % 2) {
+            (0, 0) => 0.3,
+            (1, 1) => 0.2,
+            _ => 0.45,
+        };
+        assert_close(*value, expected, 1e-5);
+    }
+}
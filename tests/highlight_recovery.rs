@@ -0,0 +1,84 @@
+mod common;
+
+use common::{assert_close, bayer, default_isp_params, device, params, read_rgb};
+use glam::Vec3;
+use gpwgpu::utils::DebugEncoder;
+use wgpu_isp::{
+    operations::{HighlightMode, HighlightRecoveryPush, WhiteBalanceMode},
+    setup::State,
+};
+
+const WIDTH: usize = 128;
+const HEIGHT: usize = 64;
+
+/// Raw values of the two halves, green clips on the right.
+const UNCLIPPED: Vec3 = Vec3::new(0.4, 0.8, 0.6);
+const CLIPPED: Vec3 = Vec3::new(0.6, 1., 0.9);
+
+const GAINS: Vec3 = Vec3::new(2., 1., 1.5);
+
+#[test]
+fn repairs_clipped_green() {
+    let (device, queue) = device();
+
+    let mut isp_params = default_isp_params();
+    let white_balance = &mut isp_params.auto_white_balance_push;
+    white_balance.mode = WhiteBalanceMode::Gains;
+    white_balance.red_gain = GAINS.x;
+    white_balance.green_gain = GAINS.y;
+    white_balance.blue_gain = GAINS.z;
+
+    // Binned to 64×32
+    let mut state = State::new(&device, &queue, params(WIDTH, HEIGHT, true)).unwrap();
+
+    let raw = bayer(WIDTH, HEIGHT, |_, column| {
+        if column < WIDTH / 2 {
+            UNCLIPPED
+        } else {
+            CLIPPED
+        }
+    });
+
+    let mut run = |push: HighlightRecoveryPush| {
+        isp_params.highlight_recovery_push = push;
+        state.write_to_input(&raw);
+
+        let mut encoder = DebugEncoder::new(&device);
+        state.execute(&mut encoder, &isp_params);
+        encoder.submit(&queue);
+
+        read_rgb(&state)
+    };
+
+    let (edge, right) = (WIDTH / 4, WIDTH / 2 - 1);
+
+    // White balance alone turns the clipped half magenta
+    let original = run(HighlightRecoveryPush::default());
+    assert_close(original[right], CLIPPED * GAINS, 1e-5);
+
+    let mut push = HighlightRecoveryPush {
+        enabled: 1,
+        mode: HighlightMode::Clip,
+        ..Default::default()
+    };
+    let clipped = run(push);
+    assert_close(clipped[right], Vec3::ONE, 1e-5);
+    // Unclipped pixels are left alone by every mode
+    assert_close(clipped[0], UNCLIPPED * GAINS, 1e-5);
+
+    push.mode = HighlightMode::Blend;
+    let blended = run(push);
+    let mean = (CLIPPED * GAINS).min(GAINS).element_sum() / 3.;
+    assert_close(blended[right], Vec3::splat(mean), 1e-5);
+
+    push.mode = HighlightMode::Reconstruct;
+    let reconstructed = run(push);
+    assert_close(reconstructed[0], UNCLIPPED * GAINS, 1e-5);
+    // Next to unclipped pixels green follows their ratio to red and blue
+    let expected_green = UNCLIPPED.y / (UNCLIPPED.x * GAINS.x + UNCLIPPED.z * GAINS.z)
+        * (CLIPPED.x * GAINS.x + CLIPPED.z * GAINS.z);
+    let expected = Vec3::new(CLIPPED.x * GAINS.x, expected_green, CLIPPED.z * GAINS.z);
+    assert_close(reconstructed[edge], expected, 1e-4);
+    // Too far from them it falls back to blending
+    assert_close(reconstructed[right], blended[right], 1e-5);
+}
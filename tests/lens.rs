@@ -0,0 +1,154 @@
+mod common;
+
+use common::{default_isp_params, device, params, read_rgb};
+use glam::{Vec2, Vec3};
+use gpwgpu::utils::DebugEncoder;
+use wgpu_isp::{
+    lens::{crop_scale, distort, source_position},
+    operations::{LensCorrectionPush, ResamplingFilter, WhiteBalanceMode, RAW_SCALE},
+    setup::State,
+};
+
+const WIDTH: i32 = 64;
+const HEIGHT: i32 = 32;
+
+fn enabled() -> LensCorrectionPush {
+    LensCorrectionPush {
+        enabled: 1,
+        ..Default::default()
+    }
+}
+
+fn is_inside(position: Vec2) -> bool {
+    let last = Vec2::new((WIDTH - 1) as f32, (HEIGHT - 1) as f32);
+    position.cmpge(Vec2::splat(-1e-3)).all() && position.cmple(last + 1e-3).all()
+}
+
+#[test]
+fn model_terms() {
+    let identity = enabled();
+    let p = Vec2::new(0.3, -0.2);
+    assert_eq!(distort(&identity, p), p);
+
+    let radial = LensCorrectionPush {
+        k1: 0.1,
+        k2: 0.01,
+        ..enabled()
+    };
+    let r2 = p.length_squared();
+    let expected = p * (1. + 0.1 * r2 + 0.01 * r2 * r2);
+    assert!(distort(&radial, p).abs_diff_eq(expected, 1e-6));
+
+    // Tangential distortion moves the centre line sideways
+    let tangential = LensCorrectionPush {
+        p1: 0.05,
+        ..enabled()
+    };
+    assert!(distort(&tangential, Vec2::new(0., 0.5)).y > 0.5);
+
+    // Pixels map to themselves without distortion
+    let pixel = Vec2::new(10., 20.);
+    let source = source_position(&identity, HEIGHT, WIDTH, pixel, 1., 1.);
+    assert!(source.abs_diff_eq(pixel, 1e-4), "{source}");
+}
+
+#[test]
+fn crop_keeps_samples_inside() {
+    assert_eq!(crop_scale(&enabled(), HEIGHT, WIDTH), 1.);
+
+    for push in [
+        LensCorrectionPush {
+            k1: 0.2,
+            ..enabled()
+        },
+        LensCorrectionPush {
+            red_scale: 1.02,
+            blue_scale: 0.98,
+            ..enabled()
+        },
+        LensCorrectionPush {
+            p2: 0.05,
+            center_x: 0.1,
+            ..enabled()
+        },
+    ] {
+        let scale = crop_scale(&push, HEIGHT, WIDTH);
+        assert!(scale < 1., "{push:?}");
+        for channel_scale in [push.red_scale, 1., push.blue_scale] {
+            for pixel in [
+                Vec2::ZERO,
+                Vec2::new((WIDTH - 1) as f32, (HEIGHT - 1) as f32),
+                Vec2::new(0., (HEIGHT / 2) as f32),
+                Vec2::new((WIDTH / 2) as f32, 0.),
+            ] {
+                let source = source_position(&push, HEIGHT, WIDTH, pixel, scale, channel_scale);
+                assert!(is_inside(source), "{source} {push:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn shader_matches_model() {
+    let (device, queue) = device();
+
+    let mut isp_params = default_isp_params();
+    isp_params.auto_white_balance_push.mode = WhiteBalanceMode::Gains;
+
+    let params = params(2 * WIDTH as usize, 2 * HEIGHT as usize, true);
+    let mut state = State::new(&device, &queue, params).unwrap();
+
+    // Binned to a horizontal ramp, every channel holds (column + 1) / WIDTH
+    let raw = (0..4 * HEIGHT * WIDTH)
+        .map(|i| (i % (2 * WIDTH) / 2 + 1) as f32 / WIDTH as f32 * RAW_SCALE)
+        .collect::<Vec<_>>();
+
+    let mut run = |push: LensCorrectionPush| {
+        isp_params.lens_correction_push = push;
+        state.write_to_input(&raw);
+
+        let mut encoder = DebugEncoder::new(&device);
+        state.execute(&mut encoder, &isp_params);
+        encoder.submit(&queue);
+
+        read_rgb(&state)
+    };
+
+    // Without distortion every pixel samples itself
+    for filter in [ResamplingFilter::Bilinear, ResamplingFilter::Bicubic] {
+        let output = run(LensCorrectionPush {
+            filter,
+            ..enabled()
+        });
+        for (i, pixel) in output.iter().enumerate() {
+            let expected = (i as i32 % WIDTH + 1) as f32 / WIDTH as f32;
+            assert!((pixel.y - expected).abs() < 1e-5, "{pixel} at {i}");
+        }
+    }
+
+    let push = LensCorrectionPush {
+        k1: 0.2,
+        red_scale: 1.02,
+        crop: 0,
+        ..enabled()
+    };
+    let cropped = LensCorrectionPush { crop: 1, ..push };
+    let scale = crop_scale(&cropped, HEIGHT, WIDTH);
+
+    // The corners sample outside the image unless cropped
+    assert_eq!(run(push)[0], Vec3::ZERO);
+
+    // The ramp is linear, so bilinear resampling gives the source column
+    let output = run(cropped);
+    for (i, pixel) in output.iter().enumerate() {
+        let (row, column) = (i as i32 / WIDTH, i as i32 % WIDTH);
+        let pixel_position = Vec2::new(column as f32, row as f32);
+        for (channel, channel_scale) in [(0, push.red_scale), (1, 1.)] {
+            let source =
+                source_position(&cropped, HEIGHT, WIDTH, pixel_position, scale, channel_scale);
+            assert!(is_inside(source), "{source} at {i}");
+            let expected = (source.x + 1.) / WIDTH as f32;
+            assert!((pixel[channel] - expected).abs() < 1e-4, "{pixel} at {i}");
+        }
+    }
+}
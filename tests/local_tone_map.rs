@@ -0,0 +1,48 @@
+mod common;
+
+use common::{default_isp_params, device, params, read_rgb};
+use gpwgpu::utils::DebugEncoder;
+use wgpu_isp::{operations::RAW_SCALE, setup::State};
+
+const WIDTH: usize = 128;
+const HEIGHT: usize = 64;
+
+#[test]
+fn equalization_stretches_contrast() {
+    let (device, queue) = device();
+
+    let mut isp_params = default_isp_params();
+    isp_params.local_tone_map_push.enabled = 1;
+    isp_params.local_tone_map_push.tile_size = 1000;
+    isp_params.local_tone_map_push.clip_limit = 1000.;
+
+    // Binned to 64×32, so the two halves stay clean
+    let mut state = State::new(&device, &queue, params(WIDTH, HEIGHT, true)).unwrap();
+
+    let raw = (0..HEIGHT * WIDTH)
+        .map(|i| if i % WIDTH < WIDTH / 2 { 0.2 } else { 0.3 } * RAW_SCALE)
+        .collect::<Vec<_>>();
+
+    let mut run = |strength: f32| {
+        isp_params.local_tone_map_push.strength = strength;
+        state.write_to_input(&raw);
+
+        let mut encoder = DebugEncoder::new(&device);
+        state.execute(&mut encoder, &isp_params);
+        encoder.submit(&queue);
+
+        let output = read_rgb(&state);
+        (output[0].y, output[WIDTH / 2 - 1].y)
+    };
+
+    // No strength leaves the image alone
+    let (left, right) = run(0.);
+    assert!((left - 0.2).abs() < 1e-5, "{left}");
+    assert!((right - 0.3).abs() < 1e-5, "{right}");
+
+    // Without clipping the darker half maps to the middle of the range and
+    // the brighter one to white
+    let (left, right) = run(1.);
+    assert!((left - 0.5f32.powf(2.2)).abs() < 1e-3, "{left}");
+    assert!((right - 1.).abs() < 1e-3, "{right}");
+}
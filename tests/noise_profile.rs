@@ -0,0 +1,94 @@
+use wgpu_isp::noise_profile::{
+    bayer_to_quads, estimate_noise_profile, estimate_noise_profile_from_pair,
+};
+
+const WIDTH: usize = 512;
+const HEIGHT: usize = 256;
+const A: f32 = 2e-3;
+const B: f32 = 1e-5;
+
+struct Gaussian(u64);
+
+impl Gaussian {
+    fn uniform(&mut self) -> f32 {
+        // xorshift64
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        ((self.0 >> 40) as f32 + 0.5) / (1u64 << 24) as f32
+    }
+
+    fn sample(&mut self) -> f32 {
+        let (u1, u2) = (self.uniform(), self.uniform());
+        (-2. * u1.ln()).sqrt() * (2. * std::f32::consts::PI * u2).cos()
+    }
+}
+
+/// A horizontal ramp from dark to bright with Poisson-Gaussian noise.
+fn noisy_ramp(rng: &mut Gaussian) -> Vec<f32> {
+    (0..WIDTH * HEIGHT)
+        .map(|i| {
+            let signal = 0.05 + 0.9 * (i % WIDTH) as f32 / WIDTH as f32;
+            signal + (A * signal + B).sqrt() * rng.sample()
+        })
+        .collect()
+}
+
+fn assert_close(actual: f32, expected: f32, tolerance: f32) {
+    assert!(
+        (actual - expected).abs() <= tolerance * expected,
+        "{actual} is not within {tolerance} of {expected}"
+    );
+}
+
+#[test]
+fn quads_match_bayer_to_vec4_layout() {
+    let frame = (0..16).map(|i| i as f32).collect::<Vec<_>>();
+    let quads = bayer_to_quads(&frame, 4, 4);
+    assert_eq!(
+        quads,
+        vec![
+            [0., 1., 4., 5.],
+            [2., 3., 6., 7.],
+            [8., 9., 12., 13.],
+            [10., 11., 14., 15.],
+        ]
+    );
+}
+
+#[test]
+fn flat_field_ramp() {
+    let mut rng = Gaussian(0x2545f4914f6cdd1d);
+    let frame = noisy_ramp(&mut rng);
+
+    let profile = estimate_noise_profile(&frame, WIDTH, HEIGHT, 8);
+    for channel in profile.channels {
+        assert_close(channel.a, A, 0.1);
+        assert!(channel.b < 10. * B, "b = {} is far above {B}", channel.b);
+    }
+}
+
+#[test]
+fn capture_pair() {
+    let mut rng = Gaussian(0x9e3779b97f4a7c15);
+    let first = noisy_ramp(&mut rng);
+    let second = noisy_ramp(&mut rng);
+
+    let profile = estimate_noise_profile_from_pair(&first, &second, WIDTH, HEIGHT, 8);
+    for channel in profile.channels {
+        assert_close(channel.a, A, 0.1);
+    }
+}
+
+#[test]
+fn dark_frame_only_has_read_noise() {
+    let mut rng = Gaussian(0x853c49e6748fea9b);
+    let frame = (0..WIDTH * HEIGHT)
+        .map(|_| B.sqrt() * rng.sample())
+        .collect::<Vec<_>>();
+
+    let profile = estimate_noise_profile(&frame, WIDTH, HEIGHT, 8);
+    for channel in profile.channels {
+        assert_close(channel.b, B, 0.1);
+    }
+}
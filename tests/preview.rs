@@ -0,0 +1,27 @@
+mod common;
+
+use common::{default_isp_params, device, params, read_rgb};
+use glam::Vec3;
+use gpwgpu::utils::DebugEncoder;
+use wgpu_isp::{operations::RAW_SCALE, setup::State};
+
+#[test]
+fn preview_bins_to_half_resolution() {
+    let (device, queue) = device();
+
+    let isp_params = default_isp_params();
+
+    let mut state = State::new(&device, &queue, params(64, 32, true)).unwrap();
+    state.write_to_input(&vec![RAW_SCALE / 2.; 64 * 32]);
+
+    let mut encoder = DebugEncoder::new(&device);
+    state.execute(&mut encoder, &isp_params);
+    encoder.submit(&queue);
+
+    let output = read_rgb(&state);
+
+    assert_eq!(output.len(), 32 * 16);
+    for pixel in output {
+        assert!(pixel.abs_diff_eq(Vec3::splat(0.5), 1e-5), "{pixel}");
+    }
+}
@@ -6,7 +6,14 @@ use gpwgpu::{
 };
 use std::time::Instant;
 use wgpu_isp::{
-    operations::{AutoWhiteBalancePush, BlackLevelPush, Buffers, DebayerPush, ISPParams, SHADERS},
+    color::CameraCalibration,
+    operations::{
+        AutoExposurePush, AutoWhiteBalancePush, BlackLevelPush, Buffers, ColorAdjustPush,
+        CrosstalkKernels, CurvesPush, DebayerPush, DefringePush, DemosaicAlgorithm, DenoisePush,
+        FalseColorSuppressionPush, GreenEqualizationPush, HighlightRecoveryPush, ISPParams,
+        LensCorrectionPush, LocalToneMapPush, LutPush, OutputTransformPush, SharpenPush,
+        StatisticsPush, TemporalDenoisePush, ToneMapPush, SHADERS,
+    },
     setup::{Params, State},
 };
 
@@ -21,10 +28,14 @@ fn runner() {
         width: 1920,
         height: 1080,
         shader_processor: SHADERS.clone(),
+        preview: false,
     };
 
     let isp_params = ISPParams {
-        debayer_push: DebayerPush { enabled: 1 },
+        debayer_push: DebayerPush {
+            enabled: 1,
+            algorithm: DemosaicAlgorithm::Malvar,
+        },
         black_level_push: BlackLevelPush {
             r_offset: 0.0,
             gr_offset: 0.0,
@@ -33,7 +44,7 @@ fn runner() {
             alpha: 0.0,
             beta: 0.0,
         },
-        auto_white_balance_push: AutoWhiteBalancePush { gain: 1.0 },
+        auto_white_balance_push: AutoWhiteBalancePush::default(),
         gamma_push: wgpu_isp::operations::GammaPush {
             gain: 1.,
             gamma: 1.,
@@ -41,6 +52,26 @@ fn runner() {
         color_correction_push: wgpu_isp::operations::ColorCorrectionPush {
             color_correction_matrix: Mat4::IDENTITY,
         },
+        crosstalk: CrosstalkKernels::default(),
+        green_equalization_push: GreenEqualizationPush::default(),
+        denoise_push: DenoisePush::default(),
+        noise_profile: None,
+        temporal_denoise_push: TemporalDenoisePush::default(),
+        false_color_suppression_push: FalseColorSuppressionPush::default(),
+        camera_calibration: CameraCalibration::default(),
+        statistics_push: StatisticsPush::default(),
+        auto_exposure_push: AutoExposurePush::default(),
+        output_transform_push: OutputTransformPush::default(),
+        tone_map_push: ToneMapPush::default(),
+        local_tone_map_push: LocalToneMapPush::default(),
+        lut_push: LutPush::default(),
+        lut_path: None,
+        curves_push: CurvesPush::default(),
+        color_adjust_push: ColorAdjustPush::default(),
+        sharpen_push: SharpenPush::default(),
+        lens_correction_push: LensCorrectionPush::default(),
+        highlight_recovery_push: HighlightRecoveryPush::default(),
+        defringe_push: DefringePush::default(),
     };
 
     let mut state = State::new(&device, &queue, params).unwrap();
@@ -63,7 +94,7 @@ fn runner() {
         // encoder.set_debug_bundle(make_debug_bundle(&state));
         // encoder.activate();
 
-        state.sequential.execute(&mut encoder, &isp_params);
+        state.execute(&mut encoder, &isp_params);
 
         // encoder.inspect_buffers().unwrap();
 
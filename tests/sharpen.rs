@@ -0,0 +1,109 @@
+mod common;
+
+use common::{bayer, default_isp_params, device, params, read_rgb};
+use glam::Vec3;
+use gpwgpu::utils::DebugEncoder;
+use wgpu_isp::{
+    operations::{SharpenPush, WhiteBalanceMode},
+    setup::State,
+};
+
+const WIDTH: usize = 128;
+const HEIGHT: usize = 64;
+
+const DARK: Vec3 = Vec3::new(0.3, 0.2, 0.1);
+const LIGHT: Vec3 = Vec3::new(0.6, 0.5, 0.4);
+
+fn luma(rgb: Vec3) -> f32 {
+    rgb.dot(Vec3::new(0.2126, 0.7152, 0.0722))
+}
+
+#[test]
+fn sharpens_edges_without_halos() {
+    let (device, queue) = device();
+
+    let mut isp_params = default_isp_params();
+    isp_params.auto_white_balance_push.mode = WhiteBalanceMode::Gains;
+
+    // Binned to 64×32 with a vertical edge in the middle
+    let mut state = State::new(&device, &queue, params(WIDTH, HEIGHT, true)).unwrap();
+
+    let raw = bayer(
+        WIDTH,
+        HEIGHT,
+        |_, column| if column < WIDTH / 2 { DARK } else { LIGHT },
+    );
+
+    let mut run = |push: SharpenPush, gamma: f32| {
+        isp_params.sharpen_push = push;
+        isp_params.gamma_push.gamma = gamma;
+        state.write_to_input(&raw);
+
+        let mut encoder = DebugEncoder::new(&device);
+        state.execute(&mut encoder, &isp_params);
+        encoder.submit(&queue);
+
+        read_rgb(&state)
+    };
+
+    let edge = WIDTH / 4;
+    let unlimited = run(
+        SharpenPush {
+            enabled: 1,
+            amount: 1.,
+            overshoot: 10.,
+            ..Default::default()
+        },
+        1.,
+    );
+    // Darker before the edge and lighter after it, flat areas are untouched
+    assert!(luma(unlimited[edge - 1]) < luma(DARK) - 0.01, "{}", unlimited[edge - 1]);
+    assert!(luma(unlimited[edge]) > luma(LIGHT) + 0.01, "{}", unlimited[edge]);
+    assert!(unlimited[4].abs_diff_eq(DARK, 1e-5), "{}", unlimited[4]);
+    // Only the luma changes
+    for pixel in [unlimited[edge - 1], unlimited[edge]] {
+        assert!((pixel.x - pixel.y - 0.1).abs() < 1e-5, "{pixel}");
+        assert!((pixel.z - pixel.y + 0.1).abs() < 1e-5, "{pixel}");
+    }
+
+    // Without overshoot the edge stays within its two sides
+    let limited = run(
+        SharpenPush {
+            enabled: 1,
+            amount: 1.,
+            overshoot: 0.,
+            ..Default::default()
+        },
+        1.,
+    );
+    assert!(limited[edge - 1].abs_diff_eq(DARK, 1e-5), "{}", limited[edge - 1]);
+    assert!(limited[edge].abs_diff_eq(LIGHT, 1e-5), "{}", limited[edge]);
+
+    // The edge is all below a large threshold
+    let thresholded = run(
+        SharpenPush {
+            enabled: 1,
+            amount: 1.,
+            threshold: 1.,
+            overshoot: 10.,
+            ..Default::default()
+        },
+        1.,
+    );
+    assert!(thresholded[edge - 1].abs_diff_eq(DARK, 1e-5), "{}", thresholded[edge - 1]);
+
+    // Sharpening happens on linear light, the gamma comes after it
+    let encoded = run(
+        SharpenPush {
+            enabled: 1,
+            amount: 1.,
+            overshoot: 10.,
+            ..Default::default()
+        },
+        1. / 2.2,
+    );
+    for i in [edge - 1, edge] {
+        let decoded = encoded[i].powf(2.2);
+        assert!(decoded.abs_diff_eq(unlimited[i], 1e-4), "{decoded} {}", unlimited[i]);
+    }
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use common::{default_isp_params, device, params};
+use gpwgpu::utils::DebugEncoder;
+use wgpu_isp::{
+    operations::{HISTOGRAM_BINS, HISTOGRAM_EV_RANGE, RAW_SCALE},
+    setup::State,
+};
+
+const WIDTH: usize = 128;
+const HEIGHT: usize = 96;
+
+#[test]
+fn zone_statistics() {
+    let (device, queue) = device();
+
+    let mut isp_params = default_isp_params();
+    isp_params.statistics_push.enabled = 1;
+    isp_params.statistics_push.zones_vertical = 3;
+    isp_params.statistics_push.zones_horizontal = 4;
+    isp_params.statistics_push.saturation = 1.;
+
+    // Flat grey, except for the left half, which is a quarter as bright with
+    // clipped red
+    let frame = (0..WIDTH * HEIGHT)
+        .map(|i| {
+            let (row, col) = (i / WIDTH, i % WIDTH);
+            match (col < WIDTH / 2, row % 2 == 0 && col % 2 == 0) {
+                (true, true) => 1.5,
+                (true, false) => 0.125,
+                (false, _) => 0.5,
+            }
+        })
+        .map(|value| value * RAW_SCALE)
+        .collect::<Vec<_>>();
+
+    let mut state = State::new(&device, &queue, params(WIDTH, HEIGHT, false)).unwrap();
+    state.write_to_input(&frame);
+
+    let mut encoder = DebugEncoder::new(&device);
+    state.execute(&mut encoder, &isp_params);
+    encoder.submit(&queue);
+
+    let stats = state.read_stats();
+    assert_eq!((stats.zones_vertical, stats.zones_horizontal), (3, 4));
+    assert_eq!(stats.zones.len(), 12);
+
+    let quads_per_zone = (HEIGHT / 2 / 3 * WIDTH / 2 / 4) as u32;
+    let bin = |luminance: f32| {
+        let (min, max) = HISTOGRAM_EV_RANGE;
+        ((luminance.log2() - min as f32) / (max - min) as f32 * HISTOGRAM_BINS as f32) as usize
+    };
+
+    for row in 0..3 {
+        // Zones 2 and 3 are flat grey
+        for col in 2..4 {
+            let zone = stats.zone(row, col);
+            assert_eq!(zone.count, quads_per_zone);
+            assert_eq!(zone.clipped, [0; 4]);
+            for mean in zone.mean {
+                assert!((mean - 0.5).abs() < 1e-5, "{zone:?}");
+            }
+            assert_eq!(zone.histogram[bin(0.5)], quads_per_zone);
+        }
+
+        let zone = stats.zone(row, 0);
+        assert_eq!(zone.clipped, [quads_per_zone, 0, 0, 0]);
+        assert!((zone.mean[0] - 1.5).abs() < 1e-5, "{zone:?}");
+        assert!((zone.mean[1] - 0.125).abs() < 1e-5, "{zone:?}");
+        assert!(zone.sharpness < 1e-8, "{zone:?}");
+    }
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use common::{assert_close, default_isp_params, device, params};
+use gpwgpu::{
+    automatic_buffers::{AllOperations, Operation},
+    bytemuck,
+    utils::{read_buffer, DebugEncoder},
+};
+use wgpu_isp::operations::{Buffers, ISPParams, TemporalDenoise, TemporalDenoisePush, PT};
+
+const WIDTH: usize = 32;
+const HEIGHT: usize = 16;
+
+/// Standard deviation of the noise model.
+const SIGMA: f32 = 0.01;
+
+#[test]
+fn accumulates_static_frames_and_resets_on_motion() {
+    let (device, queue) = device();
+    let params = params(WIDTH, HEIGHT, false);
+
+    let mut sequential =
+        AllOperations::<PT>::new(&params, vec![Operation::new::<TemporalDenoise>()]).unwrap();
+    sequential.finalize(&device, &params).unwrap();
+
+    let mut isp_params = default_isp_params();
+    isp_params.denoise_push.noise_a = 0.;
+    isp_params.denoise_push.noise_b = SIGMA * SIGMA;
+    isp_params.temporal_denoise_push = TemporalDenoisePush {
+        enabled: 1,
+        max_frames: 100.,
+        motion_threshold: 3.,
+    };
+
+    // Runs a flat frame through, the accumulation works in place
+    let mut run = |level: f32, isp_params: &ISPParams| {
+        let frame = sequential.buffers.get_from_any(Buffers::BlackLevel);
+        queue.write_buffer(frame, 0, bytemuck::cast_slice(&vec![level; WIDTH * HEIGHT]));
+
+        let mut encoder = DebugEncoder::new(&device);
+        sequential.execute(&mut encoder, isp_params);
+        encoder.submit(&queue);
+
+        let frame = sequential.buffers.get_from_any(Buffers::BlackLevel);
+        let output = read_buffer::<f32>(&device, frame, 0, None);
+        assert!(output.iter().all(|value| *value == output[0]), "{output:?}");
+        output[0]
+    };
+
+    // Differences of two sigma are noise, the output is the running mean
+    let levels = [0.51, 0.49, 0.51, 0.49, 0.52];
+    for (i, level) in levels.into_iter().enumerate() {
+        let mean = levels[..=i].iter().sum::<f32>() / (i + 1) as f32;
+        assert_close(run(level, &isp_params), mean, 1e-5);
+    }
+
+    // Thirty sigma is motion, the history starts again from the new frame
+    assert_close(run(0.8, &isp_params), 0.8, 1e-5);
+    assert_close(run(0.82, &isp_params), 0.81, 1e-5);
+
+    // Disabling passes frames through and drops the history
+    let mut disabled = isp_params.clone();
+    disabled.temporal_denoise_push.enabled = 0;
+    assert_close(run(0.78, &disabled), 0.78, 1e-6);
+    assert_close(run(0.8, &isp_params), 0.8, 1e-6);
+
+    // Once two frames are in the history every new one keeps a third of the
+    // weight, instead of ever less
+    isp_params.temporal_denoise_push.max_frames = 2.;
+    assert_close(run(0.81, &isp_params), 0.805, 1e-5);
+    assert_close(run(0.79, &isp_params), 0.8, 1e-5);
+    assert_close(run(0.81, &isp_params), 0.8 + 0.01 / 3., 1e-5);
+}
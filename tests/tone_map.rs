@@ -0,0 +1,89 @@
+mod common;
+
+use common::{assert_close, default_isp_params, device, params, read_rgb};
+use glam::Vec3;
+use gpwgpu::utils::DebugEncoder;
+use wgpu_isp::{
+    operations::{ToneMapOperator, ToneMapPush, RAW_SCALE},
+    setup::State,
+    tone_map::{hable, reinhard_extended, tone_map},
+};
+
+const OPERATORS: [ToneMapOperator; 4] = [
+    ToneMapOperator::Reinhard,
+    ToneMapOperator::Hable,
+    ToneMapOperator::Aces,
+    ToneMapOperator::Agx,
+];
+
+#[test]
+fn white_maps_to_one() {
+    assert_eq!(reinhard_extended(4., 4.), 1.);
+    assert_close(hable(11.2, 11.2), 1., 1e-6);
+
+    for operator in OPERATORS {
+        let push = ToneMapPush {
+            operator,
+            ..Default::default()
+        };
+        let white = tone_map(&push, Vec3::splat(push.white));
+        for channel in white.to_array() {
+            assert_close(channel, 1., 1e-2);
+        }
+        for channel in tone_map(&push, Vec3::ZERO).to_array() {
+            assert_close(channel, 0., 1e-6);
+        }
+    }
+}
+
+#[test]
+fn curves_rise_and_stay_neutral() {
+    for operator in OPERATORS {
+        let push = ToneMapPush {
+            operator,
+            ..Default::default()
+        };
+        let mut previous = 0.;
+        for step in 1..=64 {
+            let grey = tone_map(&push, Vec3::splat(step as f32 / 4.));
+            assert!(grey.x >= previous, "{operator:?} falls at {step}");
+            assert_close(grey.x, grey.y, 1e-3);
+            assert_close(grey.z, grey.y, 1e-3);
+            previous = grey.x;
+        }
+    }
+}
+
+#[test]
+fn exposure_is_in_stops() {
+    let push = ToneMapPush::default();
+    let brighter = ToneMapPush {
+        exposure: 1.,
+        ..push
+    };
+    let input = Vec3::new(0.3, 0.2, 0.1);
+    assert_eq!(tone_map(&brighter, input), tone_map(&push, 2. * input));
+}
+
+#[test]
+fn shader_matches_reference() {
+    let (device, queue) = device();
+
+    let mut isp_params = default_isp_params();
+    isp_params.tone_map_push.enabled = 1;
+    isp_params.tone_map_push.exposure = 3.;
+
+    let mut state = State::new(&device, &queue, params(64, 32, true)).unwrap();
+
+    for operator in OPERATORS {
+        isp_params.tone_map_push.operator = operator;
+        state.write_to_input(&vec![RAW_SCALE / 2.; 64 * 32]);
+
+        let mut encoder = DebugEncoder::new(&device);
+        state.execute(&mut encoder, &isp_params);
+        encoder.submit(&queue);
+
+        let expected = tone_map(&isp_params.tone_map_push, Vec3::splat(0.5));
+        assert_close(read_rgb(&state)[0], expected, 1e-3);
+    }
+}
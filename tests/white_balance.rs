@@ -0,0 +1,151 @@
+mod common;
+
+use common::{default_isp_params, device, params};
+use gpwgpu::utils::{read_buffer, DebugEncoder};
+use wgpu_isp::{
+    operations::{Buffers, ISPParams, WhiteBalanceAlgorithm, WhiteBalanceMode, RAW_SCALE},
+    setup::State,
+};
+
+const WIDTH: usize = 256;
+const HEIGHT: usize = 128;
+
+/// A horizontal ramp with a colour cast: red at half and blue at twice the
+/// level of green. Every white point estimate sees the same cast.
+fn tinted_ramp() -> Vec<f32> {
+    let scale = [0.5, 1., 1., 2.];
+    (0..WIDTH * HEIGHT)
+        .map(|i| {
+            let (row, col) = (i / WIDTH, i % WIDTH);
+            let level = 0.1 + 0.3 * col as f32 / WIDTH as f32;
+            scale[(row % 2) * 2 + col % 2] * level * RAW_SCALE
+        })
+        .collect()
+}
+
+#[test]
+fn algorithms_remove_colour_cast() {
+    let (device, queue) = device();
+
+    let mut isp_params = default_isp_params();
+
+    let mut state = State::new(&device, &queue, params(WIDTH, HEIGHT, false)).unwrap();
+    state.write_to_input(&tinted_ramp());
+
+    for algorithm in [
+        WhiteBalanceAlgorithm::GrayWorld,
+        WhiteBalanceAlgorithm::WhitePatch,
+        WhiteBalanceAlgorithm::Percentile,
+        WhiteBalanceAlgorithm::GrayEdge,
+    ] {
+        isp_params.auto_white_balance_push.algorithm = algorithm;
+
+        let mut encoder = DebugEncoder::new(&device);
+        state.execute(&mut encoder, &isp_params);
+        encoder.submit(&queue);
+
+        let gains_buf = state
+            .sequential
+            .buffers
+            .get_from_any(Buffers::WhiteBalanceGains);
+        let gains = read_buffer::<[f32; 4]>(&device, gains_buf, 0, None)[0];
+
+        let expected = [2., 1., 1., 0.5];
+        for (gain, expected) in gains.iter().zip(expected) {
+            assert!(
+                (gain - expected).abs() < 0.02 * expected,
+                "{algorithm:?}: {gains:?}"
+            );
+        }
+    }
+}
+
+/// The tinted ramp with a clipped red stripe over the left quarter.
+fn ramp_with_clipped_stripe() -> Vec<f32> {
+    let mut frame = tinted_ramp();
+    for row in (0..HEIGHT).step_by(2) {
+        for col in (0..WIDTH / 4).step_by(2) {
+            frame[row * WIDTH + col] = 2. * RAW_SCALE;
+        }
+    }
+    frame
+}
+
+#[test]
+fn masked_quads_are_left_out() {
+    let (device, queue) = device();
+
+    let isp_params = default_isp_params();
+
+    let mut state = State::new(&device, &queue, params(WIDTH, HEIGHT, false)).unwrap();
+    state.write_to_input(&ramp_with_clipped_stripe());
+
+    let mut saturation = isp_params.clone();
+    saturation.auto_white_balance_push.saturation = 1.5;
+
+    let mut roi = isp_params.clone();
+    roi.auto_white_balance_push.roi_left = 0.25;
+
+    for isp_params in [saturation, roi] {
+        let mut encoder = DebugEncoder::new(&device);
+        state.execute(&mut encoder, &isp_params);
+        encoder.submit(&queue);
+
+        assert_eq!(state.awb_quad_count(), (HEIGHT / 2 * WIDTH / 2 * 3 / 4) as u32);
+
+        let gains_buf = state
+            .sequential
+            .buffers
+            .get_from_any(Buffers::WhiteBalanceGains);
+        let gains = read_buffer::<[f32; 4]>(&device, gains_buf, 0, None)[0];
+
+        let expected = [2., 1., 1., 0.5];
+        for (gain, expected) in gains.iter().zip(expected) {
+            assert!((gain - expected).abs() < 0.02 * expected, "{gains:?}");
+        }
+    }
+}
+
+#[test]
+fn smoothing_converges_towards_new_gains() {
+    let (device, queue) = device();
+
+    let mut isp_params = default_isp_params();
+    isp_params.auto_white_balance_push.time_constant = 4.;
+
+    let mut state = State::new(&device, &queue, params(WIDTH, HEIGHT, false)).unwrap();
+
+    let red_gain = |state: &mut State, frame: &[f32], isp_params: &ISPParams| {
+        state.write_to_input(frame);
+        let mut encoder = DebugEncoder::new(&device);
+        state.execute(&mut encoder, isp_params);
+        encoder.submit(&queue);
+
+        let gains_buf = state
+            .sequential
+            .buffers
+            .get_from_any(Buffers::WhiteBalanceGains);
+        read_buffer::<[f32; 4]>(&device, gains_buf, 0, None)[0][0]
+    };
+
+    // The first frame after creation isn't smoothed
+    assert!((red_gain(&mut state, &tinted_ramp(), &isp_params) - 2.).abs() < 0.04);
+
+    let neutral = vec![0.2 * RAW_SCALE; WIDTH * HEIGHT];
+    let alpha = 1. - (-1f32 / 4.).exp();
+    let expected = 2. + (1. - 2.) * alpha;
+    assert!((red_gain(&mut state, &neutral, &isp_params) - expected).abs() < 0.04);
+
+    state.reset_awb_smoothing();
+    assert!((red_gain(&mut state, &neutral, &isp_params) - 1.).abs() < 1e-3);
+
+    // Another algorithm starts from its own estimate
+    isp_params.auto_white_balance_push.algorithm = WhiteBalanceAlgorithm::WhitePatch;
+    assert!((red_gain(&mut state, &tinted_ramp(), &isp_params) - 2.).abs() < 0.04);
+
+    // So does auto mode after the manual gains
+    let mut manual = isp_params.clone();
+    manual.auto_white_balance_push.mode = WhiteBalanceMode::Gains;
+    red_gain(&mut state, &tinted_ramp(), &manual);
+    assert!((red_gain(&mut state, &neutral, &isp_params) - 1.).abs() < 1e-3);
+}
@@ -20,12 +20,23 @@ use viewer::{
     camera2d::{My2dCameraPlugin, My2dController},
     file_watcher::FilesystemWatcher,
     simple_renderer::{ImageSettings, SimpleRendererPlugin, StateImage},
-    ui_form::{BoundedSlider, IntCheckbox, Mat4Slider},
+    ui_form::{
+        BoundedSlider, CurveEditor, DropdownOptions, EnumDropdown, HueBandsEditor, IntCheckbox,
+        IntDrag, Mat4Slider,
+    },
 };
 use wgpu_isp::{
+    color::CameraCalibration,
+    cube::CubeLut,
+    noise_profile::estimate_noise_profile,
     operations::{
-        AutoWhiteBalancePush, BlackLevelPush, ColorCorrectionPush, DebayerPush, GammaPush,
-        ISPParams,
+        AutoExposurePush, AutoWhiteBalancePush, BlackLevelPush, ColorAdjustPush,
+        ColorCorrectionPush, CrosstalkKernels, CurvesPush, DebayerPush, DefringePush,
+        DemosaicAlgorithm, DenoisePush, FalseColorSuppressionPush, GammaPush,
+        GreenEqualizationPush, HighlightMode, HighlightRecoveryPush, ISPParams, LensCorrectionPush,
+        LocalToneMapPush, LutInterpolation, LutPosition, LutPush, MeteringMode, OutputPrimaries,
+        OutputTransformPush, ResamplingFilter, SharpenPush, StatisticsPush, TemporalDenoisePush,
+        ToneMapOperator, ToneMapPush, TransferFunction, WhiteBalanceAlgorithm, WhiteBalanceMode,
     },
     setup::Params,
 };
@@ -93,12 +104,14 @@ struct InputUiState {
     file: Field,
     width: Field,
     height: Field,
+    preview: bool,
 }
 
 #[derive(Component)]
 struct UiComponent {
     file_input: InputUiState,
     in_out_json: Field,
+    lut: Field,
     full_ui: FullUi,
 }
 
@@ -167,35 +180,17 @@ fn watch_for_shader_changes(
                 ..state.state.params
             };
 
-            let new_state = match state.state.reload(params) {
+            let new_state = match state.state.rebuild(params) {
                 Ok(state) => state,
                 Err(e) => {
                     dbg!(e);
                     continue;
                 }
             };
-            let mut encoder = new_state.device.create_command_encoder(&default());
-
-            let old_input = state
-                .state
-                .sequential
-                .buffers
-                .get_from_any(wgpu_isp::operations::Buffers::Raw);
-
-            encoder.copy_buffer_to_buffer(
-                old_input,
-                0,
-                new_state
-                    .sequential
-                    .buffers
-                    .get_from_any(wgpu_isp::operations::Buffers::Raw),
-                0,
-                old_input.size(),
-            );
-
-            new_state.queue.submit(Some(encoder.finish()));
 
+            let cpu_side_data = state.cpu_side_data.take();
             *state = StateImage::new(new_state);
+            state.cpu_side_data = cpu_side_data;
             should_execute.0 = true;
         }
     }
@@ -206,9 +201,12 @@ struct ParamsComponent(ISPParams);
 
 fn setup_scene(mut commands: Commands) {
     let isp_params = ISPParams {
-        debayer_push: DebayerPush { enabled: 1 },
+        debayer_push: DebayerPush {
+            enabled: 1,
+            algorithm: DemosaicAlgorithm::Malvar,
+        },
         black_level_push: BlackLevelPush::default(),
-        auto_white_balance_push: AutoWhiteBalancePush { gain: 1.0 },
+        auto_white_balance_push: AutoWhiteBalancePush::default(),
         gamma_push: GammaPush {
             gain: 1.0,
             gamma: 1.0,
@@ -216,6 +214,26 @@ fn setup_scene(mut commands: Commands) {
         color_correction_push: ColorCorrectionPush {
             color_correction_matrix: Mat4::IDENTITY,
         },
+        crosstalk: CrosstalkKernels::default(),
+        green_equalization_push: GreenEqualizationPush::default(),
+        denoise_push: DenoisePush::default(),
+        noise_profile: None,
+        temporal_denoise_push: TemporalDenoisePush::default(),
+        false_color_suppression_push: FalseColorSuppressionPush::default(),
+        camera_calibration: CameraCalibration::default(),
+        statistics_push: StatisticsPush::default(),
+        auto_exposure_push: AutoExposurePush::default(),
+        output_transform_push: OutputTransformPush::default(),
+        tone_map_push: ToneMapPush::default(),
+        local_tone_map_push: LocalToneMapPush::default(),
+        lut_push: LutPush::default(),
+        lut_path: None,
+        curves_push: CurvesPush::default(),
+        color_adjust_push: ColorAdjustPush::default(),
+        sharpen_push: SharpenPush::default(),
+        lens_correction_push: LensCorrectionPush::default(),
+        highlight_recovery_push: HighlightRecoveryPush::default(),
+        defringe_push: DefringePush::default(),
     };
 
     commands
@@ -243,6 +261,10 @@ fn setup_scene(mut commands: Commands) {
         UiComponent {
             full_ui: FullUi::new(&mut id_provider),
             in_out_json: Field::default(),
+            lut: Field {
+                id: id_provider(),
+                ..Default::default()
+            },
             file_input: InputUiState {
                 file: Field {
                     content: r"C:\Users\andre\Downloads\MPV-cam1-left.raw".to_string(),
@@ -259,6 +281,7 @@ fn setup_scene(mut commands: Commands) {
                     err: None,
                     id: id_provider(),
                 },
+                preview: false,
             },
         },
     ));
@@ -272,10 +295,18 @@ enum FrameChange {
     NotRequired,
     NewInput,
     Reload,
+    Preview,
 }
 
-fn re_execute(mut query: Query<(&ParamsComponent, &mut ShouldExecute, &mut StateImage)>) {
-    for (params, mut should_execute, mut state) in &mut query {
+fn re_execute(
+    mut query: Query<(
+        &mut ParamsComponent,
+        &mut UiComponent,
+        &mut ShouldExecute,
+        &mut StateImage,
+    )>,
+) {
+    for (mut params, mut ui_state, mut should_execute, mut state) in &mut query {
         if !should_execute.0 {
             continue;
         }
@@ -283,7 +314,22 @@ fn re_execute(mut query: Query<(&ParamsComponent, &mut ShouldExecute, &mut State
 
         let mut encoder = DebugEncoder::new(&state.device);
 
-        state.sequential.execute(&mut encoder, &params.0);
+        // The LUT of parameters loaded from json. One that fails to load is
+        // dropped, so it isn't retried every frame, and shown in the LUT line.
+        if let Some(path) = params.0.lut_path.clone() {
+            if state.lut_path() != Some(path.as_path()) {
+                if let Err(e) = state.load_lut(&path) {
+                    ui_state.lut.content = path.display().to_string();
+                    ui_state.lut.err = Some(e.into());
+                    params.0.lut_path = None;
+                }
+            }
+        }
+
+        // The sRGB surface of the window does the encoding
+        let mut isp_params = params.0.clone();
+        isp_params.output_transform_push.transfer = TransferFunction::Linear;
+        state.execute(&mut encoder, &isp_params);
 
         state.to_texture.execute(&mut encoder, &[]);
 
@@ -354,6 +400,79 @@ fn json_line(
     });
 }
 
+fn noise_profile_line(
+    ui: &mut Ui,
+    params: &mut Mut<ParamsComponent>,
+    should_execute: &mut Mut<ShouldExecute>,
+    state_image: Option<&StateImage>,
+) {
+    ui.label("Noise profile");
+
+    // The black level buffer holds the temporal average while that is on
+    let temporal = params.0.temporal_denoise_push.enabled != 0;
+    let state = state_image
+        .filter(|image| image.cpu_side_data.is_some() && !temporal)
+        .map(|image| &image.state);
+
+    ui.horizontal(|ui| {
+        let estimate = ui
+            .add_enabled(state.is_some(), egui::Button::new("Estimate from flat field"))
+            .on_hover_text(
+                "Fits the noise of the loaded frame, which has to be a flat-field or dark \
+                 capture. Scene texture counts as noise. Needs temporal denoising off.",
+            );
+        if estimate.clicked() {
+            // The button is only enabled when a frame has been processed
+            let state = state.unwrap();
+            // Black levelled and crosstalk corrected, as Denoise sees it
+            let frame = state.read_black_level();
+            let (width, height) = (state.params.width as usize, state.params.height as usize);
+            params.0.noise_profile = Some(estimate_noise_profile(&frame, width, height, 16));
+            should_execute.0 |= true;
+        }
+        if ui.button("Clear").clicked() {
+            params.0.noise_profile = None;
+            should_execute.0 |= true;
+        }
+    });
+
+    if let Some(profile) = &params.0.noise_profile {
+        for (name, channel) in ["R", "Gr", "Gb", "B"].iter().zip(profile.channels) {
+            ui.label(format!(
+                "{name}: a = {:.3e}, b = {:.3e}",
+                channel.a, channel.b
+            ));
+        }
+    }
+}
+
+fn lut_line(
+    ui: &mut Ui,
+    ui_state: &mut Mut<UiComponent>,
+    params: &mut Mut<ParamsComponent>,
+    should_execute: &mut Mut<ShouldExecute>,
+) {
+    ui.label("3D LUT (.cube)");
+
+    ui_state.lut.single_line(ui);
+    if let Some(err) = &ui_state.lut.err {
+        ui.label(&err.0);
+    }
+    ui.horizontal(|ui| {
+        if ui.button("Load").clicked() {
+            // Checked here so the error can be shown, re_execute uploads it
+            if ui_state.lut.parse(CubeLut::load).is_some() {
+                params.0.lut_path = Some(ui_state.lut.content.clone().into());
+                should_execute.0 |= true;
+            }
+        }
+        if ui.button("Clear").clicked() {
+            params.0.lut_path = None;
+            should_execute.0 |= true;
+        }
+    });
+}
+
 fn input_line(ui: &mut Ui, new_input: &mut Mut<FrameChange>, ui_state: &mut Mut<UiComponent>) {
     ui.label("Enter a file input:");
     let mut set_new_input = || **new_input = FrameChange::NewInput;
@@ -370,6 +489,13 @@ fn input_line(ui: &mut Ui, new_input: &mut Mut<FrameChange>, ui_state: &mut Mut<
         .file_input
         .height
         .run_on_changed(ui, &mut set_new_input);
+
+    if ui
+        .checkbox(&mut ui_state.file_input.preview, "Half resolution preview")
+        .changed()
+    {
+        **new_input = FrameChange::Preview;
+    }
 }
 
 fn new_input(
@@ -421,6 +547,7 @@ fn new_input(
                     width,
                     height,
                     shader_processor,
+                    preview: ui_component.file_input.preview,
                 };
 
                 let image_settings = ImageSettings {
@@ -468,6 +595,33 @@ fn new_input(
                 let state = state_image.as_ref().unwrap();
                 state.state.write_to_input(&data);
             }
+            FrameChange::Preview => {
+                // Nothing is loaded yet, the next input picks the setting up
+                let Some(mut state_image) = state_image else {
+                    *new_input = FrameChange::NotRequired;
+                    continue;
+                };
+
+                let params = Params {
+                    preview: ui_component.file_input.preview,
+                    ..state_image.state.params.clone()
+                };
+
+                let new_state = match state_image.state.rebuild(params) {
+                    Ok(state) => state,
+                    Err(e) => {
+                        ui_component.file_input.file.err = Some(e.into());
+                        *new_input = FrameChange::NotRequired;
+                        continue;
+                    }
+                };
+
+                let cpu_side_data = state_image.cpu_side_data.take();
+                *state_image = StateImage::new(new_state);
+                state_image.cpu_side_data = cpu_side_data;
+
+                should_execute.as_mut().unwrap().0 = true;
+            }
         }
 
         *new_input = FrameChange::NotRequired;
@@ -481,17 +635,35 @@ fn ui(
         &mut ShouldExecute,
         &mut UiComponent,
         &mut FrameChange,
+        Option<&StateImage>,
     )>,
 ) {
     let ctx = egui_contexts.ctx_mut();
 
     egui::SidePanel::left("primary_panel").show(ctx, |ui| {
         egui::ScrollArea::vertical().show(ui, |ui| {
-            for (mut params, mut should_execute, mut ui_state, mut new_input) in &mut query {
+            for (mut params, mut should_execute, mut ui_state, mut new_input, state_image) in
+                &mut query
+            {
                 input_line(ui, &mut new_input, &mut ui_state);
 
                 json_line(ui, &mut ui_state, &mut params, &mut should_execute);
 
+                noise_profile_line(ui, &mut params, &mut should_execute, state_image);
+
+                lut_line(ui, &mut ui_state, &mut params, &mut should_execute);
+
+                if let Some(state_image) = state_image {
+                    if ui.button("Reset temporal history").clicked() {
+                        state_image.state.reset_temporal_history();
+                        should_execute.0 |= true;
+                    }
+                    if ui.button("Reset AWB smoothing").clicked() {
+                        state_image.state.reset_awb_smoothing();
+                        should_execute.0 |= true;
+                    }
+                }
+
                 should_execute.0 |= ui_state.full_ui.show(ui, &mut params.0);
             }
         })
@@ -1,5 +1,9 @@
-use bevy_egui::egui::{self, TextEdit, Ui};
+use bevy_egui::egui::{self, Color32, Sense, Stroke, TextEdit, Ui};
 use glam::Mat4;
+use wgpu_isp::{
+    color_adjust::{HueBands, HUE_BANDS, HUE_BAND_NAMES},
+    curves::ToneCurve,
+};
 
 pub struct BoundedSlider {
     pub name: String,
@@ -129,3 +133,215 @@ impl IntCheckbox {
         out
     }
 }
+
+pub struct IntDrag {
+    pub name: &'static str,
+}
+
+impl IntDrag {
+    pub fn show(&mut self, ui: &mut Ui, value: &mut u32) -> bool {
+        ui.horizontal(|ui| {
+            ui.label(self.name);
+            ui.add(egui::DragValue::new(value)).changed()
+        })
+        .inner
+    }
+}
+
+/// Implemented by `generate_ui_impl!` for enums marked with `UiMarker`.
+pub trait DropdownOptions: Copy + PartialEq + 'static {
+    fn options() -> &'static [(Self, &'static str)];
+}
+
+pub struct EnumDropdown {
+    pub name: &'static str,
+    egui_id: usize,
+}
+
+impl EnumDropdown {
+    pub fn new(name: &'static str, egui_id: usize) -> Self {
+        Self { name, egui_id }
+    }
+
+    pub fn show<T: DropdownOptions>(&mut self, ui: &mut Ui, value: &mut T) -> bool {
+        let selected = T::options()
+            .iter()
+            .find(|(option, _)| option == value)
+            .map_or("", |(_, label)| label);
+
+        let mut changed = false;
+        egui::ComboBox::new(self.egui_id, self.name)
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                for (option, label) in T::options() {
+                    changed |= ui.selectable_value(value, *option, *label).changed();
+                }
+            });
+        changed
+    }
+}
+
+/// Control points of a [`ToneCurve`] on a unit square. Drag a point to move
+/// it, double click to add one and right click a point to remove it. The
+/// first and last point can't be removed.
+pub struct CurveEditor {
+    pub name: &'static str,
+    egui_id: usize,
+    dragged: Option<usize>,
+}
+
+impl CurveEditor {
+    const SIZE: f32 = 200.;
+    const POINT_RADIUS: f32 = 4.;
+
+    pub fn new(name: &'static str, egui_id: usize) -> Self {
+        Self {
+            name,
+            egui_id,
+            dragged: None,
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut Ui, value: &mut ToneCurve) -> bool {
+        ui.label(self.name);
+        let (response, painter) =
+            ui.allocate_painter(egui::Vec2::splat(Self::SIZE), Sense::click_and_drag());
+        let rect = response.rect;
+
+        let to_screen = |[x, y]: [f32; 2]| {
+            egui::pos2(
+                rect.left() + x * rect.width(),
+                rect.bottom() - y * rect.height(),
+            )
+        };
+        let from_screen = |pos: egui::Pos2| {
+            [
+                ((pos.x - rect.left()) / rect.width()).clamp(0., 1.),
+                ((rect.bottom() - pos.y) / rect.height()).clamp(0., 1.),
+            ]
+        };
+        let hovered_point = |pos: egui::Pos2, points: &[[f32; 2]]| {
+            points
+                .iter()
+                .position(|&point| to_screen(point).distance(pos) <= 2. * Self::POINT_RADIUS)
+        };
+
+        let mut changed = false;
+        let pointer = response.interact_pointer_pos();
+
+        if response.drag_started() {
+            self.dragged = pointer.and_then(|pos| hovered_point(pos, &value.points));
+        }
+        if !response.dragged() {
+            self.dragged = None;
+        }
+        if let (Some(index), Some(pos)) = (self.dragged, pointer) {
+            value.points[index] = from_screen(pos);
+            changed = true;
+        }
+
+        if response.double_clicked() {
+            if let Some(pos) = pointer {
+                value.points.push(from_screen(pos));
+                changed = true;
+            }
+        }
+
+        if response.secondary_clicked() {
+            if let Some(index) = pointer.and_then(|pos| hovered_point(pos, &value.points)) {
+                let mut order = (0..value.points.len()).collect::<Vec<_>>();
+                order.sort_by(|&a, &b| value.points[a][0].total_cmp(&value.points[b][0]));
+                let is_end = index == order[0] || index == order[order.len() - 1];
+                if !is_end {
+                    value.points.remove(index);
+                    changed = true;
+                }
+            }
+        }
+
+        let grid = Stroke::new(1., Color32::DARK_GRAY);
+        painter.rect_stroke(rect, 0., grid);
+        for i in 1..4 {
+            let t = i as f32 / 4.;
+            painter.line_segment([to_screen([t, 0.]), to_screen([t, 1.])], grid);
+            painter.line_segment([to_screen([0., t]), to_screen([1., t])], grid);
+        }
+
+        let samples = 64;
+        let curve = (0..=samples)
+            .map(|i| {
+                let x = i as f32 / samples as f32;
+                to_screen([x, value.evaluate(x).clamp(0., 1.)])
+            })
+            .collect::<Vec<_>>();
+        painter.add(egui::Shape::line(curve, Stroke::new(2., Color32::LIGHT_GRAY)));
+
+        for &point in &value.points {
+            painter.circle_filled(to_screen(point), Self::POINT_RADIUS, Color32::WHITE);
+        }
+
+        ui.push_id(self.egui_id, |ui| {
+            if ui.button("Reset").clicked() {
+                *value = ToneCurve::default();
+                changed = true;
+            }
+        });
+
+        changed
+    }
+}
+
+/// Hue, saturation and lightness offsets of every band of [`HueBands`], one
+/// row per band.
+pub struct HueBandsEditor {
+    pub name: &'static str,
+    egui_id: usize,
+}
+
+impl HueBandsEditor {
+    pub fn new(name: &'static str, egui_id: usize) -> Self {
+        Self { name, egui_id }
+    }
+
+    pub fn show(&mut self, ui: &mut Ui, value: &mut HueBands) -> bool {
+        ui.label(self.name);
+        let mut changed = false;
+        egui::Grid::new(self.egui_id).show(ui, |ui| {
+            ui.label("");
+            ui.label("Hue");
+            ui.label("Saturation");
+            ui.label("Lightness");
+            ui.end_row();
+
+            for band in 0..HUE_BANDS {
+                ui.label(HUE_BAND_NAMES[band]);
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut value.hue[band])
+                            .speed(0.5)
+                            .clamp_range(-180. ..=180.),
+                    )
+                    .changed();
+                for offset in [&mut value.saturation[band], &mut value.lightness[band]] {
+                    changed |= ui
+                        .add(
+                            egui::DragValue::new(offset)
+                                .speed(0.01)
+                                .clamp_range(-1. ..=1.),
+                        )
+                        .changed();
+                }
+                ui.end_row();
+            }
+        });
+
+        ui.push_id(self.egui_id, |ui| {
+            if ui.button("Reset").clicked() {
+                *value = HueBands::default();
+                changed = true;
+            }
+        });
+
+        changed
+    }
+}